@@ -0,0 +1,495 @@
+//! Which buttons perform each of the player's actions.
+//!
+//! The defaults can be overridden by a `controls.txt` file in [`Storage`], as
+//! `action = buttons` lines, where `buttons` is a space separated list of the
+//! buttons bound to the action, or empty to unbind it. Blank lines and lines
+//! starting with `#` are ignored, and actions which aren't listed keep their
+//! default buttons. The file also holds the [`GamepadSettings`], as
+//! `gamepad_dead_zone` and `gamepad_look_sensitivity` lines.
+//!
+//! Keys are named by their position on a US keyboard, using the names of
+//! [`KeyCode`] such as `KeyW` or `ShiftLeft`. Mouse buttons are named
+//! `MouseLeft`, `MouseRight`, `MouseMiddle`, `MouseBack` and `MouseForward`,
+//! and gamepad buttons are named after [`gilrs::Button`] with a `Gamepad`
+//! prefix, such as `GamepadSouth` or `GamepadRightTrigger2`.
+
+use crate::gamepad::GamepadSettings;
+use crate::input::{Button, InputState};
+use crate::platform::{Storage, info, warn};
+use crate::world::inventory::HOTBAR_SLOTS;
+use anyhow::{Context, Result, ensure};
+use std::collections::HashMap;
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+const CONTROLS_KEY: &str = "controls.txt";
+
+/// Something the player can do by pressing a button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+  MoveForward,
+  MoveBackward,
+  MoveLeft,
+  MoveRight,
+  /// Jump while walking, or fly up while flying.
+  Jump,
+  /// Fly down while flying.
+  Descend,
+  BreakBlock,
+  PlaceBlock,
+  /// Select the hotbar slot with the given index.
+  SelectSlot(usize),
+  NextSlot,
+  PreviousSlot,
+  ToggleMovementMode,
+  ToggleDebugDisplay,
+  /// Skip an hour forward in a local world while the debug display is shown.
+  SkipTime,
+  LookBehind,
+  ToggleFullscreen,
+  Quit,
+}
+
+/// Every action with its name in the controls file, in the order they're
+/// written.
+const ACTIONS: &[(&str, Action)] = &[
+  ("move_forward", Action::MoveForward),
+  ("move_backward", Action::MoveBackward),
+  ("move_left", Action::MoveLeft),
+  ("move_right", Action::MoveRight),
+  ("jump", Action::Jump),
+  ("descend", Action::Descend),
+  ("break_block", Action::BreakBlock),
+  ("place_block", Action::PlaceBlock),
+  ("select_slot_1", Action::SelectSlot(0)),
+  ("select_slot_2", Action::SelectSlot(1)),
+  ("select_slot_3", Action::SelectSlot(2)),
+  ("select_slot_4", Action::SelectSlot(3)),
+  ("select_slot_5", Action::SelectSlot(4)),
+  ("select_slot_6", Action::SelectSlot(5)),
+  ("select_slot_7", Action::SelectSlot(6)),
+  ("select_slot_8", Action::SelectSlot(7)),
+  ("select_slot_9", Action::SelectSlot(8)),
+  ("next_slot", Action::NextSlot),
+  ("previous_slot", Action::PreviousSlot),
+  ("toggle_movement_mode", Action::ToggleMovementMode),
+  ("toggle_debug_display", Action::ToggleDebugDisplay),
+  ("skip_time", Action::SkipTime),
+  ("look_behind", Action::LookBehind),
+  ("toggle_fullscreen", Action::ToggleFullscreen),
+  ("quit", Action::Quit),
+];
+
+const DEAD_ZONE_KEY: &str = "gamepad_dead_zone";
+const LOOK_SENSITIVITY_KEY: &str = "gamepad_look_sensitivity";
+
+/// Pairs each of the given variants of `$type` with its name, which is the
+/// variant's name after `$prefix`.
+macro_rules! named_variants {
+  ($prefix:literal, $type:path: $($name:ident),* $(,)?) => {{
+    use $type as Type;
+    &[$((concat!($prefix, stringify!($name)), Type::$name)),*]
+  }};
+}
+
+const MOUSE_BUTTONS: &[(&str, MouseButton)] =
+  named_variants!["Mouse", MouseButton: Left, Right, Middle, Back, Forward];
+
+const GAMEPAD_BUTTONS: &[(&str, gilrs::Button)] = named_variants![
+  "Gamepad", gilrs::Button: South, East, North, West, C, Z, LeftTrigger, LeftTrigger2,
+  RightTrigger, RightTrigger2, Select, Start, Mode, LeftThumb, RightThumb, DPadUp, DPadDown,
+  DPadLeft, DPadRight,
+];
+
+/// The keys which can be bound.
+const KEY_CODES: &[(&str, KeyCode)] = named_variants!["", KeyCode:
+  KeyA,
+  KeyB,
+  KeyC,
+  KeyD,
+  KeyE,
+  KeyF,
+  KeyG,
+  KeyH,
+  KeyI,
+  KeyJ,
+  KeyK,
+  KeyL,
+  KeyM,
+  KeyN,
+  KeyO,
+  KeyP,
+  KeyQ,
+  KeyR,
+  KeyS,
+  KeyT,
+  KeyU,
+  KeyV,
+  KeyW,
+  KeyX,
+  KeyY,
+  KeyZ,
+  Digit0,
+  Digit1,
+  Digit2,
+  Digit3,
+  Digit4,
+  Digit5,
+  Digit6,
+  Digit7,
+  Digit8,
+  Digit9,
+  F1,
+  F2,
+  F3,
+  F4,
+  F5,
+  F6,
+  F7,
+  F8,
+  F9,
+  F10,
+  F11,
+  F12,
+  Space,
+  Enter,
+  Escape,
+  Tab,
+  Backspace,
+  CapsLock,
+  ShiftLeft,
+  ShiftRight,
+  ControlLeft,
+  ControlRight,
+  AltLeft,
+  AltRight,
+  ArrowUp,
+  ArrowDown,
+  ArrowLeft,
+  ArrowRight,
+  Insert,
+  Delete,
+  Home,
+  End,
+  PageUp,
+  PageDown,
+  Minus,
+  Equal,
+  BracketLeft,
+  BracketRight,
+  Backslash,
+  Semicolon,
+  Quote,
+  Backquote,
+  Comma,
+  Period,
+  Slash,
+  IntlBackslash,
+  Numpad0,
+  Numpad1,
+  Numpad2,
+  Numpad3,
+  Numpad4,
+  Numpad5,
+  Numpad6,
+  Numpad7,
+  Numpad8,
+  Numpad9,
+  NumpadAdd,
+  NumpadSubtract,
+  NumpadMultiply,
+  NumpadDivide,
+  NumpadDecimal,
+  NumpadEnter,
+];
+
+fn find_by_name<T: Copy>(variants: &[(&str, T)], name: &str) -> Option<T> {
+  variants
+    .iter()
+    .find(|&&(variant_name, _)| variant_name == name)
+    .map(|&(_, variant)| variant)
+}
+
+fn find_name<T: PartialEq>(variants: &[(&'static str, T)], variant: &T) -> Option<&'static str> {
+  variants
+    .iter()
+    .find(|(_, named)| named == variant)
+    .map(|&(name, _)| name)
+}
+
+fn parse_button(name: &str) -> Option<Button> {
+  find_by_name(KEY_CODES, name)
+    .map(Button::Key)
+    .or_else(|| find_by_name(MOUSE_BUTTONS, name).map(Button::Mouse))
+    .or_else(|| find_by_name(GAMEPAD_BUTTONS, name).map(Button::Gamepad))
+}
+
+fn button_name(button: Button) -> Option<&'static str> {
+  match button {
+    Button::Key(code) => find_name(KEY_CODES, &code),
+    Button::Mouse(button) => find_name(MOUSE_BUTTONS, &button),
+    Button::Gamepad(button) => find_name(GAMEPAD_BUTTONS, &button),
+  }
+}
+
+/// The buttons bound to each action, and how gamepad sticks respond.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+  buttons: HashMap<Action, Vec<Button>>,
+  gamepad: GamepadSettings,
+}
+
+impl Default for Bindings {
+  fn default() -> Self {
+    use Button::{Gamepad, Key, Mouse};
+
+    const SLOT_KEYS: [KeyCode; HOTBAR_SLOTS] = [
+      KeyCode::Digit1,
+      KeyCode::Digit2,
+      KeyCode::Digit3,
+      KeyCode::Digit4,
+      KeyCode::Digit5,
+      KeyCode::Digit6,
+      KeyCode::Digit7,
+      KeyCode::Digit8,
+      KeyCode::Digit9,
+    ];
+
+    let mut buttons = HashMap::from([
+      (Action::MoveForward, vec![Key(KeyCode::KeyW)]),
+      (Action::MoveBackward, vec![Key(KeyCode::KeyS)]),
+      (Action::MoveLeft, vec![Key(KeyCode::KeyA)]),
+      (Action::MoveRight, vec![Key(KeyCode::KeyD)]),
+      (
+        Action::Jump,
+        vec![Key(KeyCode::Space), Gamepad(gilrs::Button::South)],
+      ),
+      (
+        Action::Descend,
+        vec![Key(KeyCode::ShiftLeft), Gamepad(gilrs::Button::East)],
+      ),
+      (
+        Action::BreakBlock,
+        vec![
+          Mouse(MouseButton::Left),
+          Gamepad(gilrs::Button::RightTrigger2),
+        ],
+      ),
+      (
+        Action::PlaceBlock,
+        vec![
+          Mouse(MouseButton::Right),
+          Gamepad(gilrs::Button::LeftTrigger2),
+        ],
+      ),
+      (Action::NextSlot, vec![Gamepad(gilrs::Button::RightTrigger)]),
+      (
+        Action::PreviousSlot,
+        vec![Gamepad(gilrs::Button::LeftTrigger)],
+      ),
+      (
+        Action::ToggleMovementMode,
+        vec![Key(KeyCode::KeyF), Gamepad(gilrs::Button::North)],
+      ),
+      (Action::ToggleDebugDisplay, vec![Key(KeyCode::F3)]),
+      (Action::SkipTime, vec![Key(KeyCode::F4)]),
+      (Action::LookBehind, vec![Key(KeyCode::KeyC)]),
+      (Action::ToggleFullscreen, vec![Key(KeyCode::F11)]),
+      (Action::Quit, vec![Key(KeyCode::Escape)]),
+    ]);
+    for (slot, code) in SLOT_KEYS.into_iter().enumerate() {
+      buttons.insert(Action::SelectSlot(slot), vec![Key(code)]);
+    }
+
+    Self {
+      buttons,
+      gamepad: GamepadSettings::default(),
+    }
+  }
+}
+
+impl Bindings {
+  /// Loads the bindings from the controls file, writing one with the default
+  /// bindings for the player to edit if there isn't one yet.
+  ///
+  /// Falls back to the defaults if the file can't be read.
+  pub fn load(storage: &Storage) -> Self {
+    let result = storage
+      .read(CONTROLS_KEY)
+      .and_then(|controls| match controls {
+        Some(controls) => Self::decode(&String::from_utf8(controls)?),
+        None => {
+          info!("Writing the default controls to {}", CONTROLS_KEY);
+          let bindings = Self::default();
+          storage.write(CONTROLS_KEY, bindings.encode().as_bytes())?;
+
+          Ok(bindings)
+        }
+      });
+
+    result.unwrap_or_else(|err| {
+      warn!(
+        "Using the default controls, as {} could not be loaded: {:#}",
+        CONTROLS_KEY, err
+      );
+      Self::default()
+    })
+  }
+
+  pub fn encode(&self) -> String {
+    let mut text = String::from("# Opencraft controls\n");
+
+    for &(name, action) in ACTIONS {
+      let buttons: Vec<_> = self
+        .buttons(action)
+        .iter()
+        .filter_map(|&button| button_name(button))
+        .collect();
+      text += &format!("{} = {}\n", name, buttons.join(" "));
+    }
+
+    text += &format!(
+      "{} = {}\n{} = {}\n",
+      DEAD_ZONE_KEY, self.gamepad.dead_zone, LOOK_SENSITIVITY_KEY, self.gamepad.look_sensitivity
+    );
+
+    text
+  }
+
+  /// Decodes a controls file, overriding the default bindings of the actions it
+  /// lists.
+  pub fn decode(text: &str) -> Result<Self> {
+    let mut bindings = Self::default();
+
+    for line in text.lines().map(str::trim) {
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+
+      let (name, value) = line
+        .split_once('=')
+        .with_context(|| format!("controls line has no '=': {line:?}"))?;
+      let (name, value) = (name.trim(), value.trim());
+
+      match name {
+        DEAD_ZONE_KEY => {
+          let dead_zone = value.parse()?;
+          ensure!(
+            (0.0..1.0).contains(&dead_zone),
+            "gamepad dead zone {dead_zone} is not between 0 and 1"
+          );
+          bindings.gamepad.dead_zone = dead_zone;
+          continue;
+        }
+        LOOK_SENSITIVITY_KEY => {
+          bindings.gamepad.look_sensitivity = value.parse()?;
+          continue;
+        }
+        _ => {}
+      }
+
+      let action =
+        find_by_name(ACTIONS, name).with_context(|| format!("unknown action {name:?}"))?;
+
+      let buttons = value
+        .split_whitespace()
+        .map(|button| parse_button(button).with_context(|| format!("unknown button {button:?}")))
+        .collect::<Result<_>>()?;
+      bindings.buttons.insert(action, buttons);
+    }
+
+    Ok(bindings)
+  }
+
+  pub fn gamepad(&self) -> &GamepadSettings {
+    &self.gamepad
+  }
+
+  pub fn buttons(&self, action: Action) -> &[Button] {
+    self.buttons.get(&action).map_or(&[], Vec::as_slice)
+  }
+
+  /// Returns whether pressing `button` performs `action`.
+  pub fn is_bound(&self, action: Action, button: Button) -> bool {
+    self.buttons(action).contains(&button)
+  }
+
+  /// Returns whether any button bound to `action` is held.
+  pub fn is_held(&self, input: &InputState, action: Action) -> bool {
+    self
+      .buttons(action)
+      .iter()
+      .any(|&button| input.is_held(button))
+  }
+
+  /// Returns whether any button bound to `action` is held, or was tapped since
+  /// the input's transitions were cleared.
+  pub fn is_down(&self, input: &InputState, action: Action) -> bool {
+    self
+      .buttons(action)
+      .iter()
+      .any(|&button| input.is_down(button))
+  }
+
+  pub fn was_pressed(&self, input: &InputState, action: Action) -> bool {
+    self
+      .buttons(action)
+      .iter()
+      .any(|&button| input.was_pressed(button))
+  }
+
+  pub fn was_released(&self, input: &InputState, action: Action) -> bool {
+    self
+      .buttons(action)
+      .iter()
+      .any(|&button| input.was_released(button))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Check that the default bindings survive being written and read back.
+  #[test]
+  fn test_encode_decode_defaults() {
+    let bindings = Bindings::default();
+
+    assert_eq!(bindings, Bindings::decode(&bindings.encode()).unwrap());
+  }
+
+  // Check that listed actions are rebound or unbound, and the rest keep their
+  // defaults.
+  #[test]
+  fn test_decode_overrides() {
+    let bindings = Bindings::decode(
+      "# Also move with the arrow keys\n\
+       move_forward = KeyW ArrowUp\n\
+       move_left=ArrowLeft\n\
+       \n\
+       look_behind =\n\
+       gamepad_dead_zone = 0.25\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+      [Button::Key(KeyCode::KeyW), Button::Key(KeyCode::ArrowUp)],
+      bindings.buttons(Action::MoveForward)
+    );
+    assert_eq!(
+      [Button::Key(KeyCode::ArrowLeft)],
+      bindings.buttons(Action::MoveLeft)
+    );
+    assert!(bindings.buttons(Action::LookBehind).is_empty());
+    assert_eq!(
+      Bindings::default().buttons(Action::Jump),
+      bindings.buttons(Action::Jump)
+    );
+
+    assert_eq!(0.25, bindings.gamepad().dead_zone);
+
+    assert!(Bindings::decode("fly = Space").is_err());
+    assert!(Bindings::decode("gamepad_dead_zone = 1.5").is_err());
+    assert!(Bindings::decode("jump = Spacebar").is_err());
+  }
+}
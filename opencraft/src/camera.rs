@@ -17,14 +17,26 @@ pub struct Camera {
 }
 
 impl Camera {
-  pub fn new() -> Self {
-    Self::default()
+  pub fn new(position: Vec3, yaw: Angle, pitch: Angle) -> Self {
+    Self {
+      position,
+      yaw,
+      pitch,
+    }
   }
 
   pub fn position(&self) -> Vec3 {
     self.position
   }
 
+  pub fn yaw(&self) -> Angle {
+    self.yaw
+  }
+
+  pub fn pitch(&self) -> Angle {
+    self.pitch
+  }
+
   fn rotor_yaw(&self) -> Rotor3 {
     Rotor3::angle_plane(self.yaw, ZX_PLANE)
   }
@@ -41,8 +53,10 @@ impl Camera {
     self.rotor().rotate(Z_AXIS)
   }
 
-  pub fn translate(&mut self, offset: Vec3) {
-    self.position += self.rotor_yaw().rotate(offset);
+  /// Rotates a direction relative to where the camera is facing horizontally
+  /// into world space, ignoring the camera's pitch.
+  pub fn horizontal_to_world(&self, direction: Vec3) -> Vec3 {
+    self.rotor_yaw().rotate(direction)
   }
 
   pub fn rotate(&mut self, yaw: Angle, pitch: Angle) {
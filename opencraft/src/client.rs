@@ -0,0 +1,276 @@
+use crate::bindings::Action;
+use crate::core::math::vec2::Vec2;
+use crate::core::type_conversions::CoerceLossy;
+use crate::game::Game;
+use crate::input::Button;
+use crate::platform;
+use crate::platform::error;
+use crate::windowing::cursor_lock::CursorLock;
+use crate::windowing::fullscreen;
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use winit::application::ApplicationHandler;
+use winit::dpi::PhysicalPosition;
+use winit::event::{DeviceEvent, DeviceId, ElementState, KeyEvent, MouseScrollDelta, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop, EventLoopClosed, EventLoopProxy};
+use winit::keyboard::PhysicalKey;
+use winit::window::{Window, WindowId};
+
+/// Pixels scrolled by touchpads which count as scrolling one line.
+const PIXELS_PER_LINE: f64 = 40.0;
+
+pub fn start() -> Result<()> {
+  platform::init_logging();
+
+  let event_loop = EventLoop::with_user_event().build()?;
+
+  let mut app = App::new(event_loop.create_proxy());
+  event_loop.run_app(&mut app)?;
+
+  Ok(())
+}
+
+struct AppState {
+  window: Arc<Window>,
+  game: Game,
+}
+
+enum AppEvent {
+  SpinWaitWindowInit(Arc<Window>),
+  Init(Box<AppState>),
+}
+
+struct UnwrapApp<'a> {
+  window: &'a Window,
+  game: &'a mut Game,
+  cursor_lock: &'a CursorLock,
+}
+
+struct App {
+  state: Option<AppState>,
+  cursor_lock: CursorLock,
+  event_loop_proxy: EventLoopProxy<AppEvent>,
+}
+
+impl App {
+  fn new(event_loop_proxy: EventLoopProxy<AppEvent>) -> Self {
+    App {
+      state: None,
+      cursor_lock: CursorLock::default(),
+      event_loop_proxy,
+    }
+  }
+
+  fn is_ready(&self) -> bool {
+    self.state.is_some()
+  }
+
+  fn unwrap(&mut self) -> UnwrapApp<'_> {
+    let state = self.state.as_mut().unwrap();
+
+    UnwrapApp {
+      window: &state.window,
+      game: &mut state.game,
+      cursor_lock: &self.cursor_lock,
+    }
+  }
+}
+
+impl ApplicationHandler<AppEvent> for App {
+  fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+    let window_attributes =
+      platform::init_window_attributes(Window::default_attributes().with_title("Opencraft"));
+
+    let window = Arc::new(
+      event_loop
+        .create_window(window_attributes)
+        .expect("could not create window"),
+    );
+
+    self.cursor_lock.hide_mouse(&window);
+    self.cursor_lock.try_lock(&window);
+
+    verify_send_event(
+      self
+        .event_loop_proxy
+        .send_event(AppEvent::SpinWaitWindowInit(window)),
+    );
+  }
+
+  fn user_event(&mut self, _event_loop: &ActiveEventLoop, event: AppEvent) {
+    match event {
+      AppEvent::SpinWaitWindowInit(window) => {
+        let event_loop_proxy = self.event_loop_proxy.clone();
+
+        if is_valid_window(&window) {
+          platform::run_future(async move {
+            let game = Game::new(Arc::clone(&window))
+              .await
+              .expect("could not initialise game");
+
+            verify_send_event(
+              event_loop_proxy.send_event(AppEvent::Init(Box::new(AppState { window, game }))),
+            );
+          });
+        } else {
+          platform::run_future(async move {
+            platform::sleep(Duration::from_millis(100)).await;
+
+            verify_send_event(event_loop_proxy.send_event(AppEvent::SpinWaitWindowInit(window)));
+          });
+        }
+      }
+      AppEvent::Init(app_state) => self.state = Some(*app_state),
+    };
+  }
+
+  fn window_event(
+    &mut self,
+    event_loop: &ActiveEventLoop,
+    _window_id: WindowId,
+    event: WindowEvent,
+  ) {
+    if !self.is_ready() {
+      return;
+    }
+
+    let UnwrapApp {
+      window,
+      game,
+      cursor_lock,
+    } = self.unwrap();
+
+    match event {
+      WindowEvent::CloseRequested => {
+        save_and_exit(event_loop, game);
+      }
+      WindowEvent::RedrawRequested => {
+        if let Err(err) = game.compose() {
+          error!("Error during composition loop: {:?}", err);
+          event_loop.exit();
+        }
+      }
+      WindowEvent::Resized(physical_size) => {
+        game.resize(physical_size);
+      }
+      WindowEvent::ScaleFactorChanged { .. } => {
+        game.resize(window.inner_size());
+      }
+      // The player may have edited the controls or settings while away
+      WindowEvent::Focused(true) => game.reload_config(),
+      WindowEvent::MouseInput { state, button, .. } => match state {
+        ElementState::Pressed => game.press(Button::Mouse(button)),
+        ElementState::Released => game.release(Button::Mouse(button)),
+      },
+      WindowEvent::MouseWheel { delta, .. } => {
+        game.scroll(match delta {
+          MouseScrollDelta::LineDelta(x, y) => Vec2::new(x, y),
+          MouseScrollDelta::PixelDelta(PhysicalPosition { x, y }) => Vec2::new(
+            (x / PIXELS_PER_LINE).coerce_lossy(),
+            (y / PIXELS_PER_LINE).coerce_lossy(),
+          ),
+        });
+      }
+      WindowEvent::CursorMoved { position, .. } => {
+        game.move_cursor(Some(Vec2::new(
+          position.x.coerce_lossy(),
+          position.y.coerce_lossy(),
+        )));
+      }
+      WindowEvent::CursorLeft { .. } => game.move_cursor(None),
+      WindowEvent::KeyboardInput {
+        event:
+          KeyEvent {
+            state,
+            physical_key: PhysicalKey::Code(code),
+            ..
+          },
+        ..
+      } => {
+        let button = Button::Key(code);
+        let bindings = game.bindings();
+
+        match state {
+          ElementState::Pressed => {
+            let toggle_fullscreen = bindings.is_bound(Action::ToggleFullscreen, button);
+
+            // A web page can't close itself
+            #[cfg(not(target_family = "wasm"))]
+            if bindings.is_bound(Action::Quit, button) {
+              save_and_exit(event_loop, game);
+            }
+            if toggle_fullscreen {
+              fullscreen::toggle(window);
+            }
+
+            game.press(button);
+          }
+          ElementState::Released => {
+            if bindings.is_bound(Action::ToggleFullscreen, button) {
+              cursor_lock.try_user_requested_lock(window);
+            }
+
+            game.release(button);
+          }
+        }
+      }
+      _ => {}
+    };
+  }
+
+  fn device_event(
+    &mut self,
+    _event_loop: &ActiveEventLoop,
+    _device_id: DeviceId,
+    event: DeviceEvent,
+  ) {
+    if !self.is_ready() {
+      return;
+    }
+
+    let UnwrapApp {
+      window,
+      game,
+      cursor_lock,
+    } = self.unwrap();
+
+    #[allow(clippy::single_match)]
+    match event {
+      DeviceEvent::MouseMotion { delta: (x, y) } => {
+        cursor_lock.update_position(window);
+
+        game.motion(Vec2::new(x.coerce_lossy(), y.coerce_lossy()));
+      }
+      _ => {}
+    }
+  }
+
+  fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+    if !self.is_ready() {
+      return;
+    }
+
+    let UnwrapApp { window, .. } = self.unwrap();
+
+    window.request_redraw();
+  }
+}
+
+fn save_and_exit(event_loop: &ActiveEventLoop, game: &mut Game) {
+  if let Err(err) = game.save() {
+    error!("Could not save the world: {:?}", err);
+  }
+
+  event_loop.exit();
+}
+
+fn is_valid_window(window: &Window) -> bool {
+  (window.inner_size().width > 0) && (window.inner_size().height > 0)
+}
+
+fn verify_send_event(result: Result<(), EventLoopClosed<AppEvent>>) {
+  if result.is_err() {
+    panic!("event loop closed");
+  }
+}
@@ -1,7 +1,9 @@
 use std::mem;
 
+pub mod bytes;
 #[allow(dead_code)]
 pub mod math;
+pub mod timestep;
 pub mod type_conversions;
 
 pub fn slice_byte_len<T>(slice: &[T]) -> usize {
@@ -0,0 +1,105 @@
+use anyhow::{Result, bail};
+
+/// Appends little-endian values to a byte buffer.
+#[derive(Debug, Default)]
+pub struct ByteWriter {
+  bytes: Vec<u8>,
+}
+
+impl ByteWriter {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn into_bytes(self) -> Vec<u8> {
+    self.bytes
+  }
+
+  pub fn bytes(&mut self, bytes: &[u8]) {
+    self.bytes.extend_from_slice(bytes);
+  }
+
+  pub fn u8(&mut self, value: u8) {
+    self.bytes.push(value);
+  }
+
+  pub fn u16(&mut self, value: u16) {
+    self.bytes(&value.to_le_bytes());
+  }
+
+  pub fn u32(&mut self, value: u32) {
+    self.bytes(&value.to_le_bytes());
+  }
+
+  pub fn u64(&mut self, value: u64) {
+    self.bytes(&value.to_le_bytes());
+  }
+
+  pub fn i32(&mut self, value: i32) {
+    self.bytes(&value.to_le_bytes());
+  }
+
+  pub fn f32(&mut self, value: f32) {
+    self.bytes(&value.to_le_bytes());
+  }
+}
+
+/// Reads little-endian values from the front of a byte slice.
+#[derive(Debug)]
+pub struct ByteReader<'a> {
+  bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+  pub fn new(bytes: &'a [u8]) -> Self {
+    Self { bytes }
+  }
+
+  /// Returns the bytes which haven't been read yet.
+  pub fn remaining(&self) -> &'a [u8] {
+    self.bytes
+  }
+
+  pub fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+    let Some((taken, rest)) = self.bytes.split_first_chunk::<N>() else {
+      bail!("data ended unexpectedly");
+    };
+
+    self.bytes = rest;
+    Ok(*taken)
+  }
+
+  pub fn take_slice(&mut self, len: usize) -> Result<&'a [u8]> {
+    if len > self.bytes.len() {
+      bail!("data ended unexpectedly");
+    }
+
+    let (taken, rest) = self.bytes.split_at(len);
+    self.bytes = rest;
+    Ok(taken)
+  }
+
+  pub fn u8(&mut self) -> Result<u8> {
+    Ok(u8::from_le_bytes(self.take()?))
+  }
+
+  pub fn u16(&mut self) -> Result<u16> {
+    Ok(u16::from_le_bytes(self.take()?))
+  }
+
+  pub fn u32(&mut self) -> Result<u32> {
+    Ok(u32::from_le_bytes(self.take()?))
+  }
+
+  pub fn u64(&mut self) -> Result<u64> {
+    Ok(u64::from_le_bytes(self.take()?))
+  }
+
+  pub fn i32(&mut self) -> Result<i32> {
+    Ok(i32::from_le_bytes(self.take()?))
+  }
+
+  pub fn f32(&mut self) -> Result<f32> {
+    Ok(f32::from_le_bytes(self.take()?))
+  }
+}
@@ -1,6 +1,7 @@
 pub mod aligned_box3;
 pub mod angle;
 pub mod bivec3;
+pub mod frustum;
 pub mod mat4;
 pub mod rotor3;
 pub mod segment3;
@@ -3,7 +3,7 @@ use crate::core::math::segment3::Segment3;
 use crate::core::math::vec3::Vec3;
 use crate::core::math::{X_AXIS, Y_AXIS, Z_AXIS};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BoxFace {
   Left,
   Right,
@@ -14,6 +14,31 @@ pub enum BoxFace {
 }
 
 impl BoxFace {
+  pub const ALL: [BoxFace; 6] = [
+    BoxFace::Left,
+    BoxFace::Right,
+    BoxFace::Top,
+    BoxFace::Bottom,
+    BoxFace::Back,
+    BoxFace::Front,
+  ];
+
+  /// Returns the position of this face in [`BoxFace::ALL`].
+  pub fn index(self) -> usize {
+    self as usize
+  }
+
+  pub fn opposite(self) -> Self {
+    match self {
+      BoxFace::Left => BoxFace::Right,
+      BoxFace::Right => BoxFace::Left,
+      BoxFace::Top => BoxFace::Bottom,
+      BoxFace::Bottom => BoxFace::Top,
+      BoxFace::Back => BoxFace::Front,
+      BoxFace::Front => BoxFace::Back,
+    }
+  }
+
   pub fn normal(self) -> Vec3 {
     match self {
       BoxFace::Left => X_AXIS,
@@ -26,27 +51,57 @@ impl BoxFace {
   }
 }
 
+/// An axis-aligned box, with `extents` being half its size along each axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct AlignedBox3 {
   center: Vec3,
-  extent: f32,
+  extents: Vec3,
 }
 
 impl AlignedBox3 {
+  pub const fn new(center: Vec3, extents: Vec3) -> Self {
+    Self { center, extents }
+  }
+
   pub const fn cube(center: Vec3, extent: f32) -> Self {
-    Self { center, extent }
+    Self::new(center, Vec3::new(extent, extent, extent))
+  }
+
+  pub const fn center(&self) -> Vec3 {
+    self.center
+  }
+
+  pub const fn extents(&self) -> Vec3 {
+    self.extents
+  }
+
+  pub fn min(&self) -> Vec3 {
+    self.center - self.extents
+  }
+
+  pub fn max(&self) -> Vec3 {
+    self.center + self.extents
+  }
+
+  pub fn translate(&self, offset: Vec3) -> Self {
+    Self::new(self.center + offset, self.extents)
+  }
+
+  /// Returns true if the boxes overlap, rather than merely touching.
+  pub fn intersects(&self, other: &AlignedBox3) -> bool {
+    let (min, max) = (self.min(), self.max());
+    let (other_min, other_max) = (other.min(), other.max());
+
+    (min.x() < other_max.x())
+      && (other_min.x() < max.x())
+      && (min.y() < other_max.y())
+      && (other_min.y() < max.y())
+      && (min.z() < other_max.z())
+      && (other_min.z() < max.z())
   }
 
   pub fn find_intersecting_face(&self, segment: &Segment3) -> Option<BoxFace> {
-    const FACES: [BoxFace; 6] = [
-      BoxFace::Left,
-      BoxFace::Right,
-      BoxFace::Top,
-      BoxFace::Bottom,
-      BoxFace::Back,
-      BoxFace::Front,
-    ];
-
-    for face in FACES {
+    for face in BoxFace::ALL {
       let normal = face.normal();
       let direction_match = Vec3::dot(segment.direction(), normal);
 
@@ -74,7 +129,9 @@ impl AlignedBox3 {
     let start_a0 = Vec3::dot(start, axis_0);
     let end_a0 = Vec3::dot(end, axis_0);
 
-    let face_center = self.center + (face.normal() * self.extent);
+    let face_center = self.center + (face.normal() * Vec3::dot(self.extents, axis_0));
+    let extent_a1 = Vec3::dot(self.extents, axis_1);
+    let extent_a2 = Vec3::dot(self.extents, axis_2);
     let face_center_a0 = Vec3::dot(face_center, axis_0);
 
     let (min_a0, max_a0) = math::min_max(start_a0, end_a0);
@@ -94,15 +151,8 @@ impl AlignedBox3 {
     let p_a2 = Vec3::dot(p, axis_2);
     let face_center_a2 = Vec3::dot(face_center, axis_2);
 
-    math::in_range(
-      p_a1,
-      face_center_a1 - self.extent,
-      face_center_a1 + self.extent,
-    ) && math::in_range(
-      p_a2,
-      face_center_a2 - self.extent,
-      face_center_a2 + self.extent,
-    )
+    math::in_range(p_a1, face_center_a1 - extent_a1, face_center_a1 + extent_a1)
+      && math::in_range(p_a2, face_center_a2 - extent_a2, face_center_a2 + extent_a2)
   }
 }
 
@@ -125,4 +175,31 @@ mod tests {
 
     assert_eq!(Some(BoxFace::Right), face);
   }
+
+  // Check that a segment passing beside a box's narrow side misses it, while
+  // one passing through its wide side hits.
+  #[test]
+  fn test_intersect_non_cubic() {
+    let slab = AlignedBox3::new(Vec3::new(0.0, 0.0, 2.0), Vec3::new(2.0, 0.25, 0.5));
+    let segment =
+      |y: f32| Segment3::start_direction_len(Vec3::new(1.5, y, 0.0), Vec3::new(0.0, 0.0, 1.0), 5.0);
+
+    assert_eq!(
+      Some(BoxFace::Front),
+      slab.find_intersecting_face(&segment(0.2))
+    );
+    assert_eq!(None, slab.find_intersecting_face(&segment(0.3)));
+  }
+
+  // Check that boxes which only touch don't count as intersecting.
+  #[test]
+  fn test_intersects() {
+    let a = AlignedBox3::new(Vec3::default(), Vec3::new(0.5, 1.0, 0.5));
+    let touching = AlignedBox3::cube(Vec3::new(1.0, 0.0, 0.0), 0.5);
+    let overlapping = AlignedBox3::cube(Vec3::new(0.5, 1.2, 0.0), 0.5);
+
+    assert!(!a.intersects(&touching));
+    assert!(a.intersects(&overlapping));
+    assert!(overlapping.intersects(&a));
+  }
 }
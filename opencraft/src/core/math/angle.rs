@@ -11,6 +11,10 @@ fn degrees_to_radians(degrees: f32) -> f32 {
   degrees * (PI / 180.0)
 }
 
+fn radians_to_degrees(radians: f32) -> f32 {
+  radians * (180.0 / PI)
+}
+
 #[derive(
   Debug,
   Default,
@@ -47,6 +51,14 @@ impl Angle {
     Self::radians(degrees_to_radians(degrees))
   }
 
+  pub const fn as_radians(self) -> f32 {
+    self.radians
+  }
+
+  pub fn as_degrees(self) -> f32 {
+    radians_to_degrees(self.radians)
+  }
+
   pub fn sin(self) -> f32 {
     self.radians.sin()
   }
@@ -0,0 +1,88 @@
+use crate::core::math::aligned_box3::AlignedBox3;
+use crate::core::math::vec3::Vec3;
+
+/// A plane, dividing space into the points in front of it, which its normal
+/// points towards, and those behind it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+  normal: Vec3,
+  /// Signed distance of the origin from the plane.
+  offset: f32,
+}
+
+impl Plane {
+  /// Creates the plane of the points `p` where `dot(normal, p) + offset = 0`,
+  /// scaled so that the normal has unit length.
+  pub fn new(normal: Vec3, offset: f32) -> Self {
+    let len = normal.len();
+
+    Self {
+      normal: normal / len,
+      offset: offset / len,
+    }
+  }
+
+  pub const fn normal(&self) -> Vec3 {
+    self.normal
+  }
+
+  /// Returns how far `point` is in front of the plane, or negative if it is
+  /// behind it.
+  pub fn signed_distance(&self, point: Vec3) -> f32 {
+    Vec3::dot(self.normal, point) + self.offset
+  }
+
+  /// Returns true if all of `aligned_box` is behind the plane.
+  pub fn is_behind(&self, aligned_box: &AlignedBox3) -> bool {
+    // Distance from the box's center to its corner furthest in front
+    let extents = aligned_box.extents();
+    let radius = (extents.x() * self.normal.x().abs())
+      + (extents.y() * self.normal.y().abs())
+      + (extents.z() * self.normal.z().abs());
+
+    self.signed_distance(aligned_box.center()) < -radius
+  }
+}
+
+/// The space which a camera sees, bounded by six planes facing inwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+  planes: [Plane; 6],
+}
+
+impl Frustum {
+  pub const fn new(planes: [Plane; 6]) -> Self {
+    Self { planes }
+  }
+
+  pub const fn planes(&self) -> &[Plane; 6] {
+    &self.planes
+  }
+
+  /// Returns true if `aligned_box` may be seen.
+  ///
+  /// Boxes just outside a corner of the frustum, behind none of its planes,
+  /// are counted as seen too, so a box is only ever wrongly kept, never
+  /// wrongly culled.
+  pub fn may_contain(&self, aligned_box: &AlignedBox3) -> bool {
+    !self.planes.iter().any(|plane| plane.is_behind(aligned_box))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Check that a box is only behind a plane when all of it is, and not just
+  // its center.
+  #[test]
+  fn test_box_behind_plane() {
+    let plane = Plane::new(Vec3::new(0.0, 2.0, 0.0), -2.0);
+    let aligned_box = |y: f32| AlignedBox3::cube(Vec3::new(5.0, y, -3.0), 0.5);
+
+    assert_eq!(-1.0, plane.signed_distance(Vec3::default()));
+    assert!(plane.is_behind(&aligned_box(0.0)));
+    assert!(!plane.is_behind(&aligned_box(0.75)));
+    assert!(!plane.is_behind(&aligned_box(2.0)));
+  }
+}
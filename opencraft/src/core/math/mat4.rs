@@ -1,4 +1,5 @@
 use crate::core::math::angle::Angle;
+use crate::core::math::frustum::{Frustum, Plane};
 use crate::core::math::rotor3::Rotor3;
 use crate::core::math::vec3::Vec3;
 use crate::core::math::{X_AXIS, Y_AXIS, Z_AXIS};
@@ -23,6 +24,12 @@ impl Mat4x4 {
     mat[(3, 3)] = 1.0;
     mat
   }
+
+  /// Returns the coefficients of the row of the matrix at `index`, such as
+  /// the coefficients which make up the clip space x coordinate for row 0.
+  fn row(&self, index: usize) -> [f32; 4] {
+    [0, 1, 2, 3].map(|col| self[(col, index)])
+  }
 }
 
 impl Index<(usize, usize)> for Mat4x4 {
@@ -97,3 +104,64 @@ pub fn rotate(rotor: Rotor3) -> Mat4x4 {
   mat[(3, 3)] = 1.0;
   mat
 }
+
+/// Extracts the frustum seen through `transform`, a world to clip space
+/// transform such as a perspective projection times a camera transform.
+///
+/// A point is on screen when its clip space coordinates satisfy
+/// `-w <= x <= w`, `-w <= y <= w` and `0 <= z <= w`, each of which is a
+/// combination of the matrix's rows, and so a plane in world space.
+pub fn frustum(transform: &Mat4x4) -> Frustum {
+  let [x, y, z, w] = [0, 1, 2, 3].map(|index| transform.row(index));
+  let plane = |[a, b, c, d]: [f32; 4]| Plane::new(Vec3::new(a, b, c), d);
+  let add = |lhs: [f32; 4], rhs: [f32; 4]| [0, 1, 2, 3].map(|i| lhs[i] + rhs[i]);
+  let sub = |lhs: [f32; 4], rhs: [f32; 4]| [0, 1, 2, 3].map(|i| lhs[i] - rhs[i]);
+
+  Frustum::new([
+    plane(add(w, x)),
+    plane(sub(w, x)),
+    plane(add(w, y)),
+    plane(sub(w, y)),
+    plane(z),
+    plane(sub(w, z)),
+  ])
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::core::math::aligned_box3::AlignedBox3;
+  use crate::core::math::angle::QUARTER_ROTATION;
+
+  // Check that a frustum looking down +Z from the origin contains boxes in
+  // front of the camera, and not those behind it, beside it or too far away.
+  #[test]
+  fn test_frustum_culls_boxes() {
+    let frustum = frustum(&perspective(800.0, 600.0, QUARTER_ROTATION, 0.1, 100.0));
+    let cube = |x: f32, y: f32, z: f32| AlignedBox3::cube(Vec3::new(x, y, z), 1.0);
+
+    assert!(frustum.may_contain(&cube(0.0, 0.0, 10.0)));
+    assert!(!frustum.may_contain(&cube(0.0, 0.0, -10.0)));
+    assert!(!frustum.may_contain(&cube(20.0, 0.0, 10.0)));
+    assert!(!frustum.may_contain(&cube(0.0, -20.0, 10.0)));
+    assert!(!frustum.may_contain(&cube(0.0, 0.0, 120.0)));
+    // Straddling the edge of the view
+    assert!(frustum.may_contain(&cube(13.5, 0.0, 10.0)));
+  }
+
+  // Check that moving the camera moves the frustum with it, and that its
+  // planes face inwards with unit normals.
+  #[test]
+  fn test_frustum_follows_camera() {
+    let transform =
+      &perspective(1.0, 1.0, QUARTER_ROTATION, 0.1, 100.0) * &translate(Vec3::new(-50.0, 0.0, 0.0));
+    let frustum = frustum(&transform);
+
+    assert!(frustum.may_contain(&AlignedBox3::cube(Vec3::new(50.0, 0.0, 10.0), 1.0)));
+    assert!(!frustum.may_contain(&AlignedBox3::cube(Vec3::new(0.0, 0.0, 10.0), 1.0)));
+    for plane in frustum.planes() {
+      assert!((plane.normal().len() - 1.0).abs() < 1e-5);
+      assert!(plane.signed_distance(Vec3::new(50.0, 0.0, 10.0)) > 0.0);
+    }
+  }
+}
@@ -23,6 +23,10 @@ impl Segment3 {
     self.start
   }
 
+  pub const fn len(&self) -> f32 {
+    self.len
+  }
+
   pub fn end(&self) -> Vec3 {
     self.start + (self.direction * self.len)
   }
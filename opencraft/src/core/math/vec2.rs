@@ -37,6 +37,10 @@ impl Vec2 {
     self.y
   }
 
+  pub fn len(self) -> f32 {
+    self.x().hypot(self.y())
+  }
+
   pub fn normalise_components_to(self, rhs: Self) -> Self {
     Self::new(self.x() / rhs.x(), self.y() / rhs.y())
   }
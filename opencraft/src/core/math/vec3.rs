@@ -1,8 +1,24 @@
 use crate::core::math;
 use derive_more::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use zerocopy::{Immutable, IntoBytes};
 
+#[repr(C)]
 #[derive(
-  Debug, Default, Clone, Copy, Neg, Add, Sub, Mul, Div, AddAssign, SubAssign, MulAssign, DivAssign,
+  Debug,
+  Default,
+  Clone,
+  Copy,
+  Neg,
+  Add,
+  Sub,
+  Mul,
+  Div,
+  AddAssign,
+  SubAssign,
+  MulAssign,
+  DivAssign,
+  Immutable,
+  IntoBytes,
 )]
 pub struct Vec3 {
   x: f32,
@@ -56,6 +72,11 @@ impl Vec3 {
     (lhs - rhs).len_sq()
   }
 
+  /// Linearly interpolates from `start` at `t = 0` to `end` at `t = 1`.
+  pub fn lerp(start: Self, end: Self, t: f32) -> Self {
+    start + ((end - start) * t)
+  }
+
   pub fn len(self) -> f32 {
     self.len_sq().sqrt()
   }
@@ -72,6 +93,18 @@ impl Vec3 {
   }
 }
 
+impl From<[f32; 3]> for Vec3 {
+  fn from([x, y, z]: [f32; 3]) -> Self {
+    Self::new(x, y, z)
+  }
+}
+
+impl From<Vec3> for [f32; 3] {
+  fn from(vec: Vec3) -> Self {
+    [vec.x, vec.y, vec.z]
+  }
+}
+
 impl std::ops::Mul<Vec3> for f32 {
   type Output = Vec3;
 
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+/// Splits elapsed real time into fixed length ticks, so that a simulation
+/// advances the same way however fast it's rendered.
+///
+/// Time which doesn't make up a whole tick is carried over to the next call to
+/// [`FixedTimestep::advance`], and [`FixedTimestep::alpha`] says how far
+/// through the next tick it is, for interpolating between ticks.
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+  tick: Duration,
+  max_ticks: u32,
+  accumulated: Duration,
+}
+
+impl FixedTimestep {
+  /// Creates a timestep of `tick` long ticks, running at most `max_ticks`
+  /// ticks per call to [`FixedTimestep::advance`].
+  pub fn new(tick: Duration, max_ticks: u32) -> Self {
+    assert!(!tick.is_zero(), "tick duration must be positive");
+    assert!(max_ticks > 0, "must be able to run at least one tick");
+
+    Self {
+      tick,
+      max_ticks,
+      accumulated: Duration::ZERO,
+    }
+  }
+
+  pub fn tick(&self) -> Duration {
+    self.tick
+  }
+
+  /// Adds `elapsed` time, and returns how many ticks to run to catch up.
+  ///
+  /// If more than `max_ticks` ticks are due, the excess time is dropped rather
+  /// than making the next frame even longer, so the simulation slows down
+  /// instead of falling ever further behind.
+  pub fn advance(&mut self, elapsed: Duration) -> u32 {
+    self.accumulated += elapsed;
+
+    let mut ticks = 0;
+    while (self.accumulated >= self.tick) && (ticks < self.max_ticks) {
+      self.accumulated -= self.tick;
+      ticks += 1;
+    }
+
+    if self.accumulated >= self.tick {
+      self.accumulated = Duration::ZERO;
+    }
+    ticks
+  }
+
+  /// Returns how far through the next tick the accumulated time is, from 0 to
+  /// 1.
+  pub fn alpha(&self) -> f32 {
+    self.accumulated.div_duration_f32(self.tick)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const TICK: Duration = Duration::from_millis(50);
+
+  // Check that elapsed time is split into whole ticks, carrying over the rest.
+  #[test]
+  fn test_whole_ticks() {
+    let mut timestep = FixedTimestep::new(TICK, 10);
+
+    assert_eq!(0, timestep.advance(Duration::from_millis(30)));
+    assert_eq!(0.6, timestep.alpha());
+    assert_eq!(1, timestep.advance(Duration::from_millis(30)));
+    assert_eq!(2, timestep.advance(Duration::from_millis(100)));
+    assert!((timestep.alpha() - 0.2).abs() < 1e-6);
+  }
+
+  // Check that the number of ticks doesn't depend on how the elapsed time is
+  // split into frames.
+  #[test]
+  fn test_frame_rate_independent() {
+    let mut fast = FixedTimestep::new(TICK, 10);
+    let mut slow = FixedTimestep::new(TICK, 10);
+
+    let fast_ticks: u32 = (0..120).map(|_| fast.advance(TICK / 5)).sum();
+    let slow_ticks: u32 = (0..24).map(|_| slow.advance(TICK)).sum();
+
+    assert_eq!(24, fast_ticks);
+    assert_eq!(slow_ticks, fast_ticks);
+  }
+
+  // Check that a long stall runs at most the maximum number of ticks, and drops
+  // the rest of the time.
+  #[test]
+  fn test_long_frame_clamped() {
+    let mut timestep = FixedTimestep::new(TICK, 5);
+
+    assert_eq!(5, timestep.advance(Duration::from_secs(10)));
+    assert_eq!(0.0, timestep.alpha());
+    assert_eq!(1, timestep.advance(TICK));
+  }
+}
@@ -1,3 +1,4 @@
+#[cfg(feature = "client")]
 use wgpu::BufferAddress;
 
 pub trait Coerce<T> {
@@ -20,12 +21,31 @@ impl CoerceLossy<f32> for usize {
   }
 }
 
+#[cfg(feature = "client")]
 impl Coerce<BufferAddress> for usize {
   fn coerce(self) -> BufferAddress {
     self.try_into().unwrap()
   }
 }
 
+impl Coerce<u8> for usize {
+  fn coerce(self) -> u8 {
+    self.try_into().unwrap()
+  }
+}
+
+impl Coerce<u16> for usize {
+  fn coerce(self) -> u16 {
+    self.try_into().unwrap()
+  }
+}
+
+impl Coerce<i32> for usize {
+  fn coerce(self) -> i32 {
+    self.try_into().unwrap()
+  }
+}
+
 impl Coerce<usize> for u32 {
   fn coerce(self) -> usize {
     self.try_into().unwrap()
@@ -44,6 +64,18 @@ impl Coerce<usize> for i32 {
   }
 }
 
+impl CoerceLossy<f32> for i32 {
+  fn coerce_lossy(self) -> f32 {
+    self as f32
+  }
+}
+
+impl CoerceLossy<f32> for u64 {
+  fn coerce_lossy(self) -> f32 {
+    self as f32
+  }
+}
+
 impl CoerceLossy<f32> for f64 {
   fn coerce_lossy(self) -> f32 {
     self as f32
@@ -62,6 +94,12 @@ impl CoerceLossy<u32> for f32 {
   }
 }
 
+impl CoerceLossy<i32> for f32 {
+  fn coerce_lossy(self) -> i32 {
+    self as i32
+  }
+}
+
 impl CoerceLossy<u8> for f32 {
   fn coerce_lossy(self) -> u8 {
     self as u8
@@ -1,1449 +1,492 @@
+use crate::bindings::{Action, Bindings};
 use crate::camera::{Camera, Direction};
-use crate::core;
-use crate::core::math;
-use crate::core::math::aligned_box3::{AlignedBox3, BoxFace};
 use crate::core::math::angle::{Angle, FULL_ROTATION};
-use crate::core::math::mat4::Mat4x4;
-use crate::core::math::segment3::Segment3;
 use crate::core::math::vec2::Vec2;
 use crate::core::math::vec3::Vec3;
-use crate::core::math::{X_AXIS, Y_AXIS, Z_AXIS, mat4};
-use crate::core::type_conversions::{Coerce, CoerceLossy};
-use crate::platform::{Instant, ResourceReader};
-use crate::resources::Texture;
-use crate::text::{FontAtlas, TextVertex};
-use anyhow::Result;
-use image::GenericImageView;
-use std::collections::HashSet;
-use std::sync::{Arc, LazyLock};
+use crate::core::math::{X_AXIS, Y_AXIS, Z_AXIS};
+use crate::core::timestep::FixedTimestep;
+use crate::core::type_conversions::CoerceLossy;
+use crate::gamepad::{self, Gamepads};
+use crate::input::{Button, InputState};
+use crate::model::{DebugDisplay, Input, Model, Scene, TICK_DURATION};
+use crate::net::client::ServerConnection;
+use crate::net::protocol::{ClientMessage, POSITION_INTERVAL, PlayerId, ServerMessage};
+use crate::platform::{self, Instant, Storage, error, info};
+use crate::renderer::Renderer;
+use crate::settings::Settings;
+use crate::world::World;
+use crate::world::block::Block;
+use crate::world::generation::{DEFAULT_SEED, GENERATION_RADIUS, TerrainGenerator, UNLOAD_MARGIN};
+use crate::world::inventory::HOTBAR_SLOTS;
+use crate::world::player::Player;
+use crate::world::position::BlockPos;
+use crate::world::save::{AUTOSAVE_INTERVAL, WorldSave};
+use crate::world::time::{DAY_LENGTH, WorldTime};
+use anyhow::{Context, Result};
+use gilrs::Axis;
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
-use std::{iter, mem};
-use wgpu::util::{BufferInitDescriptor, DeviceExt};
-use wgpu::wgt::TextureDataOrder;
-use wgpu::{
-  Backends, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
-  BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState,
-  Buffer, BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites,
-  CommandEncoderDescriptor, CompareFunction, DepthBiasState, DepthStencilState, Device,
-  DeviceDescriptor, ExperimentalFeatures, Extent3d, Face, Features, FragmentState, FrontFace,
-  Instance, InstanceDescriptor, Limits, LoadOp, MemoryHints, MultisampleState, Operations,
-  PipelineCompilationOptions, PipelineLayoutDescriptor, PolygonMode, PowerPreference, PresentMode,
-  PrimitiveState, PrimitiveTopology, Queue, RenderPassColorAttachment,
-  RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
-  RequestAdapterOptions, Sampler, SamplerBindingType, SamplerDescriptor, ShaderStages,
-  StencilState, StoreOp, Surface, SurfaceConfiguration, TextureDescriptor, TextureDimension,
-  TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor,
-  TextureViewDimension, Trace, VertexBufferLayout, VertexState, VertexStepMode, include_wgsl,
-  vertex_attr_array,
-};
 use winit::dpi::PhysicalSize;
-use winit::event::MouseButton;
-use winit::keyboard::KeyCode;
 use winit::window::Window;
-use zerocopy::{Immutable, IntoBytes};
 
-const FONT_SCALE: f32 = 24.0;
 const FRAME_TIME_MEASUREMENTS: usize = 60;
 
-const BLOCK_LIMIT: usize = 256;
-
-static FOV: LazyLock<Angle> = LazyLock::new(|| Angle::degrees(75.0));
-const Z_NEAR: f32 = 0.01;
-const Z_FAR: f32 = 1000.0;
-
-const CUBE_SIZE: f32 = 1.0;
-const CUBE_HALF: f32 = CUBE_SIZE / 2.0;
-const CUBE_TRANSLATE: Vec3 = Vec3::new(0.0, 0.0, 3.0);
-
-const BACK: f32 = CUBE_HALF;
-const FRONT: f32 = -CUBE_HALF;
-const BOTTOM: f32 = -CUBE_HALF;
-const TOP: f32 = CUBE_HALF;
-const LEFT: f32 = -CUBE_HALF;
-const RIGHT: f32 = CUBE_HALF;
-
-const TEX_WIDTH: f32 = 48.0;
-const TEX_HEIGHT: f32 = 64.0;
-
-const TEX_FRONT_LEFT: f32 = 16.0 / TEX_WIDTH;
-const TEX_FRONT_RIGHT: f32 = 32.0 / TEX_WIDTH;
-const TEX_FRONT_TOP: f32 = 32.0 / TEX_HEIGHT;
-const TEX_FRONT_BOTTOM: f32 = 48.0 / TEX_HEIGHT;
-
-const TEX_BACK_LEFT: f32 = 16.0 / TEX_WIDTH;
-const TEX_BACK_RIGHT: f32 = 32.0 / TEX_WIDTH;
-const TEX_BACK_TOP: f32 = 0.0 / TEX_HEIGHT;
-const TEX_BACK_BOTTOM: f32 = 16.0 / TEX_HEIGHT;
-
-const TEX_TOP_LEFT: f32 = 16.0 / TEX_WIDTH;
-const TEX_TOP_RIGHT: f32 = 32.0 / TEX_WIDTH;
-const TEX_TOP_TOP: f32 = 16.0 / TEX_HEIGHT;
-const TEX_TOP_BOTTOM: f32 = 32.0 / TEX_HEIGHT;
-
-const TEX_BOTTOM_LEFT: f32 = 16.0 / TEX_WIDTH;
-const TEX_BOTTOM_RIGHT: f32 = 32.0 / TEX_WIDTH;
-const TEX_BOTTOM_TOP: f32 = 48.0 / TEX_HEIGHT;
-const TEX_BOTTOM_BOTTOM: f32 = 64.0 / TEX_HEIGHT;
-
-const TEX_LEFT_LEFT: f32 = 0.0 / TEX_WIDTH;
-const TEX_LEFT_RIGHT: f32 = 16.0 / TEX_WIDTH;
-const TEX_LEFT_TOP: f32 = 16.0 / TEX_HEIGHT;
-const TEX_LEFT_BOTTOM: f32 = 32.0 / TEX_HEIGHT;
-
-const TEX_RIGHT_LEFT: f32 = 32.0 / TEX_WIDTH;
-const TEX_RIGHT_RIGHT: f32 = 48.0 / TEX_WIDTH;
-const TEX_RIGHT_TOP: f32 = 16.0 / TEX_HEIGHT;
-const TEX_RIGHT_BOTTOM: f32 = 32.0 / TEX_HEIGHT;
-
-#[repr(C)]
-#[derive(Clone, Copy, Immutable, IntoBytes)]
-struct Vertex {
-  position: [f32; 3],
-  texture_coordinate: [f32; 2],
-}
-
-const VERTICES: &[Vertex] = &[
-  // Front face
-  Vertex {
-    position: [LEFT, TOP, FRONT],
-    texture_coordinate: [TEX_FRONT_LEFT, TEX_FRONT_TOP],
-  },
-  Vertex {
-    position: [LEFT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_FRONT_LEFT, TEX_FRONT_BOTTOM],
-  },
-  Vertex {
-    position: [RIGHT, TOP, FRONT],
-    texture_coordinate: [TEX_FRONT_RIGHT, TEX_FRONT_TOP],
-  },
-  Vertex {
-    position: [RIGHT, TOP, FRONT],
-    texture_coordinate: [TEX_FRONT_RIGHT, TEX_FRONT_TOP],
-  },
-  Vertex {
-    position: [LEFT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_FRONT_LEFT, TEX_FRONT_BOTTOM],
-  },
-  Vertex {
-    position: [RIGHT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_FRONT_RIGHT, TEX_FRONT_BOTTOM],
-  },
-  // Back face
-  Vertex {
-    position: [LEFT, TOP, BACK],
-    texture_coordinate: [TEX_BACK_LEFT, TEX_BACK_BOTTOM],
-  },
-  Vertex {
-    position: [RIGHT, TOP, BACK],
-    texture_coordinate: [TEX_BACK_RIGHT, TEX_BACK_BOTTOM],
-  },
-  Vertex {
-    position: [LEFT, BOTTOM, BACK],
-    texture_coordinate: [TEX_BACK_LEFT, TEX_BACK_TOP],
-  },
-  Vertex {
-    position: [RIGHT, BOTTOM, BACK],
-    texture_coordinate: [TEX_BACK_RIGHT, TEX_BACK_TOP],
-  },
-  Vertex {
-    position: [LEFT, BOTTOM, BACK],
-    texture_coordinate: [TEX_BACK_LEFT, TEX_BACK_TOP],
-  },
-  Vertex {
-    position: [RIGHT, TOP, BACK],
-    texture_coordinate: [TEX_BACK_RIGHT, TEX_BACK_BOTTOM],
-  },
-  // Top face
-  Vertex {
-    position: [LEFT, TOP, BACK],
-    texture_coordinate: [TEX_TOP_LEFT, TEX_TOP_TOP],
-  },
-  Vertex {
-    position: [LEFT, TOP, FRONT],
-    texture_coordinate: [TEX_TOP_LEFT, TEX_TOP_BOTTOM],
-  },
-  Vertex {
-    position: [RIGHT, TOP, BACK],
-    texture_coordinate: [TEX_TOP_RIGHT, TEX_TOP_TOP],
-  },
-  Vertex {
-    position: [RIGHT, TOP, BACK],
-    texture_coordinate: [TEX_TOP_RIGHT, TEX_TOP_TOP],
-  },
-  Vertex {
-    position: [LEFT, TOP, FRONT],
-    texture_coordinate: [TEX_TOP_LEFT, TEX_TOP_BOTTOM],
-  },
-  Vertex {
-    position: [RIGHT, TOP, FRONT],
-    texture_coordinate: [TEX_TOP_RIGHT, TEX_TOP_BOTTOM],
-  },
-  // Bottom face
-  Vertex {
-    position: [RIGHT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_BOTTOM_RIGHT, TEX_BOTTOM_TOP],
-  },
-  Vertex {
-    position: [LEFT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_BOTTOM_LEFT, TEX_BOTTOM_TOP],
-  },
-  Vertex {
-    position: [LEFT, BOTTOM, BACK],
-    texture_coordinate: [TEX_BOTTOM_LEFT, TEX_BOTTOM_BOTTOM],
-  },
-  Vertex {
-    position: [LEFT, BOTTOM, BACK],
-    texture_coordinate: [TEX_BOTTOM_LEFT, TEX_BOTTOM_BOTTOM],
-  },
-  Vertex {
-    position: [RIGHT, BOTTOM, BACK],
-    texture_coordinate: [TEX_BOTTOM_RIGHT, TEX_BOTTOM_BOTTOM],
-  },
-  Vertex {
-    position: [RIGHT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_BOTTOM_RIGHT, TEX_BOTTOM_TOP],
-  },
-  // Left face
-  Vertex {
-    position: [LEFT, TOP, BACK],
-    texture_coordinate: [TEX_LEFT_RIGHT, TEX_LEFT_TOP],
-  },
-  Vertex {
-    position: [LEFT, BOTTOM, BACK],
-    texture_coordinate: [TEX_LEFT_LEFT, TEX_LEFT_TOP],
-  },
-  Vertex {
-    position: [LEFT, TOP, FRONT],
-    texture_coordinate: [TEX_LEFT_RIGHT, TEX_LEFT_BOTTOM],
-  },
-  Vertex {
-    position: [LEFT, TOP, FRONT],
-    texture_coordinate: [TEX_LEFT_RIGHT, TEX_LEFT_BOTTOM],
-  },
-  Vertex {
-    position: [LEFT, BOTTOM, BACK],
-    texture_coordinate: [TEX_LEFT_LEFT, TEX_LEFT_TOP],
-  },
-  Vertex {
-    position: [LEFT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_LEFT_LEFT, TEX_LEFT_BOTTOM],
-  },
-  // Right face
-  Vertex {
-    position: [RIGHT, TOP, BACK],
-    texture_coordinate: [TEX_RIGHT_LEFT, TEX_RIGHT_TOP],
-  },
-  Vertex {
-    position: [RIGHT, TOP, FRONT],
-    texture_coordinate: [TEX_RIGHT_LEFT, TEX_RIGHT_BOTTOM],
-  },
-  Vertex {
-    position: [RIGHT, BOTTOM, BACK],
-    texture_coordinate: [TEX_RIGHT_RIGHT, TEX_RIGHT_TOP],
-  },
-  Vertex {
-    position: [RIGHT, BOTTOM, BACK],
-    texture_coordinate: [TEX_RIGHT_RIGHT, TEX_RIGHT_TOP],
-  },
-  Vertex {
-    position: [RIGHT, TOP, FRONT],
-    texture_coordinate: [TEX_RIGHT_LEFT, TEX_RIGHT_BOTTOM],
-  },
-  Vertex {
-    position: [RIGHT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_RIGHT_RIGHT, TEX_RIGHT_BOTTOM],
-  },
-];
-
-#[repr(C)]
-#[derive(Clone, Copy, Immutable, IntoBytes)]
-struct Quad {
-  left: f32,
-  right: f32,
-  top: f32,
-  bot: f32,
-}
-
-fn calculate_crosshair_quad(screen_size: Vec2, crosshair_size: u32) -> Quad {
-  const WIDTH_FRACTION: f32 = 0.008;
-
-  let size_pixels = (WIDTH_FRACTION * screen_size.x()).ceil().coerce_lossy();
-  let size_pixels = math::align(size_pixels, crosshair_size.coerce());
-
-  let (pixels_left, pixels_right) = math::split(size_pixels.coerce_lossy());
-
-  let (width_left, width_right) = math::split(screen_size.x());
-  let (height_top, height_bot) = math::split(screen_size.y());
-
-  Quad {
-    left: -(pixels_left / width_left),
-    right: pixels_right / width_right,
-    // To ensure crosshair remains square, use the same amount of pixels vertically as horizontally
-    top: -(pixels_left / height_top),
-    bot: pixels_right / height_bot,
-  }
-}
-
-const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
-
-/// Resources that need to be constructed based on the screen's resolution, and
-/// therefore reconstructed on resize.
-struct ScreenSpaceResources {
-  perspective: Mat4x4,
-  depth_view: TextureView,
-  render_view: TextureView,
-  fullscreen_copy_texture_bind_group: BindGroup,
-}
-
-impl ScreenSpaceResources {
-  pub fn construct(
-    device: &Device,
-    config: &SurfaceConfiguration,
-    fullscreen_copy_texture_bind_group_layout: &BindGroupLayout,
-    default_sampler: &Sampler,
-  ) -> Self {
-    let width = config.width;
-    let height = config.height;
-
-    let depth_texture = device.create_texture(&TextureDescriptor {
-      label: Some("Depth Texture"),
-      size: Extent3d {
-        width,
-        height,
-        depth_or_array_layers: 1,
-      },
-      mip_level_count: 1,
-      sample_count: 1,
-      dimension: TextureDimension::D2,
-      format: DEPTH_FORMAT,
-      usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
-      view_formats: &[],
-    });
-
-    let render_texture = device.create_texture(&TextureDescriptor {
-      label: Some("Offscreen Render Texture"),
-      size: Extent3d {
-        width,
-        height,
-        depth_or_array_layers: 1,
-      },
-      mip_level_count: 1,
-      sample_count: 1,
-      dimension: TextureDimension::D2,
-      format: config.format,
-      usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
-      view_formats: &[],
-    });
-    let render_view = render_texture.create_view(&TextureViewDescriptor::default());
-
-    let fullscreen_copy_texture_bind_group = device.create_bind_group(&BindGroupDescriptor {
-      label: Some("Fullscreen Copy Bind Group"),
-      layout: fullscreen_copy_texture_bind_group_layout,
-      entries: &[
-        BindGroupEntry {
-          binding: 0,
-          resource: BindingResource::TextureView(&render_view),
-        },
-        BindGroupEntry {
-          binding: 1,
-          resource: BindingResource::Sampler(default_sampler),
-        },
-      ],
-    });
-
-    Self {
-      perspective: mat4::perspective(
-        width.coerce_lossy(),
-        height.coerce_lossy(),
-        *FOV,
-        Z_NEAR,
-        Z_FAR,
-      ),
-      depth_view: depth_texture.create_view(&TextureViewDescriptor::default()),
-      render_view,
-      fullscreen_copy_texture_bind_group,
-    }
-  }
+/// Limits the number of chunks loaded or generated per frame, to avoid
+/// stalling.
+const CHUNKS_LOADED_PER_FRAME: usize = 4;
+/// Name which the player logs in to multiplayer servers with.
+const PLAYER_NAME: &str = "Player";
+/// Most ticks run in one frame, so that after a long frame the simulation slows
+/// down rather than stalling to catch up.
+const MAX_TICKS_PER_FRAME: u32 = 15;
+/// How far the debug action skips the time of day forward: an hour.
+const TIME_SKIPPED: Duration = Duration::from_secs(DAY_LENGTH.as_secs() / 24);
+
+/// Where the world being played comes from.
+enum Session {
+  /// A world generated and saved on this device.
+  Local { save: WorldSave, last_save: Instant },
+  /// A world hosted by a multiplayer server, which confirms every change.
+  Remote {
+    connection: ServerConnection,
+    /// Set once the server has accepted the login.
+    player: Option<PlayerId>,
+    /// Blocks the player asked the server to change, which it hasn't answered
+    /// yet.
+    pending_changes: HashMap<BlockPos, Block>,
+    last_position_sent: Instant,
+  },
+  /// A remote world whose server can no longer be reached.
+  Disconnected,
 }
 
+/// Runs the [`Model`] from the player's input, and renders it.
 pub struct Game {
   last: Instant,
-
-  camera: Camera,
-
-  keys_down: HashSet<KeyCode>,
-  mouse_buttons_released: HashSet<MouseButton>,
-
-  blocks: Vec<Vec3>,
-
-  target_cube_index_face: Option<(usize, BoxFace)>,
+  timestep: FixedTimestep,
+  session: Session,
+  model: Model,
+
+  /// Where the controls and settings files are kept.
+  config: Storage,
+  bindings: Bindings,
+  settings: Settings,
+  input: InputState,
+  gamepads: Gamepads,
+  /// Lines scrolled which haven't yet moved the hotbar selection, positive
+  /// when scrolling up.
+  scrolled_lines: f32,
 
   show_debug_display: bool,
   frame_times: Vec<Duration>,
   frame_time_stale_index: usize,
 
-  font_atlas: FontAtlas,
-
-  surface: Surface<'static>,
-  device: Device,
-  queue: Queue,
-  config: SurfaceConfiguration,
-  default_sampler: Sampler,
-
-  screen: ScreenSpaceResources,
-
-  transform_buffer: Buffer,
-  transform_bind_group: BindGroup,
-  pipeline: RenderPipeline,
-  vertex_buffer: Buffer,
-  grass_bind_group: BindGroup,
-
-  outline_transform_buffer: Buffer,
-  outline_transform_bind_group: BindGroup,
-  outline_pipeline: RenderPipeline,
-
-  skybox_transform_buffer: Buffer,
-  skybox_transform_bind_group: BindGroup,
-  skybox_pipeline: RenderPipeline,
-
-  fullscreen_copy_texture_bind_group_layout: BindGroupLayout,
-  fullscreen_copy_pipeline: RenderPipeline,
-
-  crosshair_size: u32,
-  crosshair_quad_buffer: Buffer,
-  crosshair_bind_group: BindGroup,
-  crosshair_pipeline: RenderPipeline,
-
-  text_buffer: Option<Buffer>,
-  text_bind_group: BindGroup,
-  text_pipeline: RenderPipeline,
+  renderer: Renderer,
 }
 
 impl Game {
   pub async fn new(window: Arc<Window>) -> Result<Self> {
-    let instance = Instance::new(&InstanceDescriptor {
-      backends: Backends::all(),
-      ..Default::default()
-    });
-    let surface = instance.create_surface(Arc::clone(&window))?;
-    let adapter = instance
-      .request_adapter(&RequestAdapterOptions {
-        power_preference: PowerPreference::default(),
-        force_fallback_adapter: false,
-        compatible_surface: Some(&surface),
-      })
-      .await?;
-
-    let (device, queue) = adapter
-      .request_device(&DeviceDescriptor {
-        label: None,
-        required_features: Features::empty(),
-        required_limits: Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
-        experimental_features: ExperimentalFeatures::disabled(),
-        memory_hints: MemoryHints::Performance,
-        trace: Trace::Off,
-      })
-      .await?;
-
-    let capabilities = surface.get_capabilities(&adapter);
-    let surface_format = capabilities
-      .formats
-      .iter()
-      .copied()
-      .find(TextureFormat::is_srgb)
-      .unwrap_or(capabilities.formats[0]);
-    let size = window.inner_size();
-    let config = SurfaceConfiguration {
-      usage: TextureUsages::RENDER_ATTACHMENT,
-      format: surface_format,
-      width: size.width,
-      height: size.height,
-      present_mode: PresentMode::AutoVsync,
-      desired_maximum_frame_latency: 3,
-      alpha_mode: capabilities.alpha_modes[0],
-      view_formats: Vec::new(),
-    };
-
-    surface.configure(&device, &config);
-
-    let default_sampler = device.create_sampler(&SamplerDescriptor::default());
-
-    let assets = ResourceReader::new()?;
-
-    let grass_image = assets.load_texture(Texture::Grass).await?;
-    let grass_rgba = grass_image.to_rgba8();
-    let (grass_width, grass_height) = grass_image.dimensions();
-
-    let grass_texture = device.create_texture_with_data(
-      &queue,
-      &TextureDescriptor {
-        label: Some("Grass Texture"),
-        size: Extent3d {
-          width: grass_width,
-          height: grass_height,
-          depth_or_array_layers: 1,
-        },
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: TextureDimension::D2,
-        format: TextureFormat::Rgba8UnormSrgb,
-        usage: TextureUsages::TEXTURE_BINDING,
-        view_formats: &[],
-      },
-      TextureDataOrder::default(),
-      &grass_rgba,
-    );
-
-    let grass_texture_view = grass_texture.create_view(&TextureViewDescriptor::default());
-    let grass_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-      label: Some("Grass Bind Group Layout"),
-      entries: &[
-        BindGroupLayoutEntry {
-          binding: 0,
-          visibility: ShaderStages::FRAGMENT,
-          ty: BindingType::Texture {
-            sample_type: TextureSampleType::Float { filterable: true },
-            view_dimension: TextureViewDimension::D2,
-            multisampled: false,
+    let config = Storage::new()?;
+    let settings = Settings::load(&config);
+    let renderer = Renderer::new(window, &settings).await?;
+
+    let (session, terrain_generator, time, camera, world) = match platform::server_address() {
+      Some(address) => {
+        info!("Connecting to {}", address);
+        let connection = ServerConnection::connect(&address, PLAYER_NAME)
+          .with_context(|| format!("could not connect to {address}"))?;
+
+        // The world's seed and time and the player's position arrive with the
+        // login
+        (
+          Session::Remote {
+            connection,
+            player: None,
+            pending_changes: HashMap::new(),
+            last_position_sent: Instant::now(),
           },
-          count: None,
-        },
-        BindGroupLayoutEntry {
-          binding: 1,
-          visibility: ShaderStages::FRAGMENT,
-          ty: BindingType::Sampler(SamplerBindingType::Filtering),
-          count: None,
-        },
-      ],
-    });
-    let grass_bind_group = device.create_bind_group(&BindGroupDescriptor {
-      label: Some("Grass Bind Group"),
-      layout: &grass_bind_group_layout,
-      entries: &[
-        BindGroupEntry {
-          binding: 0,
-          resource: BindingResource::TextureView(&grass_texture_view),
-        },
-        BindGroupEntry {
-          binding: 1,
-          resource: BindingResource::Sampler(&default_sampler),
-        },
-      ],
-    });
-
-    let transform_buffer = device.create_buffer(&BufferDescriptor {
-      label: Some("Model -> Clip Space Transform Buffer"),
-      size: (mem::size_of::<Mat4x4>() * BLOCK_LIMIT).coerce(),
-      usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-      mapped_at_creation: false,
-    });
-    let transform_buffer_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-      label: Some("Transform Buffer Bind Group Layout"),
-      entries: &[BindGroupLayoutEntry {
-        binding: 0,
-        visibility: ShaderStages::VERTEX,
-        ty: BindingType::Buffer {
-          ty: BufferBindingType::Uniform,
-          has_dynamic_offset: false,
-          min_binding_size: None,
-        },
-        count: None,
-      }],
-    });
-    let transform_bind_group = device.create_bind_group(&BindGroupDescriptor {
-      label: Some("Transform Buffer Bind Group"),
-      layout: &transform_buffer_layout,
-      entries: &[BindGroupEntry {
-        binding: 0,
-        resource: transform_buffer.as_entire_binding(),
-      }],
-    });
-
-    let shader = device.create_shader_module(include_wgsl!("shaders/cube.wgsl"));
-    let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-      label: Some("Render Pipeline Layout"),
-      bind_group_layouts: &[&transform_buffer_layout, &grass_bind_group_layout],
-      immediate_size: 0,
-    });
-    let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-      label: Some("Render Pipeline"),
-      layout: Some(&layout),
-      vertex: VertexState {
-        module: &shader,
-        entry_point: Some("vs_main"),
-        compilation_options: PipelineCompilationOptions::default(),
-        buffers: &[VertexBufferLayout {
-          array_stride: mem::size_of::<Vertex>().coerce(),
-          step_mode: VertexStepMode::Vertex,
-          attributes: &vertex_attr_array![0 => Float32x3, 1 => Float32x2],
-        }],
-      },
-      fragment: Some(FragmentState {
-        module: &shader,
-        entry_point: Some("fs_main"),
-        compilation_options: PipelineCompilationOptions::default(),
-        targets: &[Some(ColorTargetState {
-          format: config.format,
-          blend: Some(BlendState::REPLACE),
-          write_mask: ColorWrites::ALL,
-        })],
-      }),
-      primitive: PrimitiveState {
-        topology: PrimitiveTopology::TriangleList,
-        strip_index_format: None,
-        front_face: FrontFace::Ccw,
-        cull_mode: Some(Face::Back),
-        unclipped_depth: false,
-        polygon_mode: PolygonMode::Fill,
-        conservative: false,
-      },
-      depth_stencil: Some(DepthStencilState {
-        format: DEPTH_FORMAT,
-        depth_write_enabled: true,
-        depth_compare: CompareFunction::Less,
-        stencil: StencilState::default(),
-        bias: DepthBiasState::default(),
-      }),
-      multisample: MultisampleState {
-        count: 1,
-        mask: !0,
-        alpha_to_coverage_enabled: false,
-      },
-      multiview_mask: None,
-      cache: None,
-    });
-
-    let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-      label: Some("Vertex Buffer"),
-      contents: VERTICES.as_bytes(),
-      usage: BufferUsages::VERTEX,
-    });
-
-    let outline_transform_buffer = device.create_buffer(&BufferDescriptor {
-      label: Some("Model -> Clip Space Transform Buffer"),
-      size: mem::size_of::<Mat4x4>().coerce(),
-      usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-      mapped_at_creation: false,
-    });
-    let outline_transform_buffer_layout =
-      device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-        label: Some("Transform Buffer Bind Group Layout"),
-        entries: &[BindGroupLayoutEntry {
-          binding: 0,
-          visibility: ShaderStages::VERTEX,
-          ty: BindingType::Buffer {
-            ty: BufferBindingType::Uniform,
-            has_dynamic_offset: false,
-            min_binding_size: None,
-          },
-          count: None,
-        }],
-      });
-    let outline_transform_bind_group = device.create_bind_group(&BindGroupDescriptor {
-      label: Some("Transform Buffer Bind Group"),
-      layout: &outline_transform_buffer_layout,
-      entries: &[BindGroupEntry {
-        binding: 0,
-        resource: outline_transform_buffer.as_entire_binding(),
-      }],
-    });
-
-    let outline_shader = device.create_shader_module(include_wgsl!("shaders/cube_outline.wgsl"));
-    let outline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-      label: Some("Render Pipeline Layout"),
-      bind_group_layouts: &[&outline_transform_buffer_layout],
-      immediate_size: 0,
-    });
-    let outline_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-      label: Some("Render Pipeline"),
-      layout: Some(&outline_layout),
-      vertex: VertexState {
-        module: &outline_shader,
-        entry_point: Some("vs_main"),
-        compilation_options: PipelineCompilationOptions::default(),
-        buffers: &[VertexBufferLayout {
-          array_stride: mem::size_of::<Vertex>().coerce(),
-          step_mode: VertexStepMode::Vertex,
-          attributes: &vertex_attr_array![0 => Float32x3],
-        }],
-      },
-      fragment: Some(FragmentState {
-        module: &outline_shader,
-        entry_point: Some("fs_main"),
-        compilation_options: PipelineCompilationOptions::default(),
-        targets: &[Some(ColorTargetState {
-          format: config.format,
-          blend: Some(BlendState::REPLACE),
-          write_mask: ColorWrites::ALL,
-        })],
-      }),
-      primitive: PrimitiveState {
-        topology: PrimitiveTopology::TriangleList,
-        strip_index_format: None,
-        front_face: FrontFace::Ccw,
-        cull_mode: Some(Face::Back),
-        unclipped_depth: false,
-        polygon_mode: PolygonMode::Fill,
-        conservative: false,
-      },
-      depth_stencil: Some(DepthStencilState {
-        format: DEPTH_FORMAT,
-        depth_write_enabled: true,
-        depth_compare: CompareFunction::Less,
-        stencil: StencilState::default(),
-        bias: DepthBiasState::default(),
-      }),
-      multisample: MultisampleState {
-        count: 1,
-        mask: !0,
-        alpha_to_coverage_enabled: false,
-      },
-      multiview_mask: None,
-      cache: None,
-    });
-
-    let skybox_transform_buffer = device.create_buffer(&BufferDescriptor {
-      label: Some("Skybox Model -> Clip Space Transform Buffer"),
-      size: mem::size_of::<Mat4x4>().coerce(),
-      usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-      mapped_at_creation: false,
-    });
-    let skybox_transform_buffer_layout =
-      device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-        label: Some("Skybox Transform Buffer Bind Group Layout"),
-        entries: &[BindGroupLayoutEntry {
-          binding: 0,
-          visibility: ShaderStages::VERTEX,
-          ty: BindingType::Buffer {
-            ty: BufferBindingType::Uniform,
-            has_dynamic_offset: false,
-            min_binding_size: None,
-          },
-          count: None,
-        }],
-      });
-    let skybox_transform_bind_group = device.create_bind_group(&BindGroupDescriptor {
-      label: Some("Skybox Transform Buffer Bind Group"),
-      layout: &skybox_transform_buffer_layout,
-      entries: &[BindGroupEntry {
-        binding: 0,
-        resource: skybox_transform_buffer.as_entire_binding(),
-      }],
-    });
-
-    let skybox_shader = device.create_shader_module(include_wgsl!("shaders/skybox.wgsl"));
-    let skybox_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-      label: Some("Skybox Render Pipeline Layout"),
-      bind_group_layouts: &[&skybox_transform_buffer_layout],
-      immediate_size: 0,
-    });
-    let skybox_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-      label: Some("Skybox Render Pipeline"),
-      layout: Some(&skybox_layout),
-      vertex: VertexState {
-        module: &skybox_shader,
-        entry_point: Some("vs_main"),
-        compilation_options: PipelineCompilationOptions::default(),
-        buffers: &[VertexBufferLayout {
-          array_stride: mem::size_of::<Vertex>().coerce(),
-          step_mode: VertexStepMode::Vertex,
-          attributes: &vertex_attr_array![0 => Float32x3],
-        }],
-      },
-      fragment: Some(FragmentState {
-        module: &skybox_shader,
-        entry_point: Some("fs_main"),
-        compilation_options: PipelineCompilationOptions::default(),
-        targets: &[Some(ColorTargetState {
-          format: config.format,
-          blend: Some(BlendState::REPLACE),
-          write_mask: ColorWrites::ALL,
-        })],
-      }),
-      primitive: PrimitiveState {
-        topology: PrimitiveTopology::TriangleList,
-        strip_index_format: None,
-        front_face: FrontFace::Cw,
-        cull_mode: Some(Face::Back),
-        unclipped_depth: false,
-        polygon_mode: PolygonMode::Fill,
-        conservative: false,
-      },
-      depth_stencil: Some(DepthStencilState {
-        format: DEPTH_FORMAT,
-        depth_write_enabled: false,
-        depth_compare: CompareFunction::Always,
-        stencil: StencilState::default(),
-        bias: DepthBiasState::default(),
-      }),
-      multisample: MultisampleState {
-        count: 1,
-        mask: !0,
-        alpha_to_coverage_enabled: false,
-      },
-      multiview_mask: None,
-      cache: None,
-    });
-
-    let fullscreen_copy_texture_bind_group_layout =
-      device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-        label: Some("Fullscreen Copy Bind Group Layout"),
-        entries: &[
-          BindGroupLayoutEntry {
-            binding: 0,
-            visibility: ShaderStages::FRAGMENT,
-            ty: BindingType::Texture {
-              sample_type: TextureSampleType::Float { filterable: false },
-              view_dimension: TextureViewDimension::D2,
-              multisampled: false,
-            },
-            count: None,
-          },
-          BindGroupLayoutEntry {
-            binding: 1,
-            visibility: ShaderStages::FRAGMENT,
-            ty: BindingType::Sampler(SamplerBindingType::NonFiltering),
-            count: None,
-          },
-        ],
-      });
-    let fullscreen_copy_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-      label: Some("Fullscreen Copy Render Pipeline Layout"),
-      bind_group_layouts: &[&fullscreen_copy_texture_bind_group_layout],
-      immediate_size: 0,
-    });
-    let fullscreen_copy_shader =
-      device.create_shader_module(include_wgsl!("shaders/fullscreen_copy.wgsl"));
-    let fullscreen_copy_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-      label: Some("Fullscreen Copy Render Pipeline"),
-      layout: Some(&fullscreen_copy_layout),
-      vertex: VertexState {
-        module: &fullscreen_copy_shader,
-        entry_point: Some("vs_main"),
-        compilation_options: PipelineCompilationOptions::default(),
-        buffers: &[],
-      },
-      fragment: Some(FragmentState {
-        module: &fullscreen_copy_shader,
-        entry_point: Some("fs_main"),
-        compilation_options: PipelineCompilationOptions::default(),
-        targets: &[Some(ColorTargetState {
-          format: config.format,
-          blend: Some(BlendState::REPLACE),
-          write_mask: ColorWrites::ALL,
-        })],
-      }),
-      primitive: PrimitiveState {
-        topology: PrimitiveTopology::TriangleStrip,
-        strip_index_format: None,
-        front_face: FrontFace::Ccw,
-        cull_mode: None,
-        unclipped_depth: false,
-        polygon_mode: PolygonMode::Fill,
-        conservative: false,
-      },
-      depth_stencil: None,
-      multisample: MultisampleState {
-        count: 1,
-        mask: !0,
-        alpha_to_coverage_enabled: false,
-      },
-      multiview_mask: None,
-      cache: None,
-    });
-
-    let crosshair_image = assets.load_texture(Texture::Crosshair).await?;
-    let crosshair_alpha = crosshair_image.to_luma8();
-    let (crosshair_width, crosshair_height) = crosshair_image.dimensions();
-
-    let crosshair_texture = device.create_texture_with_data(
-      &queue,
-      &TextureDescriptor {
-        label: Some("Crosshair Alpha Texture"),
-        size: Extent3d {
-          width: crosshair_width,
-          height: crosshair_height,
-          depth_or_array_layers: 1,
-        },
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: TextureDimension::D2,
-        format: TextureFormat::R8Unorm,
-        usage: TextureUsages::TEXTURE_BINDING,
-        view_formats: &[],
-      },
-      TextureDataOrder::default(),
-      &crosshair_alpha,
-    );
-
-    let crosshair_quad_buffer = device.create_buffer_init(&BufferInitDescriptor {
-      label: Some("Crosshair Normalised Size Buffer"),
-      contents: calculate_crosshair_quad(
-        Vec2::new(config.width.coerce_lossy(), config.height.coerce_lossy()),
-        crosshair_width,
-      )
-      .as_bytes(),
-      usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-    });
-
-    let crosshair_texture_view = crosshair_texture.create_view(&TextureViewDescriptor::default());
-    let crosshair_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-      label: Some("Crosshair Bind Group Layout"),
-      entries: &[
-        BindGroupLayoutEntry {
-          binding: 0,
-          visibility: ShaderStages::VERTEX,
-          ty: BindingType::Buffer {
-            ty: BufferBindingType::Uniform,
-            has_dynamic_offset: false,
-            min_binding_size: None,
-          },
-          count: None,
-        },
-        BindGroupLayoutEntry {
-          binding: 1,
-          visibility: ShaderStages::FRAGMENT,
-          ty: BindingType::Texture {
-            sample_type: TextureSampleType::Float { filterable: true },
-            view_dimension: TextureViewDimension::D2,
-            multisampled: false,
-          },
-          count: None,
-        },
-        BindGroupLayoutEntry {
-          binding: 2,
-          visibility: ShaderStages::FRAGMENT,
-          ty: BindingType::Sampler(SamplerBindingType::Filtering),
-          count: None,
-        },
-      ],
-    });
-    let crosshair_bind_group = device.create_bind_group(&BindGroupDescriptor {
-      label: Some("Crosshair Bind Group"),
-      layout: &crosshair_bind_group_layout,
-      entries: &[
-        BindGroupEntry {
-          binding: 0,
-          resource: BindingResource::Buffer(crosshair_quad_buffer.as_entire_buffer_binding()),
-        },
-        BindGroupEntry {
-          binding: 1,
-          resource: BindingResource::TextureView(&crosshair_texture_view),
-        },
-        BindGroupEntry {
-          binding: 2,
-          resource: BindingResource::Sampler(&default_sampler),
-        },
-      ],
-    });
-    let crosshair_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-      label: Some("Crosshair Render Pipeline Layout"),
-      bind_group_layouts: &[
-        &fullscreen_copy_texture_bind_group_layout,
-        &crosshair_bind_group_layout,
-      ],
-      immediate_size: 0,
-    });
-    let crosshair_shader = device.create_shader_module(include_wgsl!("shaders/crosshair.wgsl"));
-    let crosshair_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-      label: Some("Crosshair Render Pipeline"),
-      layout: Some(&crosshair_layout),
-      vertex: VertexState {
-        module: &crosshair_shader,
-        entry_point: Some("vs_main"),
-        compilation_options: PipelineCompilationOptions::default(),
-        buffers: &[],
-      },
-      fragment: Some(FragmentState {
-        module: &crosshair_shader,
-        entry_point: Some("fs_main"),
-        compilation_options: PipelineCompilationOptions::default(),
-        targets: &[Some(ColorTargetState {
-          format: config.format,
-          blend: Some(BlendState::ALPHA_BLENDING),
-          write_mask: ColorWrites::ALL,
-        })],
-      }),
-      primitive: PrimitiveState {
-        topology: PrimitiveTopology::TriangleStrip,
-        strip_index_format: None,
-        front_face: FrontFace::Ccw,
-        cull_mode: None,
-        unclipped_depth: false,
-        polygon_mode: PolygonMode::Fill,
-        conservative: false,
-      },
-      depth_stencil: None,
-      multisample: MultisampleState {
-        count: 1,
-        mask: !0,
-        alpha_to_coverage_enabled: false,
-      },
-      multiview_mask: None,
-      cache: None,
-    });
-
-    let (font_atlas, font_atlas_alpha) = FontAtlas::load(&assets, FONT_SCALE).await?;
-    let (font_atlas_width, font_atlas_height) = font_atlas.dimensions();
-
-    let font_atlas_texture = device.create_texture_with_data(
-      &queue,
-      &TextureDescriptor {
-        label: Some("Font Atlas Alpha Texture"),
-        size: Extent3d {
-          width: font_atlas_width,
-          height: font_atlas_height,
-          depth_or_array_layers: 1,
-        },
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: TextureDimension::D2,
-        format: TextureFormat::R8Unorm,
-        usage: TextureUsages::TEXTURE_BINDING,
-        view_formats: &[],
-      },
-      TextureDataOrder::default(),
-      &font_atlas_alpha,
-    );
+          TerrainGenerator::new(DEFAULT_SEED),
+          WorldTime::default(),
+          Camera::new(Vec3::default(), Angle::default(), Angle::default()),
+          World::new(),
+        )
+      }
+      None => {
+        let save = WorldSave::new(Storage::new()?);
+        let (terrain_generator, time, camera) = match save.read_metadata()? {
+          Some(metadata) => (
+            TerrainGenerator::new(metadata.seed),
+            metadata.time,
+            Camera::new(
+              metadata.camera_position,
+              metadata.camera_yaw,
+              metadata.camera_pitch,
+            ),
+          ),
+          None => {
+            let terrain_generator = TerrainGenerator::new(DEFAULT_SEED);
+            let spawn = terrain_generator.spawn_position();
+
+            (
+              terrain_generator,
+              WorldTime::default(),
+              Camera::new(
+                spawn + Vec3::new(0.0, Player::EYE_HEIGHT, 0.0),
+                Angle::default(),
+                Angle::default(),
+              ),
+            )
+          }
+        };
 
-    let font_atlas_view = font_atlas_texture.create_view(&TextureViewDescriptor::default());
-    let text_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-      label: Some("Text Bind Group Layout"),
-      entries: &[
-        BindGroupLayoutEntry {
-          binding: 0,
-          visibility: ShaderStages::FRAGMENT,
-          ty: BindingType::Texture {
-            sample_type: TextureSampleType::Float { filterable: true },
-            view_dimension: TextureViewDimension::D2,
-            multisampled: false,
+        (
+          Session::Local {
+            save,
+            last_save: Instant::now(),
           },
-          count: None,
-        },
-        BindGroupLayoutEntry {
-          binding: 1,
-          visibility: ShaderStages::FRAGMENT,
-          ty: BindingType::Sampler(SamplerBindingType::Filtering),
-          count: None,
-        },
-      ],
-    });
-    let text_bind_group = device.create_bind_group(&BindGroupDescriptor {
-      label: Some("Text Bind Group"),
-      layout: &text_bind_group_layout,
-      entries: &[
-        BindGroupEntry {
-          binding: 0,
-          resource: BindingResource::TextureView(&font_atlas_view),
-        },
-        BindGroupEntry {
-          binding: 1,
-          resource: BindingResource::Sampler(&default_sampler),
-        },
-      ],
-    });
-    let text_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-      label: Some("Text Render Pipeline Layout"),
-      bind_group_layouts: &[&text_bind_group_layout],
-      immediate_size: 0,
-    });
-    let text_shader = device.create_shader_module(include_wgsl!("shaders/text.wgsl"));
-    let text_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-      label: Some("Text Render Pipeline"),
-      layout: Some(&text_layout),
-      vertex: VertexState {
-        module: &text_shader,
-        entry_point: Some("vs_main"),
-        compilation_options: PipelineCompilationOptions::default(),
-        buffers: &[VertexBufferLayout {
-          array_stride: mem::size_of::<TextVertex>().coerce(),
-          step_mode: VertexStepMode::Vertex,
-          attributes: &vertex_attr_array![0 => Float32x2, 1 => Float32x2],
-        }],
-      },
-      fragment: Some(FragmentState {
-        module: &text_shader,
-        entry_point: Some("fs_main"),
-        compilation_options: PipelineCompilationOptions::default(),
-        targets: &[Some(ColorTargetState {
-          format: config.format,
-          blend: Some(BlendState::ALPHA_BLENDING),
-          write_mask: ColorWrites::ALL,
-        })],
-      }),
-      primitive: PrimitiveState {
-        topology: PrimitiveTopology::TriangleList,
-        strip_index_format: None,
-        front_face: FrontFace::Ccw,
-        cull_mode: None,
-        unclipped_depth: false,
-        polygon_mode: PolygonMode::Fill,
-        conservative: false,
-      },
-      depth_stencil: None,
-      multisample: MultisampleState {
-        count: 1,
-        mask: !0,
-        alpha_to_coverage_enabled: false,
-      },
-      multiview_mask: None,
-      cache: None,
-    });
-
-    let screen = ScreenSpaceResources::construct(
-      &device,
-      &config,
-      &fullscreen_copy_texture_bind_group_layout,
-      &default_sampler,
-    );
+          terrain_generator,
+          time,
+          camera,
+          World::with_terrain(terrain_generator),
+        )
+      }
+    };
 
     Ok(Self {
       last: Instant::now(),
-      camera: Camera::new(),
-      keys_down: HashSet::new(),
-      mouse_buttons_released: HashSet::new(),
-      blocks: Vec::from([CUBE_TRANSLATE]),
-      target_cube_index_face: None,
+      timestep: FixedTimestep::new(TICK_DURATION, MAX_TICKS_PER_FRAME),
+      session,
+      model: Model::new(world, terrain_generator, time, camera),
+      bindings: Bindings::load(&config),
+      settings,
+      config,
+      input: InputState::default(),
+      gamepads: Gamepads::new(),
+      scrolled_lines: 0.0,
       show_debug_display: cfg!(debug_assertions),
       frame_times: Vec::with_capacity(FRAME_TIME_MEASUREMENTS),
       frame_time_stale_index: 0,
-      font_atlas,
-      surface,
-      device,
-      queue,
-      config,
-      default_sampler,
-      screen,
-      transform_buffer,
-      transform_bind_group,
-      pipeline,
-      vertex_buffer,
-      grass_bind_group,
-      outline_transform_buffer,
-      outline_transform_bind_group,
-      outline_pipeline,
-      skybox_transform_buffer,
-      skybox_transform_bind_group,
-      skybox_pipeline,
-      fullscreen_copy_texture_bind_group_layout,
-      fullscreen_copy_pipeline,
-      crosshair_size: crosshair_width,
-      crosshair_quad_buffer,
-      crosshair_bind_group,
-      crosshair_pipeline,
-      text_buffer: None,
-      text_bind_group,
-      text_pipeline,
+      renderer,
     })
   }
 
-  fn screen_size(&self) -> Vec2 {
-    Vec2::new(
-      self.config.width.coerce_lossy(),
-      self.config.height.coerce_lossy(),
-    )
+  pub fn resize(&mut self, size: PhysicalSize<u32>) {
+    self.renderer.resize(size);
   }
 
-  pub fn resize(&mut self, PhysicalSize { width, height }: PhysicalSize<u32>) {
-    assert!(
-      (width != 0) && (height != 0),
-      "new window size had a 0 component: ({}, {})",
-      width,
-      height
-    );
-
-    self.config.width = width;
-    self.config.height = height;
-
-    self.surface.configure(&self.device, &self.config);
+  pub fn compose(&mut self) -> Result<()> {
+    let elapsed = self.last.elapsed();
+    self.last = Instant::now();
 
-    self.screen = ScreenSpaceResources::construct(
-      &self.device,
-      &self.config,
-      &self.fullscreen_copy_texture_bind_group_layout,
-      &self.default_sampler,
-    );
+    if self.show_debug_display {
+      if self.frame_times.len() < FRAME_TIME_MEASUREMENTS {
+        self.frame_times.push(elapsed);
+      } else {
+        self.frame_times[self.frame_time_stale_index] = elapsed;
+        self.frame_time_stale_index = (self.frame_time_stale_index + 1) % FRAME_TIME_MEASUREMENTS;
+      }
+    }
 
-    self.queue.write_buffer(
-      &self.crosshair_quad_buffer,
-      0,
-      calculate_crosshair_quad(
-        Vec2::new(width.coerce_lossy(), height.coerce_lossy()),
-        self.crosshair_size,
-      )
-      .as_bytes(),
+    self.gamepads.poll(&mut self.input);
+    let (yaw, pitch) = self.bindings.gamepad().look_rotation(
+      gamepad::stick(&self.input, Axis::RightStickX, Axis::RightStickY),
+      elapsed,
     );
-  }
+    self.model.rotate_camera(yaw, pitch);
 
-  pub fn compose(&mut self) -> Result<()> {
-    let elapsed = self.last.elapsed();
-    self.last = Instant::now();
+    for _ in 0..self.timestep.advance(elapsed) {
+      self.tick();
+    }
+    self.update_session();
+
+    let debug_display = self.show_debug_display.then(|| {
+      let mean_frame_time_ms = self
+        .frame_times
+        .iter()
+        .map(Duration::as_millis_f32)
+        .sum::<f32>()
+        / self.frame_times.len().coerce_lossy();
+
+      DebugDisplay {
+        frames_per_second: 1000.0 / mean_frame_time_ms,
+        mean_frame_time_ms,
+        cursor_position: self.input.cursor_position(),
+      }
+    });
+    let view_direction = if self.bindings.is_held(&self.input, Action::LookBehind) {
+      Direction::Backward
+    } else {
+      Direction::Forward
+    };
 
-    self.update(elapsed);
-    self.render()?;
+    let scene = Scene {
+      debug_display,
+      ..self.model.scene(self.timestep.alpha())
+    };
+    self.renderer.render(&scene, view_direction)
+  }
 
-    Ok(())
+  pub fn bindings(&self) -> &Bindings {
+    &self.bindings
   }
 
-  pub fn press(&mut self, code: KeyCode) {
-    self.keys_down.insert(code);
+  pub fn press(&mut self, button: Button) {
+    self.input.press(button);
   }
 
-  pub fn release(&mut self, code: KeyCode) {
-    if code == KeyCode::F3 {
+  pub fn release(&mut self, button: Button) {
+    if self.bindings.is_bound(Action::ToggleDebugDisplay, button) {
       self.show_debug_display = !self.show_debug_display;
     }
+    // Only the server can change the time of a remote world
+    if self.show_debug_display
+      && matches!(self.session, Session::Local { .. })
+      && self.bindings.is_bound(Action::SkipTime, button)
+    {
+      let mut time = self.model.time();
+      time.advance(TIME_SKIPPED);
+      self.model.set_time(time);
+    }
+
+    self.input.release(button);
+  }
 
-    self.keys_down.remove(&code);
+  /// Scrolls by `lines` horizontally and vertically, positive when scrolling
+  /// right and up.
+  pub fn scroll(&mut self, lines: Vec2) {
+    self.input.scroll(lines);
   }
 
-  pub fn mouse_release(&mut self, button: MouseButton) {
-    self.mouse_buttons_released.insert(button);
+  /// Moves the cursor to `position` in the window, or [`None`] if it left the
+  /// window.
+  pub fn move_cursor(&mut self, position: Option<Vec2>) {
+    self.input.move_cursor(position);
   }
 
   pub fn motion(&mut self, direction: Vec2) {
     const MOVEMENT_SPEED: Angle = FULL_ROTATION;
 
-    let delta = direction.normalise_components_to(self.screen_size());
+    let delta = self.settings.mouse_sensitivity
+      * direction.normalise_components_to(self.renderer.screen_size());
 
     self
-      .camera
-      .rotate(MOVEMENT_SPEED * delta.x(), MOVEMENT_SPEED * delta.y());
+      .model
+      .rotate_camera(MOVEMENT_SPEED * delta.x(), MOVEMENT_SPEED * delta.y());
   }
 
-  fn update(&mut self, delta: Duration) {
-    const CAMERA_MOVEMENT_SPEED: f32 = 10.0;
-    const REACH_DISTANCE: f32 = 5.0;
+  /// Reloads the controls and settings files and applies them, so that they
+  /// can be edited while the game runs.
+  pub fn reload_config(&mut self) {
+    self.bindings = Bindings::load(&self.config);
+    self.settings = Settings::load(&self.config);
+    self.renderer.apply_settings(&self.settings);
+  }
 
-    if self.show_debug_display {
-      if self.frame_times.len() < FRAME_TIME_MEASUREMENTS {
-        self.frame_times.push(delta);
-      } else {
-        self.frame_times[self.frame_time_stale_index] = delta;
-        self.frame_time_stale_index = (self.frame_time_stale_index + 1) % FRAME_TIME_MEASUREMENTS;
-      }
+  /// Saves a local world, overwriting any previous save. Remote worlds are
+  /// saved by their server instead.
+  pub fn save(&mut self) -> Result<()> {
+    match &mut self.session {
+      Session::Local { save, .. } => save.write(&self.model.metadata(), self.model.world_mut()),
+      Session::Remote { .. } | Session::Disconnected => Ok(()),
     }
+  }
 
-    let delta_secs = delta.as_secs_f32();
-
-    let mut camera_movement = Vec3::default();
-    if self.keys_down.contains(&KeyCode::KeyW) {
-      camera_movement += Z_AXIS;
-    }
-    if self.keys_down.contains(&KeyCode::KeyS) {
-      camera_movement -= Z_AXIS;
-    }
-    if self.keys_down.contains(&KeyCode::KeyA) {
-      camera_movement -= X_AXIS;
-    }
-    if self.keys_down.contains(&KeyCode::KeyD) {
-      camera_movement += X_AXIS;
-    }
-    if self.keys_down.contains(&KeyCode::Space) {
-      camera_movement += Y_AXIS;
+  /// Advances the model by a tick with the input given since the last tick.
+  fn tick(&mut self) {
+    let bindings = &self.bindings;
+    let input = &self.input;
+
+    let mut movement = Vec3::default();
+    for (action, direction) in [
+      (Action::MoveForward, Z_AXIS),
+      (Action::MoveBackward, -Z_AXIS),
+      (Action::MoveLeft, -X_AXIS),
+      (Action::MoveRight, X_AXIS),
+      (Action::Jump, Y_AXIS),
+      (Action::Descend, -Y_AXIS),
+    ] {
+      if bindings.is_down(input, action) {
+        movement += direction;
+      }
     }
-    if self.keys_down.contains(&KeyCode::ShiftLeft) {
-      camera_movement -= Y_AXIS;
+    let left_stick =
+      bindings
+        .gamepad()
+        .apply_dead_zone(gamepad::stick(input, Axis::LeftStickX, Axis::LeftStickY));
+    movement += Vec3::new(left_stick.x(), 0.0, left_stick.y());
+
+    // Scrolling down moves the selection right
+    self.scrolled_lines += input.wheel_delta().y();
+    let scrolled_lines: i32 = self.scrolled_lines.trunc().coerce_lossy();
+    self.scrolled_lines = self.scrolled_lines.fract();
+    let mut scroll_slots = -scrolled_lines;
+    if bindings.was_pressed(input, Action::NextSlot) {
+      scroll_slots += 1;
     }
-    if camera_movement.len_sq() > 0.0 {
-      self
-        .camera
-        .translate(CAMERA_MOVEMENT_SPEED * delta_secs * camera_movement.norm());
+    if bindings.was_pressed(input, Action::PreviousSlot) {
+      scroll_slots -= 1;
     }
 
-    if let Some((index, face)) = self.target_cube_index_face {
-      if self.mouse_buttons_released.contains(&MouseButton::Left) {
-        self.blocks.swap_remove(index);
-      } else if self.mouse_buttons_released.contains(&MouseButton::Right)
-        && (self.blocks.len() < BLOCK_LIMIT)
-      {
-        let target_block = self.blocks.get(index).unwrap();
-        let next_block = *target_block + (CUBE_SIZE * face.normal());
+    let input = Input {
+      movement,
+      jump: bindings.is_down(input, Action::Jump),
+      break_block: bindings.is_down(input, Action::BreakBlock),
+      place_block: bindings.is_down(input, Action::PlaceBlock),
+      toggle_movement_mode: bindings.was_released(input, Action::ToggleMovementMode),
+      select_slot: (0..HOTBAR_SLOTS)
+        .rev()
+        .find(|&slot| bindings.was_pressed(input, Action::SelectSlot(slot))),
+      scroll_slots,
+    };
+    self.input.clear_transitions();
 
-        self.blocks.push(next_block);
-      }
+    if let Some((pos, block)) = self.model.tick(&input) {
+      self.set_block(pos, block);
     }
+  }
 
-    let position = self.camera.position();
-    let reach = Segment3::start_direction_len(position, self.camera.forward(), REACH_DISTANCE);
+  /// Autosaves and generates a local world, or exchanges messages with the
+  /// server hosting a remote world.
+  fn update_session(&mut self) {
+    match &mut self.session {
+      Session::Local { save, last_save } => {
+        if last_save.elapsed() >= AUTOSAVE_INTERVAL {
+          *last_save = Instant::now();
 
-    self.target_cube_index_face = None;
-    let mut min_dist = f32::MAX;
-    for (index, block) in self.blocks.iter().enumerate() {
-      if let Some(face) = AlignedBox3::cube(*block, CUBE_HALF).find_intersecting_face(&reach) {
-        let dist = Vec3::dist_sq(position, *block);
+          if let Err(err) = save.write(&self.model.metadata(), self.model.world_mut()) {
+            error!("Could not autosave the world: {:?}", err);
+          }
+        }
 
-        if dist < min_dist {
-          self.target_cube_index_face = Some((index, face));
-          min_dist = dist;
+        let render_distance = self.settings.render_distance;
+        if let Err(err) =
+          self
+            .model
+            .load_around_player(save, render_distance, CHUNKS_LOADED_PER_FRAME)
+        {
+          error!("Could not load the world: {:?}", err);
+        }
+        if let Err(err) =
+          self
+            .model
+            .unload_distant_chunks(Some(save), render_distance, UNLOAD_MARGIN)
+        {
+          error!("Could not unload the world: {:?}", err);
         }
       }
-    }
-
-    self.mouse_buttons_released.clear();
-  }
-
-  fn render(&mut self) -> Result<()> {
-    let output = self.surface.get_current_texture()?;
-    let view = output
-      .texture
-      .create_view(&TextureViewDescriptor::default());
-
-    let world_to_screen_space = &self.screen.perspective
-      * &self
-        .camera
-        .world_transform(if self.keys_down.contains(&KeyCode::KeyC) {
-          Direction::Backward
-        } else {
-          Direction::Forward
-        });
-
-    let skybox_transform = &world_to_screen_space * &mat4::translate(self.camera.position());
-    self.queue.write_buffer(
-      &self.skybox_transform_buffer,
-      0,
-      skybox_transform.as_bytes(),
-    );
-
-    let transforms: Vec<Mat4x4> = self
-      .blocks
-      .iter()
-      .map(|block| &world_to_screen_space * &mat4::translate(*block))
-      .collect();
-    self
-      .queue
-      .write_buffer(&self.transform_buffer, 0, transforms.as_bytes());
-
-    let mut encoder = self
-      .device
-      .create_command_encoder(&CommandEncoderDescriptor {
-        label: Some("Render Encoder"),
-      });
-    {
-      let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-        label: Some("Main Render Pass"),
-        color_attachments: &[Some(RenderPassColorAttachment {
-          view: &self.screen.render_view,
-          depth_slice: None,
-          resolve_target: None,
-          ops: Operations {
-            load: LoadOp::Clear(Color::BLACK),
-            store: StoreOp::Store,
-          },
-        })],
-        depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
-          view: &self.screen.depth_view,
-          depth_ops: Some(Operations {
-            load: LoadOp::Clear(1.0),
-            store: StoreOp::Discard,
-          }),
-          stencil_ops: None,
-        }),
-        timestamp_writes: None,
-        occlusion_query_set: None,
-        multiview_mask: None,
-      });
-
-      render_pass.set_pipeline(&self.skybox_pipeline);
-      render_pass.set_bind_group(0, &self.skybox_transform_bind_group, &[]);
-      render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-      render_pass.draw(0..VERTICES.len().coerce(), 0..1);
-
-      render_pass.set_pipeline(&self.pipeline);
-      render_pass.set_bind_group(0, &self.transform_bind_group, &[]);
-      render_pass.set_bind_group(1, &self.grass_bind_group, &[]);
-      render_pass.draw(0..VERTICES.len().coerce(), 0..self.blocks.len().coerce());
-
-      if let Some((index, _)) = self.target_cube_index_face {
-        self.queue.write_buffer(
-          &self.outline_transform_buffer,
-          0,
-          transforms.get(index).unwrap().as_bytes(),
-        );
+      Session::Remote { .. } => {
+        // The server forgets having sent chunks a margin beyond where it sends
+        // them, so unloading them a margin further means it has always
+        // forgotten them by the time they're unloaded, even though it hears
+        // where the player is late
+        if let Err(err) =
+          self
+            .model
+            .unload_distant_chunks(None, GENERATION_RADIUS, 2 * UNLOAD_MARGIN)
+        {
+          error!("Could not unload the world: {:?}", err);
+        }
 
-        render_pass.set_pipeline(&self.outline_pipeline);
-        render_pass.set_bind_group(0, &self.outline_transform_bind_group, &[]);
-        render_pass.draw(0..VERTICES.len().coerce(), 0..1);
+        if let Err(err) = self.sync_with_server() {
+          error!("Lost connection to the server: {:#}", err);
+          self.session = Session::Disconnected;
+        }
       }
+      Session::Disconnected => {}
     }
-    {
-      let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-        label: Some("UI Render Pass"),
-        color_attachments: &[Some(RenderPassColorAttachment {
-          view: &view,
-          depth_slice: None,
-          resolve_target: None,
-          ops: Operations {
-            load: LoadOp::Clear(Color::TRANSPARENT),
-            store: StoreOp::Store,
-          },
-        })],
-        depth_stencil_attachment: None,
-        timestamp_writes: None,
-        occlusion_query_set: None,
-        multiview_mask: None,
-      });
-
-      render_pass.set_pipeline(&self.fullscreen_copy_pipeline);
-      render_pass.set_bind_group(0, &self.screen.fullscreen_copy_texture_bind_group, &[]);
-      render_pass.draw(0..4, 0..1);
-
-      render_pass.set_pipeline(&self.crosshair_pipeline);
-      render_pass.set_bind_group(1, &self.crosshair_bind_group, &[]);
-      render_pass.draw(0..4, 0..1);
-
-      if self.show_debug_display {
-        let mean_frame_time_ms = self
-          .frame_times
-          .iter()
-          .map(Duration::as_millis_f32)
-          .sum::<f32>()
-          / self.frame_times.len().coerce_lossy();
-        let fps_text = format!(
-          "FPS: {} ({:.3}ms)",
-          (1000.0 / mean_frame_time_ms).round(),
-          mean_frame_time_ms
-        );
+  }
 
-        let mut text_vertices = Vec::new();
-        self.font_atlas.push_text_vertices(
-          &fps_text,
-          PhysicalSize::new(5, 5),
-          PhysicalSize::new(self.config.width, self.config.height),
-          &mut text_vertices,
-        );
+  fn sync_with_server(&mut self) -> Result<()> {
+    let Session::Remote {
+      connection,
+      player,
+      pending_changes,
+      last_position_sent,
+    } = &mut self.session
+    else {
+      return Ok(());
+    };
 
-        if let Some(text_buffer) = &self.text_buffer {
-          if text_buffer.size() < core::slice_byte_len(&text_vertices).coerce() {
-            self.create_text_buffer(&text_vertices);
+    while let Some(message) = connection.receive()? {
+      match message {
+        ServerMessage::LoginSuccess {
+          player: id,
+          seed,
+          time,
+          position,
+          yaw,
+          pitch,
+        } => {
+          *player = Some(id);
+          self.model.set_seed(seed);
+          self.model.set_time(time);
+          self.model.teleport(position, yaw, pitch);
+        }
+        ServerMessage::ChunkData { pos, chunk } => {
+          self.model.world_mut().insert_chunk(pos, *chunk);
+        }
+        ServerMessage::BlockChange { pos, block } => {
+          // The server answers every change asked for, with the block left
+          // there whether or not it made the change. Chunks which have been
+          // unloaded are sent again with the change once they're needed.
+          if self.model.world().chunk(pos.chunk()).is_none() {
+            pending_changes.remove(&pos);
+          } else if pending_changes.remove(&pos) == Some(block) {
+            self.model.change_block(pos, block);
           } else {
-            self
-              .queue
-              .write_buffer(text_buffer, 0, text_vertices.as_bytes());
+            self.model.world_mut().set(pos, block);
           }
-        } else {
-          self.create_text_buffer(&text_vertices);
         }
-
-        render_pass.set_pipeline(&self.text_pipeline);
-        render_pass.set_bind_group(0, &self.text_bind_group, &[]);
-        render_pass.set_vertex_buffer(0, self.text_buffer.as_ref().unwrap().slice(..));
-        render_pass.draw(0..text_vertices.len().coerce(), 0..1);
+        ServerMessage::Chat { sender, text } => info!("<{}> {}", sender, text),
+        ServerMessage::Time { time } => self.model.set_time(time),
+        // The server only sends the client's own player back when it rejected
+        // a move, keeping where the player is looking
+        ServerMessage::PlayerPosition {
+          player: moved,
+          position,
+          ..
+        } if Some(moved) == *player => {
+          let camera = self.model.camera();
+          let (yaw, pitch) = (camera.yaw(), camera.pitch());
+          self.model.teleport(position, yaw, pitch);
+        }
+        // Other players aren't drawn yet
+        ServerMessage::PlayerPosition { .. } | ServerMessage::PlayerLeft { .. } => {}
+        // Handled by the connection
+        ServerMessage::Handshake { .. } | ServerMessage::Disconnect { .. } => {}
       }
     }
 
-    self.queue.submit(iter::once(encoder.finish()));
-
-    output.present();
+    if player.is_some() && (last_position_sent.elapsed() >= POSITION_INTERVAL) {
+      *last_position_sent = Instant::now();
+      connection.send(&ClientMessage::PlayerPosition {
+        position: self.model.player().position(),
+        yaw: self.model.camera().yaw(),
+        pitch: self.model.camera().pitch(),
+      })?;
+    }
 
     Ok(())
   }
 
-  fn create_text_buffer(&mut self, text_vertices: &[TextVertex]) {
-    self.text_buffer = Some(self.device.create_buffer_init(&BufferInitDescriptor {
-      label: Some("Text Vertices"),
-      contents: text_vertices.as_bytes(),
-      usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-    }));
+  /// Changes a block in a local world, or asks the server to change it in a
+  /// remote world, which happens once the server confirms it.
+  fn set_block(&mut self, pos: BlockPos, block: Block) {
+    match &mut self.session {
+      Session::Local { .. } => self.model.change_block(pos, block),
+      Session::Remote {
+        connection,
+        pending_changes,
+        ..
+      } => {
+        pending_changes.insert(pos, block);
+        if let Err(err) = connection.send(&ClientMessage::SetBlock { pos, block }) {
+          error!("Lost connection to the server: {:#}", err);
+          self.session = Session::Disconnected;
+        }
+      }
+      Session::Disconnected => {}
+    }
   }
 }
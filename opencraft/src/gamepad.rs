@@ -0,0 +1,131 @@
+//! Gamepads, read through gilrs, which uses the Gamepad API in browsers.
+//!
+//! Gamepad buttons are ordinary [`Button`]s which can be bound to actions,
+//! while the left stick moves the player and the right stick turns the
+//! camera.
+
+use crate::core::math::angle::{Angle, HALF_ROTATION};
+use crate::core::math::vec2::Vec2;
+use crate::input::{Button, InputState};
+use crate::platform::{info, warn};
+use gilrs::{Axis, Event, EventType, Gilrs, GilrsBuilder};
+use std::time::Duration;
+
+/// How fast the camera turns with the right stick fully tilted, at a
+/// sensitivity of 1.
+const LOOK_SPEED_PER_SECOND: Angle = HALF_ROTATION;
+
+/// How gamepad sticks respond to being tilted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GamepadSettings {
+  /// How far, from 0 to 1, a stick must be tilted before it does anything,
+  /// so that sticks which don't quite return to the centre are ignored.
+  pub dead_zone: f32,
+  /// Multiplies how fast the right stick turns the camera.
+  pub look_sensitivity: f32,
+}
+
+impl Default for GamepadSettings {
+  fn default() -> Self {
+    Self {
+      dead_zone: 0.15,
+      look_sensitivity: 1.0,
+    }
+  }
+}
+
+impl GamepadSettings {
+  /// Returns how far `stick` is tilted past the dead zone, rescaled so that the
+  /// edge of the dead zone is 0 and a fully tilted stick is still 1.
+  pub fn apply_dead_zone(&self, stick: Vec2) -> Vec2 {
+    let tilt = stick.len();
+    if tilt <= self.dead_zone {
+      return Vec2::default();
+    }
+
+    let rescaled = ((tilt - self.dead_zone) / (1.0 - self.dead_zone)).min(1.0);
+    (rescaled / tilt) * stick
+  }
+
+  /// Returns the camera's `(yaw, pitch)` rotation over `elapsed` time, for the
+  /// right stick tilted by `stick`.
+  pub fn look_rotation(&self, stick: Vec2, elapsed: Duration) -> (Angle, Angle) {
+    let speed = LOOK_SPEED_PER_SECOND * (self.look_sensitivity * elapsed.as_secs_f32());
+    let stick = self.apply_dead_zone(stick);
+
+    // Tilting the stick up looks up, which is a negative pitch
+    (speed * stick.x(), speed * -stick.y())
+  }
+}
+
+/// The connected gamepads, whose input is merged as if they were one.
+pub struct Gamepads {
+  /// [`None`] if gamepads aren't supported.
+  gilrs: Option<Gilrs>,
+}
+
+impl Gamepads {
+  pub fn new() -> Self {
+    // The sticks' dead zone is applied by the game instead
+    let gilrs = match GilrsBuilder::new().with_default_filters(false).build() {
+      Ok(gilrs) => Some(gilrs),
+      Err(err) => {
+        warn!("Gamepads are unavailable: {}", err);
+        None
+      }
+    };
+
+    Self { gilrs }
+  }
+
+  /// Applies the gamepad events since the last poll to `input`.
+  pub fn poll(&mut self, input: &mut InputState) {
+    let Some(gilrs) = &mut self.gilrs else {
+      return;
+    };
+
+    while let Some(Event { id, event, .. }) = gilrs.next_event() {
+      match event {
+        EventType::Connected => info!("Gamepad connected: {}", gilrs.gamepad(id).name()),
+        EventType::Disconnected => {
+          info!("Gamepad disconnected");
+          input.release_gamepad();
+        }
+        EventType::ButtonPressed(button, _) => input.press(Button::Gamepad(button)),
+        EventType::ButtonReleased(button, _) => input.release(Button::Gamepad(button)),
+        EventType::AxisChanged(axis, value, _) => input.set_axis(axis, value),
+        _ => {}
+      }
+    }
+  }
+}
+
+/// Returns how far the stick made of the `x` and `y` axes is tilted, with +Y
+/// upwards.
+pub fn stick(input: &InputState, x: Axis, y: Axis) -> Vec2 {
+  Vec2::new(input.axis(x), input.axis(y))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Check that small tilts are ignored, and larger ones are rescaled to start
+  // from 0 at the edge of the dead zone.
+  #[test]
+  fn test_apply_dead_zone() {
+    let settings = GamepadSettings {
+      dead_zone: 0.2,
+      ..GamepadSettings::default()
+    };
+
+    let centre = settings.apply_dead_zone(Vec2::new(0.1, -0.1));
+    assert_eq!((0.0, 0.0), (centre.x(), centre.y()));
+
+    let half = settings.apply_dead_zone(Vec2::new(0.0, -0.6));
+    assert!((half.y() + 0.5).abs() < 1e-6);
+
+    let full = settings.apply_dead_zone(Vec2::new(1.0, 0.0));
+    assert!((full.x() - 1.0).abs() < 1e-6);
+  }
+}
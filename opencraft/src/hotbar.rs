@@ -0,0 +1,180 @@
+//! The hotbar overlay, drawn along the bottom of the screen with the blocks in
+//! each slot and the selected slot highlighted.
+
+use crate::core::math;
+use crate::core::math::aligned_box3::BoxFace;
+use crate::core::math::vec2::Vec2;
+use crate::core::type_conversions::{Coerce, CoerceLossy};
+use crate::mesh;
+use crate::text::{FontAtlas, TextVertex};
+use crate::world::inventory::{HOTBAR_SLOTS, Inventory};
+use winit::dpi::PhysicalSize;
+use zerocopy::{Immutable, IntoBytes};
+
+const SLOT_PIXELS: f32 = 48.0;
+const SLOT_GAP_PIXELS: f32 = 4.0;
+const BOTTOM_MARGIN_PIXELS: f32 = 8.0;
+const ICON_INSET_PIXELS: f32 = 8.0;
+const SELECTION_BORDER_PIXELS: f32 = 3.0;
+const COUNT_INSET_PIXELS: f32 = 2.0;
+
+const SLOT_COLOUR: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+const SELECTION_COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+const ICON_COLOUR: [f32; 4] = [1.0; 4];
+
+/// Texture layer of quads which are filled with their colour alone.
+const UNTEXTURED: i32 = -1;
+/// Face of a block whose texture is shown as its icon.
+const ICON_FACE: BoxFace = BoxFace::Front;
+
+#[repr(C)]
+#[derive(Immutable, IntoBytes)]
+pub struct HotbarVertex {
+  screen_position: Vec2,
+  /// Position within the texture tile, from 0 to 1.
+  tile_coordinate: Vec2,
+  /// Top-left texture coordinate of the tile in its cube net.
+  tile_origin: [f32; 2],
+  /// Layer of the block texture array, or [`UNTEXTURED`].
+  texture_layer: i32,
+  /// Colour which the texture is multiplied by.
+  colour: [f32; 4],
+}
+
+/// Returns the pixel position of the top-left corner of hotbar `slot`.
+fn slot_position(slot: usize, screen_size: Vec2) -> Vec2 {
+  let slots: f32 = HOTBAR_SLOTS.coerce_lossy();
+  let width = (slots * SLOT_PIXELS) + ((slots - 1.0) * SLOT_GAP_PIXELS);
+  let slot: f32 = slot.coerce_lossy();
+
+  Vec2::new(
+    ((screen_size.x() - width) / 2.0).round() + (slot * (SLOT_PIXELS + SLOT_GAP_PIXELS)),
+    screen_size.y() - BOTTOM_MARGIN_PIXELS - SLOT_PIXELS,
+  )
+}
+
+fn screen_size_vec(screen_size: PhysicalSize<u32>) -> Vec2 {
+  Vec2::new(
+    screen_size.width.coerce_lossy(),
+    screen_size.height.coerce_lossy(),
+  )
+}
+
+/// Pushes the two triangles of a `size` pixels wide square, whose top-left
+/// corner is at pixel `position`, textured with `texture`'s `(layer, tile
+/// origin)` if given.
+fn push_square(
+  position: Vec2,
+  size: f32,
+  texture: Option<(i32, [f32; 2])>,
+  colour: [f32; 4],
+  screen_size: Vec2,
+  vertices: &mut Vec<HotbarVertex>,
+) {
+  let (texture_layer, tile_origin) = texture.unwrap_or((UNTEXTURED, [0.0; 2]));
+
+  let to_screen = |x: f32, y: f32| {
+    Vec2::new(
+      math::affine_transform(x, 0.0..=screen_size.x(), -1.0..=1.0),
+      -math::affine_transform(y, 0.0..=screen_size.y(), -1.0..=1.0),
+    )
+  };
+  let vertex = |x: f32, y: f32| HotbarVertex {
+    screen_position: to_screen(position.x() + (x * size), position.y() + (y * size)),
+    tile_coordinate: Vec2::new(x, y),
+    tile_origin,
+    texture_layer,
+    colour,
+  };
+
+  vertices.extend([
+    vertex(0.0, 0.0),
+    vertex(0.0, 1.0),
+    vertex(1.0, 0.0),
+    vertex(1.0, 0.0),
+    vertex(0.0, 1.0),
+    vertex(1.0, 1.0),
+  ]);
+}
+
+/// Pushes the vertices of the hotbar's slots and the icons of the blocks in
+/// them, whose textures are looked up in `texture_layers`.
+pub fn push_hotbar_vertices(
+  inventory: &Inventory,
+  texture_layers: &[&'static str],
+  screen_size: PhysicalSize<u32>,
+  vertices: &mut Vec<HotbarVertex>,
+) {
+  let screen_size = screen_size_vec(screen_size);
+
+  for (slot, stack) in inventory.hotbar().iter().enumerate() {
+    let position = slot_position(slot, screen_size);
+
+    if slot == inventory.selected_slot() {
+      push_square(
+        position - Vec2::new(SELECTION_BORDER_PIXELS, SELECTION_BORDER_PIXELS),
+        SLOT_PIXELS + (2.0 * SELECTION_BORDER_PIXELS),
+        None,
+        SELECTION_COLOUR,
+        screen_size,
+        vertices,
+      );
+    }
+    push_square(
+      position,
+      SLOT_PIXELS,
+      None,
+      SLOT_COLOUR,
+      screen_size,
+      vertices,
+    );
+
+    let Some(texture) = stack.and_then(|stack| stack.block.definition().texture(ICON_FACE)) else {
+      continue;
+    };
+    let Some(layer) = texture_layers
+      .iter()
+      .position(|&layer| layer == texture.texture)
+    else {
+      continue;
+    };
+
+    push_square(
+      position + Vec2::new(ICON_INSET_PIXELS, ICON_INSET_PIXELS),
+      SLOT_PIXELS - (2.0 * ICON_INSET_PIXELS),
+      Some((layer.coerce(), mesh::net_tile_origin(texture.tile))),
+      ICON_COLOUR,
+      screen_size,
+      vertices,
+    );
+  }
+}
+
+/// Pushes the text vertices of the count of each hotbar stack of more than one
+/// block, in the bottom-right corner of its slot.
+pub fn push_count_vertices(
+  inventory: &Inventory,
+  font_atlas: &FontAtlas,
+  screen_size: PhysicalSize<u32>,
+  vertices: &mut Vec<TextVertex>,
+) {
+  for (slot, stack) in inventory.hotbar().iter().enumerate() {
+    let Some(stack) = stack.filter(|stack| stack.count > 1) else {
+      continue;
+    };
+
+    let count = stack.count.to_string();
+    let corner = slot_position(slot, screen_size_vec(screen_size))
+      + Vec2::new(
+        SLOT_PIXELS - COUNT_INSET_PIXELS - font_atlas.text_pixel_width(&count),
+        SLOT_PIXELS - COUNT_INSET_PIXELS - font_atlas.glyph_pixel_height(),
+      );
+
+    font_atlas.push_text_vertices(
+      &count,
+      PhysicalSize::new(corner.x().coerce_lossy(), corner.y().coerce_lossy()),
+      screen_size,
+      vertices,
+    );
+  }
+}
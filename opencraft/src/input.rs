@@ -0,0 +1,190 @@
+use crate::core::math::vec2::Vec2;
+use gilrs::Axis;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+/// A key, mouse button or gamepad button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Button {
+  Key(KeyCode),
+  Mouse(MouseButton),
+  Gamepad(gilrs::Button),
+}
+
+/// Which of a set of buttons are held, and which were pressed or released
+/// since the simulation last saw them.
+#[derive(Debug)]
+struct ButtonState<T> {
+  held: HashSet<T>,
+  pressed: HashSet<T>,
+  released: HashSet<T>,
+}
+
+impl<T> Default for ButtonState<T> {
+  fn default() -> Self {
+    Self {
+      held: HashSet::new(),
+      pressed: HashSet::new(),
+      released: HashSet::new(),
+    }
+  }
+}
+
+impl<T: Copy + Eq + Hash> ButtonState<T> {
+  fn press(&mut self, button: T) {
+    // Repeated presses from holding a key down aren't new presses
+    if self.held.insert(button) {
+      self.pressed.insert(button);
+    }
+  }
+
+  fn release(&mut self, button: T) {
+    if self.held.remove(&button) {
+      self.released.insert(button);
+    }
+  }
+
+  fn clear_transitions(&mut self) {
+    self.pressed.clear();
+    self.released.clear();
+  }
+}
+
+/// The state of the keyboard, mouse and gamepads, built up from their events.
+///
+/// Presses, releases and scrolling are kept until
+/// [`InputState::clear_transitions`] is called once the simulation has seen
+/// them, so that a tap between two ticks isn't missed.
+#[derive(Debug, Default)]
+pub struct InputState {
+  buttons: ButtonState<Button>,
+  /// Lines scrolled horizontally and vertically, positive when scrolling right
+  /// and up.
+  wheel_delta: Vec2,
+  /// Position of the cursor in the window in pixels, while it's in it.
+  cursor_position: Option<Vec2>,
+  /// Positions of the gamepad axes which have moved, from -1 to 1.
+  axes: HashMap<Axis, f32>,
+}
+
+impl InputState {
+  pub fn is_held(&self, button: Button) -> bool {
+    self.buttons.held.contains(&button)
+  }
+
+  pub fn was_pressed(&self, button: Button) -> bool {
+    self.buttons.pressed.contains(&button)
+  }
+
+  pub fn was_released(&self, button: Button) -> bool {
+    self.buttons.released.contains(&button)
+  }
+
+  /// Returns whether `button` is held, or was tapped since the transitions
+  /// were cleared.
+  pub fn is_down(&self, button: Button) -> bool {
+    self.is_held(button) || self.was_pressed(button)
+  }
+
+  /// Returns the lines scrolled since the transitions were cleared.
+  pub fn wheel_delta(&self) -> Vec2 {
+    self.wheel_delta
+  }
+
+  pub fn cursor_position(&self) -> Option<Vec2> {
+    self.cursor_position
+  }
+
+  pub fn axis(&self, axis: Axis) -> f32 {
+    self.axes.get(&axis).copied().unwrap_or_default()
+  }
+
+  pub fn press(&mut self, button: Button) {
+    self.buttons.press(button);
+  }
+
+  pub fn release(&mut self, button: Button) {
+    self.buttons.release(button);
+  }
+
+  pub fn scroll(&mut self, lines: Vec2) {
+    self.wheel_delta += lines;
+  }
+
+  pub fn move_cursor(&mut self, position: Option<Vec2>) {
+    self.cursor_position = position;
+  }
+
+  pub fn set_axis(&mut self, axis: Axis, value: f32) {
+    self.axes.insert(axis, value);
+  }
+
+  /// Releases every gamepad button and centres every axis, for when a gamepad
+  /// disconnects.
+  pub fn release_gamepad(&mut self) {
+    let gamepad_buttons: Vec<_> = self
+      .buttons
+      .held
+      .iter()
+      .copied()
+      .filter(|button| matches!(button, Button::Gamepad(_)))
+      .collect();
+    for button in gamepad_buttons {
+      self.release(button);
+    }
+
+    self.axes.clear();
+  }
+
+  /// Forgets the presses, releases and scrolling seen so far, keeping what's
+  /// held.
+  pub fn clear_transitions(&mut self) {
+    self.buttons.clear_transitions();
+    self.wheel_delta = Vec2::default();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Check that a click between two ticks is seen, and that holding a button
+  // keeps it down without pressing it again.
+  #[test]
+  fn test_mouse_button_transitions() {
+    let left = Button::Mouse(MouseButton::Left);
+    let right = Button::Mouse(MouseButton::Right);
+    let mut input = InputState::default();
+
+    input.press(left);
+    input.release(left);
+    assert!(input.is_down(left));
+    assert!(!input.is_held(left));
+    assert!(input.was_released(left));
+
+    input.clear_transitions();
+    assert!(!input.is_down(left));
+
+    input.press(right);
+    input.clear_transitions();
+    input.press(right);
+    assert!(input.is_held(right));
+    assert!(!input.was_pressed(right));
+  }
+
+  // Check that scrolling accumulates until the transitions are cleared.
+  #[test]
+  fn test_wheel_delta_accumulates() {
+    let mut input = InputState::default();
+
+    input.scroll(Vec2::new(0.0, 1.0));
+    input.scroll(Vec2::new(0.5, -3.0));
+    assert_eq!(0.5, input.wheel_delta().x());
+    assert_eq!(-2.0, input.wheel_delta().y());
+
+    input.clear_transitions();
+    assert_eq!(0.0, input.wheel_delta().y());
+  }
+}
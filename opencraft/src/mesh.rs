@@ -0,0 +1,530 @@
+use crate::core::math::aligned_box3::BoxFace;
+use crate::core::type_conversions::{Coerce, CoerceLossy};
+use crate::world::World;
+use crate::world::block::Block;
+use crate::world::chunk::CHUNK_SIZE;
+use crate::world::light::{Light, MAX_LIGHT};
+use crate::world::position::{BlockPos, ChunkPos};
+use zerocopy::{Immutable, IntoBytes};
+
+const TEX_WIDTH: f32 = 48.0;
+const TEX_HEIGHT: f32 = 64.0;
+
+const TEX_FRONT_LEFT: f32 = 16.0 / TEX_WIDTH;
+const TEX_FRONT_TOP: f32 = 32.0 / TEX_HEIGHT;
+
+const TEX_BACK_LEFT: f32 = 16.0 / TEX_WIDTH;
+const TEX_BACK_TOP: f32 = 0.0 / TEX_HEIGHT;
+
+const TEX_TOP_LEFT: f32 = 16.0 / TEX_WIDTH;
+const TEX_TOP_TOP: f32 = 16.0 / TEX_HEIGHT;
+
+const TEX_BOTTOM_LEFT: f32 = 16.0 / TEX_WIDTH;
+const TEX_BOTTOM_TOP: f32 = 48.0 / TEX_HEIGHT;
+
+const TEX_LEFT_LEFT: f32 = 0.0 / TEX_WIDTH;
+const TEX_LEFT_TOP: f32 = 16.0 / TEX_HEIGHT;
+
+const TEX_RIGHT_LEFT: f32 = 32.0 / TEX_WIDTH;
+const TEX_RIGHT_TOP: f32 = 16.0 / TEX_HEIGHT;
+
+/// Returns the top-left texture coordinate of the tile for `face` in a cube
+/// net.
+pub fn net_tile_origin(face: BoxFace) -> [f32; 2] {
+  match face {
+    BoxFace::Front => [TEX_FRONT_LEFT, TEX_FRONT_TOP],
+    BoxFace::Back => [TEX_BACK_LEFT, TEX_BACK_TOP],
+    BoxFace::Top => [TEX_TOP_LEFT, TEX_TOP_TOP],
+    BoxFace::Bottom => [TEX_BOTTOM_LEFT, TEX_BOTTOM_TOP],
+    // The net is laid out as seen from the front, so the faces whose normals
+    // point left and right are swapped
+    BoxFace::Right => [TEX_LEFT_LEFT, TEX_LEFT_TOP],
+    BoxFace::Left => [TEX_RIGHT_LEFT, TEX_RIGHT_TOP],
+  }
+}
+
+/// Returns the names of every block texture in the registry, each once, in the
+/// order of their layers in the block texture array.
+pub fn texture_layers() -> Vec<&'static str> {
+  let mut layers = Vec::new();
+
+  for texture in Block::ALL
+    .iter()
+    .filter_map(|block| block.definition().textures)
+    .flatten()
+  {
+    if !layers.contains(&texture.texture) {
+      layers.push(texture.texture);
+    }
+  }
+
+  layers
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Immutable, IntoBytes)]
+pub struct BlockVertex {
+  /// Position relative to the centre of the chunk's origin block.
+  position: [f32; 3],
+  /// Position within the face's texture tile, where each whole unit is one
+  /// block, so that the tile repeats across merged faces.
+  tile_coordinate: [f32; 2],
+  /// Top-left texture coordinate of the tile in its cube net.
+  tile_origin: [f32; 2],
+  texture_layer: u32,
+  /// Sky light and block light levels falling on the face, from 0 to 1.
+  light: [f32; 2],
+  /// How exposed the corner is to ambient light, from 0 when tucked between
+  /// blocks to 1 when open.
+  occlusion: f32,
+}
+
+pub struct MeshOptions {
+  /// Merge adjacent identical faces into larger quads.
+  pub greedy: bool,
+  /// Darken the corners of faces next to blocks in front of the face.
+  pub ambient_occlusion: bool,
+}
+
+impl Default for MeshOptions {
+  fn default() -> Self {
+    Self {
+      greedy: true,
+      ambient_occlusion: true,
+    }
+  }
+}
+
+/// Occlusion of a face corner with no blocks around it.
+const UNOCCLUDED: u8 = 3;
+
+/// Everything that must match for two adjacent faces to be merged.
+#[derive(Clone, Copy, PartialEq)]
+struct FaceKey {
+  texture_layer: u32,
+  tile: BoxFace,
+  /// Light in the block which the face looks into.
+  light: Light,
+  /// Occlusion of the corners at the start and end of the `a` and `b` axes,
+  /// indexed by `[a, b]`, from 0 to [`UNOCCLUDED`].
+  occlusion: [[u8; 2]; 2],
+}
+
+impl FaceKey {
+  /// Returns whether the face is evenly lit, so that merging it with equal
+  /// faces doesn't change how it is shaded.
+  fn is_mergeable(&self) -> bool {
+    self
+      .occlusion
+      .as_flattened()
+      .iter()
+      .all(|&corner| corner == self.occlusion[0][0])
+  }
+}
+
+/// The axes along which a face lies, as indices into `[x, y, z]`.
+///
+/// `a` and `b` are chosen so that `a × b` points into the block, which makes
+/// the triangles `(0, a, b)` wind counter-clockwise when seen from outside.
+struct FaceAxes {
+  normal: usize,
+  sign: i32,
+  a: usize,
+  b: usize,
+}
+
+fn face_axes(face: BoxFace) -> FaceAxes {
+  let (normal, sign, a, b) = match face {
+    BoxFace::Left => (0, 1, 2, 1),
+    BoxFace::Right => (0, -1, 1, 2),
+    BoxFace::Top => (1, 1, 0, 2),
+    BoxFace::Bottom => (1, -1, 2, 0),
+    BoxFace::Back => (2, 1, 1, 0),
+    BoxFace::Front => (2, -1, 0, 1),
+  };
+
+  FaceAxes { normal, sign, a, b }
+}
+
+/// Maps a point on `face`, relative to the block centre, to its position on
+/// the face's texture tile, so that textures are oriented as on the cube net.
+fn tile_coordinate(face: BoxFace, [x, y, z]: [f32; 3]) -> [f32; 2] {
+  match face {
+    BoxFace::Front => [x + 0.5, 0.5 - y],
+    BoxFace::Back => [x + 0.5, y + 0.5],
+    BoxFace::Top => [x + 0.5, 0.5 - z],
+    BoxFace::Bottom => [x + 0.5, z + 0.5],
+    BoxFace::Right => [y + 0.5, 0.5 - z],
+    BoxFace::Left => [0.5 - y, 0.5 - z],
+  }
+}
+
+pub struct Mesher<'a> {
+  world: &'a World,
+  texture_layers: &'a [&'static str],
+  options: &'a MeshOptions,
+}
+
+impl<'a> Mesher<'a> {
+  pub fn new(
+    world: &'a World,
+    texture_layers: &'a [&'static str],
+    options: &'a MeshOptions,
+  ) -> Self {
+    Self {
+      world,
+      texture_layers,
+      options,
+    }
+  }
+
+  /// Builds the vertices of every visible face in the chunk at `chunk_pos`.
+  ///
+  /// A face is visible if the block it faces is transparent and not of the
+  /// same type, so the faces between two blocks of glass are hidden.
+  pub fn mesh(&self, chunk_pos: ChunkPos) -> Vec<BlockVertex> {
+    let mut vertices = Vec::new();
+
+    let Some(chunk) = self.world.chunk(chunk_pos) else {
+      return vertices;
+    };
+    if chunk.is_empty() {
+      return vertices;
+    }
+
+    let origin = chunk_pos.origin();
+    let size: usize = CHUNK_SIZE.coerce();
+
+    for face in BoxFace::ALL {
+      let axes = face_axes(face);
+
+      for layer in 0..CHUNK_SIZE {
+        let mut mask: Vec<Option<FaceKey>> = vec![None; size * size];
+
+        for b in 0..CHUNK_SIZE {
+          for a in 0..CHUNK_SIZE {
+            let mut local = [0; 3];
+            local[axes.normal] = layer;
+            local[axes.a] = a;
+            local[axes.b] = b;
+
+            let pos = origin.offset(local[0], local[1], local[2]);
+            mask[(b.coerce() * size) + a.coerce()] = self.visible_face(pos, face, &axes);
+          }
+        }
+
+        self.emit_layer(face, &axes, layer, &mut mask, &mut vertices);
+      }
+    }
+
+    vertices
+  }
+
+  fn visible_face(&self, pos: BlockPos, face: BoxFace, axes: &FaceAxes) -> Option<FaceKey> {
+    let block = self.world.get(pos);
+    let texture = block.definition().texture(face)?;
+
+    let neighbour_pos = pos.adjacent(face);
+    let neighbour = self.world.get(neighbour_pos);
+    if !neighbour.is_transparent() || (neighbour == block) {
+      return None;
+    }
+
+    Some(FaceKey {
+      texture_layer: self
+        .texture_layers
+        .iter()
+        .position(|&layer| layer == texture.texture)
+        .unwrap()
+        .coerce(),
+      tile: texture.tile,
+      light: self.world.light(neighbour_pos),
+      occlusion: if self.options.ambient_occlusion {
+        self.occlusion(neighbour_pos, axes)
+      } else {
+        [[UNOCCLUDED; 2]; 2]
+      },
+    })
+  }
+
+  /// Works out the occlusion of each corner of a face from the opaque blocks
+  /// beside and diagonal to the corner, in the layer the face looks into.
+  fn occlusion(&self, facing: BlockPos, axes: &FaceAxes) -> [[u8; 2]; 2] {
+    let is_opaque = |along_a: i32, along_b: i32| {
+      let mut offset = [0; 3];
+      offset[axes.a] = along_a;
+      offset[axes.b] = along_b;
+
+      !self
+        .world
+        .get(facing.offset(offset[0], offset[1], offset[2]))
+        .is_transparent()
+    };
+
+    [-1, 1].map(|along_a| {
+      [-1, 1].map(|along_b| {
+        let sides = [is_opaque(along_a, 0), is_opaque(0, along_b)];
+
+        // Two sides already hide the corner, whatever is diagonal to it
+        if sides == [true, true] {
+          0
+        } else {
+          let occluders = sides
+            .into_iter()
+            .chain([is_opaque(along_a, along_b)])
+            .filter(|&opaque| opaque)
+            .count();
+          let occluders: u8 = occluders.coerce();
+
+          UNOCCLUDED - occluders
+        }
+      })
+    })
+  }
+
+  /// Covers the faces in `mask` with quads, merging faces with equal keys into
+  /// rectangles if greedy meshing is enabled.
+  fn emit_layer(
+    &self,
+    face: BoxFace,
+    axes: &FaceAxes,
+    layer: i32,
+    mask: &mut [Option<FaceKey>],
+    vertices: &mut Vec<BlockVertex>,
+  ) {
+    let size: usize = CHUNK_SIZE.coerce();
+
+    for b in 0..size {
+      let mut a = 0;
+      while a < size {
+        let Some(key) = mask[(b * size) + a] else {
+          a += 1;
+          continue;
+        };
+
+        let mut width = 1;
+        let mut height = 1;
+
+        if self.options.greedy && key.is_mergeable() {
+          while (a + width < size) && (mask[(b * size) + a + width] == Some(key)) {
+            width += 1;
+          }
+
+          while (b + height < size)
+            && mask[((b + height) * size) + a..][..width]
+              .iter()
+              .all(|&cell| cell == Some(key))
+          {
+            height += 1;
+          }
+        }
+
+        for row in b..(b + height) {
+          mask[(row * size) + a..][..width].fill(None);
+        }
+
+        push_quad(
+          face,
+          axes,
+          [layer, a.coerce(), b.coerce()],
+          [width.coerce_lossy(), height.coerce_lossy()],
+          key,
+          vertices,
+        );
+
+        a += width;
+      }
+    }
+  }
+}
+
+/// Pushes the two triangles of a `size` quad on `face` of the block at
+/// `[layer, a, b]` along the face's axes.
+fn push_quad(
+  face: BoxFace,
+  axes: &FaceAxes,
+  [layer, a, b]: [i32; 3],
+  [width, height]: [f32; 2],
+  key: FaceKey,
+  vertices: &mut Vec<BlockVertex>,
+) {
+  let mut base = [0.0; 3];
+  base[axes.normal] = layer.coerce_lossy() + (0.5 * axes.sign.coerce_lossy());
+  base[axes.a] = a.coerce_lossy() - 0.5;
+  base[axes.b] = b.coerce_lossy() - 0.5;
+
+  let corner = |[along_a, along_b]: [usize; 2]| {
+    let mut position = base;
+    position[axes.a] += [0.0, width][along_a];
+    position[axes.b] += [0.0, height][along_b];
+
+    // Texture coordinates are relative to the centre of the quad's first block
+    let mut relative = position;
+    relative[axes.normal] -= layer.coerce_lossy();
+    relative[axes.a] -= a.coerce_lossy();
+    relative[axes.b] -= b.coerce_lossy();
+
+    BlockVertex {
+      position,
+      tile_coordinate: tile_coordinate(face, relative),
+      tile_origin: net_tile_origin(key.tile),
+      texture_layer: key.texture_layer,
+      light: [key.light.sky(), key.light.block()]
+        .map(|level| f32::from(level) / f32::from(MAX_LIGHT)),
+      occlusion: f32::from(key.occlusion[along_a][along_b]) / f32::from(UNOCCLUDED),
+    }
+  };
+
+  let origin = corner([0, 0]);
+  let along_a = corner([1, 0]);
+  let along_b = corner([0, 1]);
+  let opposite = corner([1, 1]);
+
+  // Split the quad along the diagonal between its more exposed corners, so
+  // that a single dark corner shades a triangle rather than a stripe across
+  // the face
+  let [
+    [origin_occlusion, along_b_occlusion],
+    [along_a_occlusion, opposite_occlusion],
+  ] = key.occlusion.map(|corners| corners.map(u32::from));
+  if (origin_occlusion + opposite_occlusion) > (along_a_occlusion + along_b_occlusion) {
+    vertices.extend([origin, along_a, opposite, origin, opposite, along_b]);
+  } else {
+    vertices.extend([origin, along_a, along_b, along_b, along_a, opposite]);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::core::math::vec3::Vec3;
+
+  fn mesh_chunk(world: &World, options: MeshOptions) -> Vec<BlockVertex> {
+    let texture_layers = texture_layers();
+
+    Mesher::new(world, &texture_layers, &options).mesh(ChunkPos::new(0, 0, 0))
+  }
+
+  fn position(vertex: &BlockVertex) -> Vec3 {
+    let [x, y, z] = vertex.position;
+    Vec3::new(x, y, z)
+  }
+
+  // Check that each triangle winds counter-clockwise seen from outside the
+  // cube, matching the original cube vertices: its right-handed normal points
+  // into the cube.
+  #[test]
+  fn test_single_block_winding() {
+    let mut world = World::new();
+    world.set(BlockPos::new(0, 0, 0), Block::Stone);
+
+    let vertices = mesh_chunk(&world, MeshOptions::default());
+
+    assert_eq!(36, vertices.len());
+    for triangle in vertices.chunks_exact(3) {
+      let [v0, v1, v2] = [0, 1, 2].map(|index| position(&triangle[index]));
+      let center = (v0 + v1 + v2) / 3.0;
+
+      let normal = Vec3::cross(v1 - v0, v2 - v0);
+
+      assert!(Vec3::dot(normal, center) < 0.0);
+    }
+  }
+
+  // Check that the faces between two adjacent blocks are hidden, and that
+  // greedy meshing merges the remaining coplanar faces.
+  #[test]
+  fn test_adjacent_blocks_culled_and_merged() {
+    let mut world = World::new();
+    world.set(BlockPos::new(0, 0, 0), Block::Stone);
+    world.set(BlockPos::new(1, 0, 0), Block::Stone);
+
+    let culled = mesh_chunk(
+      &world,
+      MeshOptions {
+        greedy: false,
+        ..MeshOptions::default()
+      },
+    );
+    let merged = mesh_chunk(&world, MeshOptions::default());
+
+    assert_eq!(10 * 6, culled.len());
+    assert_eq!(6 * 6, merged.len());
+  }
+
+  // Check that faces against a block in a neighbouring chunk are hidden, but
+  // faces against glass are not.
+  #[test]
+  fn test_cull_across_chunk_boundary() {
+    let mut world = World::new();
+    world.set(BlockPos::new(CHUNK_SIZE - 1, 0, 0), Block::Stone);
+    world.set(BlockPos::new(CHUNK_SIZE, 0, 0), Block::Stone);
+    world.set(BlockPos::new(CHUNK_SIZE - 1, 1, 0), Block::Glass);
+
+    let vertices = mesh_chunk(
+      &world,
+      MeshOptions {
+        greedy: false,
+        ..MeshOptions::default()
+      },
+    );
+
+    // Stone shows 5 faces, including the one against glass, and glass shows 5
+    assert_eq!(10 * 6, vertices.len());
+  }
+
+  // Check that face corners beside opaque blocks are darkened, fully when
+  // blocks are on both sides of the corner.
+  #[test]
+  fn test_occlusion_beside_blocks() {
+    let mut world = World::new();
+    world.set(BlockPos::new(0, 0, 0), Block::Stone);
+    world.set(BlockPos::new(1, 1, 0), Block::Stone);
+    world.set(BlockPos::new(0, 1, 1), Block::Stone);
+    let texture_layers = texture_layers();
+    let options = MeshOptions::default();
+    let mesher = Mesher::new(&world, &texture_layers, &options);
+
+    let key = mesher
+      .visible_face(
+        BlockPos::new(0, 0, 0),
+        BoxFace::Top,
+        &face_axes(BoxFace::Top),
+      )
+      .unwrap();
+
+    assert_eq!([[3, 2], [2, 0]], key.occlusion);
+  }
+
+  // Check that quads are split so that a single dark corner is only in one of
+  // their triangles, whichever corner it is.
+  #[test]
+  fn test_quad_split_away_from_dark_corner() {
+    for dark_corner in [[0, 0], [0, 1], [1, 0], [1, 1]] {
+      let mut occlusion = [[UNOCCLUDED; 2]; 2];
+      occlusion[dark_corner[0]][dark_corner[1]] = 0;
+      let key = FaceKey {
+        texture_layer: 0,
+        tile: BoxFace::Top,
+        light: Light::SKY,
+        occlusion,
+      };
+
+      let mut vertices = Vec::new();
+      push_quad(
+        BoxFace::Top,
+        &face_axes(BoxFace::Top),
+        [0, 0, 0],
+        [1.0, 1.0],
+        key,
+        &mut vertices,
+      );
+
+      let dark_vertices = vertices
+        .iter()
+        .filter(|vertex| vertex.occlusion == 0.0)
+        .count();
+      assert_eq!(1, dark_vertices, "{dark_corner:?}");
+    }
+  }
+}
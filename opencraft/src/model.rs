@@ -0,0 +1,526 @@
+//! The game's view of the simulation, kept apart from rendering so that it can
+//! run and be tested without a GPU.
+
+use crate::camera::Camera;
+use crate::core::math::aligned_box3::BoxFace;
+use crate::core::math::angle::Angle;
+use crate::core::math::segment3::Segment3;
+use crate::core::math::vec2::Vec2;
+use crate::core::math::vec3::Vec3;
+use crate::net::protocol::PlayerId;
+use crate::simulation::{REACH_DISTANCE, SimulatedPlayer, Simulation};
+use crate::world::World;
+use crate::world::block::Block;
+use crate::world::generation::TerrainGenerator;
+use crate::world::inventory::Inventory;
+use crate::world::player::{MovementMode, Player};
+use crate::world::position::{BlockPos, ChunkPos};
+use crate::world::raycast::raycast;
+use crate::world::save::{WorldMetadata, WorldSave};
+use crate::world::time::WorldTime;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Length of a simulation tick, of which there are 60 per second whatever the
+/// frame rate.
+pub const TICK_DURATION: Duration = Duration::from_nanos(16_666_667);
+/// Id of the player within the model's simulation, whatever a server knows
+/// them by.
+const LOCAL_PLAYER: PlayerId = PlayerId(0);
+/// Ticks between the blocks broken or placed while the button is held.
+const BLOCK_REPEAT_TICKS: u32 = 15;
+/// Rate at which the sky fades to the colour of a newly entered biome.
+const SKY_BLEND_RATE: f32 = 1.0;
+
+/// What the player asked to do during a tick.
+#[derive(Debug, Default, Clone)]
+pub struct Input {
+  /// Direction to move in relative to where the camera faces horizontally, with
+  /// +Z forwards, +X to the right and +Y upwards. Vertical movement is only
+  /// used while flying.
+  ///
+  /// Movement shorter than 1, such as from a partly tilted stick, is slower,
+  /// while longer movement is shortened to 1.
+  pub movement: Vec3,
+  pub jump: bool,
+  /// Whether breaking is held, which breaks a block straight away and then
+  /// repeatedly.
+  pub break_block: bool,
+  /// Whether placing is held, which places a block straight away and then
+  /// repeatedly.
+  pub place_block: bool,
+  pub toggle_movement_mode: bool,
+  /// Hotbar slot to select.
+  pub select_slot: Option<usize>,
+  /// Number of hotbar slots to move the selection by.
+  pub scroll_slots: i32,
+}
+
+/// The frame time statistics shown by the debug display.
+#[derive(Debug, Clone, Copy)]
+pub struct DebugDisplay {
+  pub frames_per_second: f32,
+  pub mean_frame_time_ms: f32,
+  pub cursor_position: Option<Vec2>,
+}
+
+/// A snapshot of everything the renderer draws in a frame.
+pub struct Scene<'a> {
+  pub world: &'a World,
+  /// Chunks which changed since the last scene, whose meshes need rebuilding.
+  pub dirty_chunks: HashSet<ChunkPos>,
+  /// The player's camera, interpolated between the last two ticks.
+  pub player_camera: Camera,
+  pub sky_colour: Vec3,
+  pub time: WorldTime,
+  pub target_block: Option<BlockPos>,
+  pub inventory: &'a Inventory,
+  pub debug_display: Option<DebugDisplay>,
+}
+
+/// The simulation of the world and the player exploring it, along with what
+/// the player sees of it.
+pub struct Model {
+  simulation: Simulation,
+
+  camera: Camera,
+  /// Eye position of the player before the last tick, which the camera is
+  /// interpolated from.
+  previous_eye_position: Vec3,
+
+  /// Sky colour, fading between the colours of the biomes the player visits.
+  sky_colour: Vec3,
+  target_block_face: Option<(BlockPos, BoxFace)>,
+  /// Ticks until a held break or place button acts again.
+  break_cooldown: u32,
+  place_cooldown: u32,
+}
+
+impl Model {
+  /// Creates a model of `world` at `time`, with the player's eyes at the
+  /// camera.
+  pub fn new(
+    world: World,
+    terrain_generator: TerrainGenerator,
+    time: WorldTime,
+    camera: Camera,
+  ) -> Self {
+    let mut simulation = Simulation::new(world, terrain_generator, time);
+    simulation.add_player(
+      LOCAL_PLAYER,
+      camera.position() - Vec3::new(0.0, Player::EYE_HEIGHT, 0.0),
+    );
+    let camera_block = BlockPos::containing(camera.position());
+    let biome = terrain_generator.biome(camera_block.x(), camera_block.z());
+
+    Self {
+      simulation,
+      previous_eye_position: camera.position(),
+      camera,
+      sky_colour: Vec3::from(biome.definition().sky_colour),
+      target_block_face: None,
+      break_cooldown: 0,
+      place_cooldown: 0,
+    }
+  }
+
+  pub fn world(&self) -> &World {
+    self.simulation.world()
+  }
+
+  pub fn world_mut(&mut self) -> &mut World {
+    self.simulation.world_mut()
+  }
+
+  /// Makes a block change which the player asked for, moving a broken block
+  /// into the inventory or taking a placed block out of it.
+  pub fn change_block(&mut self, pos: BlockPos, block: Block) {
+    self.simulation.change_block(LOCAL_PLAYER, pos, block);
+  }
+
+  pub fn time(&self) -> WorldTime {
+    self.simulation.time()
+  }
+
+  /// Replaces the world's time, such as with the time kept by a server.
+  pub fn set_time(&mut self, time: WorldTime) {
+    self.simulation.set_time(time);
+  }
+
+  pub fn camera(&self) -> &Camera {
+    &self.camera
+  }
+
+  pub fn player(&self) -> &Player {
+    &self.local_player().body
+  }
+
+  /// Replaces the seed which biomes and the world's terrain are looked up
+  /// with, for a world generated elsewhere.
+  pub fn set_seed(&mut self, seed: u64) {
+    self.simulation.set_seed(seed);
+  }
+
+  /// Moves the player to stand at `position`, looking in the given direction.
+  pub fn teleport(&mut self, position: Vec3, yaw: Angle, pitch: Angle) {
+    let body = &mut self.local_player_mut().body;
+    body.teleport(position);
+    let eye_position = body.eye_position();
+
+    self.previous_eye_position = eye_position;
+    self.camera = Camera::new(eye_position, yaw, pitch);
+  }
+
+  pub fn rotate_camera(&mut self, yaw: Angle, pitch: Angle) {
+    self.camera.rotate(yaw, pitch);
+  }
+
+  pub fn toggle_movement_mode(&mut self) {
+    self.local_player_mut().body.toggle_mode();
+  }
+
+  /// Loads up to `limit` missing chunks within `radius` chunks of the player
+  /// from `save`, nearest first, generating those which were never saved.
+  pub fn load_around_player(
+    &mut self,
+    save: &mut WorldSave,
+    radius: i32,
+    limit: usize,
+  ) -> Result<()> {
+    let center = self.player_chunk();
+    self.simulation.load_around(save, &[center], radius, limit)
+  }
+
+  /// Unloads the chunks more than `margin` chunks further from the player
+  /// than [`Model::load_around_player`] loads them with `radius`, saving any
+  /// unsaved changes to them in `save` first.
+  pub fn unload_distant_chunks(
+    &mut self,
+    save: Option<&mut WorldSave>,
+    radius: i32,
+    margin: i32,
+  ) -> Result<()> {
+    let center = self.player_chunk();
+    self
+      .simulation
+      .unload_distant(save, &[center], radius, margin)
+  }
+
+  pub fn metadata(&self) -> WorldMetadata {
+    WorldMetadata {
+      seed: self.simulation.terrain_generator().seed(),
+      camera_position: self.player().eye_position(),
+      camera_yaw: self.camera.yaw(),
+      camera_pitch: self.camera.pitch(),
+      time: self.simulation.time(),
+    }
+  }
+
+  /// Advances the simulation by one tick of [`TICK_DURATION`], which depends
+  /// only on `input` and never on the time.
+  ///
+  /// Returns the block change the player asked for, if any, which isn't made
+  /// here since a server may have to confirm it first. The inventory is left
+  /// alone until [`Model::change_block`] makes it.
+  pub fn tick(&mut self, input: &Input) -> Option<(BlockPos, Block)> {
+    let delta_secs = TICK_DURATION.as_secs_f32();
+    self.previous_eye_position = self.player().eye_position();
+
+    let inventory = &mut self.local_player_mut().inventory;
+    if let Some(slot) = input.select_slot {
+      inventory.select(slot);
+    }
+    inventory.scroll(input.scroll_slots);
+    if input.toggle_movement_mode {
+      self.toggle_movement_mode();
+    }
+
+    let mut movement = input.movement;
+    if self.player().mode() == MovementMode::Walking {
+      movement = Vec3::new(movement.x(), 0.0, movement.z());
+    }
+    if movement.len_sq() > 1.0 {
+      movement = movement.norm();
+    }
+    movement = self.camera.horizontal_to_world(movement);
+
+    self
+      .simulation
+      .tick(TICK_DURATION, |_| (movement, input.jump));
+    let eye_position = self.player().eye_position();
+
+    let eye_block = BlockPos::containing(eye_position);
+    let biome = self
+      .simulation
+      .terrain_generator()
+      .biome(eye_block.x(), eye_block.z());
+    let sky_colour = Vec3::from(biome.definition().sky_colour);
+    let blend = 1.0 - (-SKY_BLEND_RATE * delta_secs).exp();
+    self.sky_colour += blend * (sky_colour - self.sky_colour);
+
+    self.break_cooldown = cool_down(self.break_cooldown, input.break_block);
+    self.place_cooldown = cool_down(self.place_cooldown, input.place_block);
+
+    let mut change = None;
+    if let Some((target, face)) = self.target_block_face {
+      if input.break_block && (self.break_cooldown == 0) {
+        self.break_cooldown = BLOCK_REPEAT_TICKS;
+        change = Some((target, Block::Air));
+      } else if input.place_block
+        && (self.place_cooldown == 0)
+        && let Some(stack) = self.local_player().inventory.selected()
+      {
+        let placed = target.adjacent(face);
+
+        if self
+          .simulation
+          .can_change_block(LOCAL_PLAYER, placed, stack.block)
+        {
+          self.place_cooldown = BLOCK_REPEAT_TICKS;
+          change = Some((placed, stack.block));
+        }
+      }
+    }
+
+    let reach = Segment3::start_direction_len(eye_position, self.camera.forward(), REACH_DISTANCE);
+    self.target_block_face = raycast(self.world(), &reach).map(|hit| (hit.block, hit.face));
+
+    change
+  }
+
+  /// Returns a snapshot of the model to render, `alpha` of the way from the
+  /// previous tick to the latest one.
+  ///
+  /// The chunks which changed since the last snapshot are handed over to it.
+  pub fn scene(&mut self, alpha: f32) -> Scene<'_> {
+    Scene {
+      dirty_chunks: self.simulation.world_mut().take_dirty_chunks(),
+      world: self.simulation.world(),
+      player_camera: Camera::new(
+        Vec3::lerp(
+          self.previous_eye_position,
+          self.player().eye_position(),
+          alpha,
+        ),
+        self.camera.yaw(),
+        self.camera.pitch(),
+      ),
+      sky_colour: self.sky_colour,
+      time: self.simulation.time(),
+      target_block: self.target_block_face.map(|(block, _)| block),
+      inventory: &self.local_player().inventory,
+      debug_display: None,
+    }
+  }
+
+  fn local_player(&self) -> &SimulatedPlayer {
+    self.simulation.player(LOCAL_PLAYER).unwrap()
+  }
+
+  fn local_player_mut(&mut self) -> &mut SimulatedPlayer {
+    self.simulation.player_mut(LOCAL_PLAYER).unwrap()
+  }
+
+  /// Returns the chunk holding the player's eyes, which chunks are loaded
+  /// around.
+  fn player_chunk(&self) -> ChunkPos {
+    BlockPos::containing(self.player().eye_position()).chunk()
+  }
+}
+
+/// Returns the cooldown of a button one tick later, which is over as soon as
+/// the button is let go.
+fn cool_down(cooldown: u32, held: bool) -> u32 {
+  if held { cooldown.saturating_sub(1) } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::core::math::angle::QUARTER_ROTATION;
+  use crate::core::math::{Y_AXIS, Z_AXIS};
+  use crate::core::type_conversions::Coerce;
+  use crate::platform::Storage;
+  use crate::world::chunk::Chunk;
+  use crate::world::generation::UNLOAD_MARGIN;
+  use std::{env, fs};
+
+  const FLOOR_HALF_WIDTH: i32 = 8;
+
+  /// Returns a model of a stone floor at y = 0, with the player standing on
+  /// it.
+  fn floor_model() -> Model {
+    let mut world = World::new();
+    for x in -FLOOR_HALF_WIDTH..=FLOOR_HALF_WIDTH {
+      for z in -FLOOR_HALF_WIDTH..=FLOOR_HALF_WIDTH {
+        world.set(BlockPos::new(x, 0, z), Block::Stone);
+      }
+    }
+
+    let eye_position = Vec3::new(0.0, 0.5 + Player::EYE_HEIGHT, 0.0);
+    Model::new(
+      world,
+      TerrainGenerator::new(0),
+      WorldTime::default(),
+      Camera::new(eye_position, Angle::default(), Angle::default()),
+    )
+  }
+
+  fn run(model: &mut Model, input: &Input, ticks: usize) -> Vec<(BlockPos, Block)> {
+    (0..ticks).filter_map(|_| model.tick(input)).collect()
+  }
+
+  // Check that the same inputs always move the player to the same place.
+  #[test]
+  fn test_ticks_are_deterministic() {
+    let input = Input {
+      movement: Z_AXIS,
+      jump: true,
+      ..Input::default()
+    };
+    let mut first = floor_model();
+    let mut second = floor_model();
+
+    run(&mut first, &input, 60);
+    run(&mut second, &input, 60);
+
+    assert_eq!(first.player().position(), second.player().position());
+    assert!(first.player().position().z() > 1.0);
+  }
+
+  // Check that walking ignores vertical movement, which only flying uses.
+  #[test]
+  fn test_vertical_movement_only_while_flying() {
+    let input = Input {
+      movement: Y_AXIS,
+      ..Input::default()
+    };
+    let mut model = floor_model();
+    let start = model.player().position();
+
+    run(&mut model, &input, 30);
+    assert_eq!(start, model.player().position());
+
+    model.toggle_movement_mode();
+    run(&mut model, &input, 30);
+    assert!(model.player().position().y() > start.y() + 1.0);
+  }
+
+  // Check that breaking and placing ask for changes to the targeted block,
+  // without making them or touching the inventory.
+  #[test]
+  fn test_block_changes_requested() {
+    let mut model = floor_model();
+    // Look straight down
+    model.rotate_camera(Angle::default(), QUARTER_ROTATION);
+    assert!(model.camera().forward().y() < -0.99);
+
+    // The first tick finds the target
+    run(&mut model, &Input::default(), 1);
+    let floor = BlockPos::new(0, 0, 0);
+    assert_eq!(Some(floor), model.scene(1.0).target_block);
+
+    let breaking = Input {
+      break_block: true,
+      ..Input::default()
+    };
+    assert_eq!(vec![(floor, Block::Air)], run(&mut model, &breaking, 1));
+    assert_eq!(Block::Stone, model.scene(1.0).world.get(floor));
+    assert_eq!(None, model.scene(1.0).inventory.selected());
+
+    let edge = BlockPos::new(FLOOR_HALF_WIDTH, 0, 0);
+    model.change_block(edge, Block::Air);
+    assert_eq!(Block::Air, model.scene(1.0).world.get(edge));
+    assert_eq!(
+      Some(Block::Stone),
+      model
+        .scene(1.0)
+        .inventory
+        .selected()
+        .map(|stack| stack.block)
+    );
+
+    // Placing on top of the floor would trap the player, unless they fly
+    let placing = Input {
+      place_block: true,
+      ..Input::default()
+    };
+    assert!(run(&mut model, &placing, 1).is_empty());
+
+    model.toggle_movement_mode();
+    let above = BlockPos::new(0, 1, 0);
+    assert_eq!(vec![(above, Block::Stone)], run(&mut model, &placing, 1));
+    assert!(model.scene(1.0).inventory.selected().is_some());
+
+    model.change_block(above, Block::Stone);
+    assert_eq!(None, model.scene(1.0).inventory.selected());
+    assert!(run(&mut model, &placing, 1).is_empty());
+  }
+
+  // Check that breaking a block which is already air, such as one another
+  // player broke first, adds nothing to the inventory.
+  #[test]
+  fn test_breaking_air_collects_nothing() {
+    let mut model = floor_model();
+    let pos = BlockPos::new(0, 3, 0);
+
+    assert_eq!(Block::Air, model.scene(1.0).world.get(pos));
+    model.change_block(pos, Block::Air);
+    assert_eq!(&Inventory::new(), model.scene(1.0).inventory);
+  }
+
+  // Check that holding the break button keeps breaking blocks at a steady
+  // rate, and that letting go of it lets the next click break one at once.
+  #[test]
+  fn test_held_break_repeats() {
+    let mut model = floor_model();
+    model.rotate_camera(Angle::default(), QUARTER_ROTATION);
+    run(&mut model, &Input::default(), 1);
+
+    let breaking = Input {
+      break_block: true,
+      ..Input::default()
+    };
+    let ticks: usize = BLOCK_REPEAT_TICKS.coerce();
+    assert_eq!(3, run(&mut model, &breaking, (2 * ticks) + 1).len());
+    assert!(run(&mut model, &breaking, 1).is_empty());
+
+    run(&mut model, &Input::default(), 1);
+    assert_eq!(1, run(&mut model, &breaking, 1).len());
+  }
+
+  // Check that chunks beyond the radius are unloaded, with modified chunks
+  // saved first so that they load again as they were.
+  #[test]
+  fn test_distant_chunks_unloaded() {
+    let path = env::temp_dir().join(format!(
+      "opencraft-model-unload-test-{}",
+      std::process::id()
+    ));
+    let mut save = WorldSave::new(Storage::at(&path));
+    let mut model = floor_model();
+    let (near, far, modified) = (
+      ChunkPos::new(2, 0, 0),
+      ChunkPos::new(0, 0, 4),
+      ChunkPos::new(-4, 0, 0),
+    );
+    for pos in [near, far, modified] {
+      model.world_mut().insert_chunk(pos, Chunk::default());
+    }
+    model.world_mut().set(modified.origin(), Block::Stone);
+
+    model
+      .unload_distant_chunks(Some(&mut save), 1, UNLOAD_MARGIN)
+      .unwrap();
+    let world = model.world();
+    let unloaded =
+      [near, far, modified, ChunkPos::new(0, 0, 0)].map(|pos| world.chunk(pos).is_none());
+    let reloaded = save.load_chunk(model.world_mut(), modified);
+    fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!([false, true, true, false], unloaded);
+    assert!(reloaded.unwrap());
+    assert_eq!(Block::Stone, model.world().get(modified.origin()));
+  }
+}
@@ -0,0 +1,4 @@
+pub mod client;
+pub mod codec;
+pub mod connection;
+pub mod protocol;
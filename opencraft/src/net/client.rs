@@ -0,0 +1,55 @@
+use crate::net::connection::Connection;
+use crate::net::protocol::{ClientMessage, PROTOCOL_VERSION, ServerMessage};
+use anyhow::{Result, bail};
+
+/// A client's connection to a multiplayer server.
+pub struct ServerConnection {
+  connection: Connection,
+}
+
+impl ServerConnection {
+  /// Connects to the server at `address` and logs in as `name`.
+  ///
+  /// The login has succeeded once [`ServerMessage::LoginSuccess`] is received.
+  pub fn connect(address: &str, name: &str) -> Result<Self> {
+    Self::login(Connection::connect(address)?, name)
+  }
+
+  /// Logs in as `name` over a newly opened connection.
+  pub fn login(mut connection: Connection, name: &str) -> Result<Self> {
+    connection.send(&ClientMessage::Handshake {
+      protocol_version: PROTOCOL_VERSION,
+    })?;
+    connection.send(&ClientMessage::Login {
+      name: name.to_owned(),
+    })?;
+
+    Ok(Self { connection })
+  }
+
+  pub fn send(&mut self, message: &ClientMessage) -> Result<()> {
+    self.connection.send(message)
+  }
+
+  /// Returns the next message from the server, or [`None`] if there isn't
+  /// one yet.
+  ///
+  /// Fails when the server disconnects, with the reason it gave.
+  pub fn receive(&mut self) -> Result<Option<ServerMessage>> {
+    self.connection.flush()?;
+
+    loop {
+      match self.connection.receive()? {
+        Some(ServerMessage::Handshake { protocol_version }) => {
+          if protocol_version != PROTOCOL_VERSION {
+            bail!("server uses protocol version {protocol_version}, but we use {PROTOCOL_VERSION}");
+          }
+        }
+        Some(ServerMessage::Disconnect { reason }) => {
+          bail!("disconnected by the server: {reason}")
+        }
+        message => return Ok(message),
+      }
+    }
+  }
+}
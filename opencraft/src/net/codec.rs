@@ -0,0 +1,313 @@
+//! Binary encoding of the values sent over the network, and the framing which
+//! splits a stream of bytes into messages.
+//!
+//! Integers and floats are little-endian. Each message is framed by a `u32`
+//! length, counting the bytes of the message which follow it.
+
+use crate::core::bytes::{ByteReader, ByteWriter};
+use crate::core::math::angle::Angle;
+use crate::core::math::vec3::Vec3;
+use crate::core::type_conversions::Coerce;
+use crate::world::block::Block;
+use crate::world::chunk::Chunk;
+use crate::world::position::{BlockPos, ChunkPos};
+use crate::world::time::WorldTime;
+use anyhow::{Context, Result, ensure};
+
+/// Longest frame accepted, so that a peer can't exhaust memory by announcing an
+/// enormous message.
+pub const MAX_FRAME_LEN: usize = 1 << 20;
+/// Longest string accepted, in bytes.
+pub const MAX_STRING_LEN: usize = 1024;
+
+pub trait Encode {
+  fn encode(&self, writer: &mut ByteWriter);
+}
+
+pub trait Decode: Sized {
+  fn decode(reader: &mut ByteReader) -> Result<Self>;
+}
+
+impl Encode for u8 {
+  fn encode(&self, writer: &mut ByteWriter) {
+    writer.u8(*self);
+  }
+}
+
+impl Decode for u8 {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    reader.u8()
+  }
+}
+
+impl Encode for u32 {
+  fn encode(&self, writer: &mut ByteWriter) {
+    writer.u32(*self);
+  }
+}
+
+impl Decode for u32 {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    reader.u32()
+  }
+}
+
+impl Encode for u64 {
+  fn encode(&self, writer: &mut ByteWriter) {
+    writer.u64(*self);
+  }
+}
+
+impl Decode for u64 {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    reader.u64()
+  }
+}
+
+impl Encode for f32 {
+  fn encode(&self, writer: &mut ByteWriter) {
+    writer.f32(*self);
+  }
+}
+
+impl Decode for f32 {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    let value = reader.f32()?;
+    ensure!(value.is_finite(), "float is not finite");
+
+    Ok(value)
+  }
+}
+
+/// Strings are a `u16` byte length followed by UTF-8.
+impl Encode for String {
+  fn encode(&self, writer: &mut ByteWriter) {
+    assert!(self.len() <= MAX_STRING_LEN, "string is too long to send");
+
+    writer.u16(self.len().coerce());
+    writer.bytes(self.as_bytes());
+  }
+}
+
+impl Decode for String {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    let len: usize = reader.u16()?.into();
+    ensure!(len <= MAX_STRING_LEN, "string is too long");
+
+    Ok(String::from_utf8(reader.take_slice(len)?.to_vec())?)
+  }
+}
+
+impl Encode for Vec3 {
+  fn encode(&self, writer: &mut ByteWriter) {
+    for coordinate in <[f32; 3]>::from(*self) {
+      coordinate.encode(writer);
+    }
+  }
+}
+
+impl Decode for Vec3 {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    Ok(Vec3::new(
+      f32::decode(reader)?,
+      f32::decode(reader)?,
+      f32::decode(reader)?,
+    ))
+  }
+}
+
+/// Angles are in radians.
+impl Encode for Angle {
+  fn encode(&self, writer: &mut ByteWriter) {
+    self.as_radians().encode(writer);
+  }
+}
+
+impl Decode for Angle {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    Ok(Angle::radians(f32::decode(reader)?))
+  }
+}
+
+impl Encode for BlockPos {
+  fn encode(&self, writer: &mut ByteWriter) {
+    writer.i32(self.x());
+    writer.i32(self.y());
+    writer.i32(self.z());
+  }
+}
+
+impl Decode for BlockPos {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    Ok(BlockPos::new(reader.i32()?, reader.i32()?, reader.i32()?))
+  }
+}
+
+impl Encode for ChunkPos {
+  fn encode(&self, writer: &mut ByteWriter) {
+    writer.i32(self.x());
+    writer.i32(self.y());
+    writer.i32(self.z());
+  }
+}
+
+impl Decode for ChunkPos {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    Ok(ChunkPos::new(reader.i32()?, reader.i32()?, reader.i32()?))
+  }
+}
+
+/// Blocks are their `u8` ID.
+impl Encode for Block {
+  fn encode(&self, writer: &mut ByteWriter) {
+    writer.u8(self.id());
+  }
+}
+
+impl Decode for Block {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    let id = reader.u8()?;
+    Block::from_id(id).with_context(|| format!("unknown block ID {id}"))
+  }
+}
+
+/// Chunks are run-length encoded, as by [`Chunk::encode_runs`].
+impl Encode for Chunk {
+  fn encode(&self, writer: &mut ByteWriter) {
+    self.encode_runs(writer);
+  }
+}
+
+impl Decode for Chunk {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    Chunk::decode_runs(reader)
+  }
+}
+
+/// World times are a `u64` number of milliseconds.
+impl Encode for WorldTime {
+  fn encode(&self, writer: &mut ByteWriter) {
+    self.as_millis().encode(writer);
+  }
+}
+
+impl Decode for WorldTime {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    Ok(WorldTime::from_millis(u64::decode(reader)?))
+  }
+}
+
+/// Encodes `message` in a frame, ready to be sent.
+pub fn encode_frame(message: &impl Encode) -> Vec<u8> {
+  let mut body = ByteWriter::new();
+  message.encode(&mut body);
+  let body = body.into_bytes();
+  assert!(body.len() <= MAX_FRAME_LEN, "message is too long to send");
+
+  let mut frame = ByteWriter::new();
+  frame.u32(body.len().coerce());
+  frame.bytes(&body);
+  frame.into_bytes()
+}
+
+/// Decodes the frame at the start of `bytes`, returning the message and the
+/// length of the frame, or [`None`] if the whole frame hasn't arrived yet.
+pub fn decode_frame<M: Decode>(bytes: &[u8]) -> Result<Option<(M, usize)>> {
+  let mut reader = ByteReader::new(bytes);
+  let Ok(len) = reader.u32() else {
+    return Ok(None);
+  };
+  let len: usize = len.coerce();
+  ensure!(len <= MAX_FRAME_LEN, "frame of {len} bytes is too long");
+
+  let Ok(body) = reader.take_slice(len) else {
+    return Ok(None);
+  };
+  let mut body = ByteReader::new(body);
+  let message = M::decode(&mut body)?;
+  ensure!(
+    body.remaining().is_empty(),
+    "frame has {} unread bytes",
+    body.remaining().len()
+  );
+
+  Ok(Some((message, 4 + len)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn round_trip<T: Encode + Decode>(value: &T) -> T {
+    let mut writer = ByteWriter::new();
+    value.encode(&mut writer);
+    let bytes = writer.into_bytes();
+
+    let mut reader = ByteReader::new(&bytes);
+    let decoded = T::decode(&mut reader).unwrap();
+    assert!(reader.remaining().is_empty());
+    decoded
+  }
+
+  // Check that every type of value decodes to what was encoded.
+  #[test]
+  fn test_value_round_trips() {
+    let mut chunk = Chunk::default();
+    chunk.set(BlockPos::new(3, 4, 5).local(), Block::Glass);
+
+    assert_eq!(0xAB, round_trip(&0xABu8));
+    assert_eq!(u64::MAX - 7, round_trip(&(u64::MAX - 7)));
+    assert_eq!(-2.5, round_trip(&-2.5f32));
+    assert_eq!("héllo", round_trip(&"héllo".to_owned()));
+    assert_eq!(
+      Vec3::new(1.0, -2.0, 3.5),
+      round_trip(&Vec3::new(1.0, -2.0, 3.5))
+    );
+    assert_eq!(Angle::radians(1.25), round_trip(&Angle::radians(1.25)));
+    assert_eq!(
+      BlockPos::new(-70_000, 12, 3),
+      round_trip(&BlockPos::new(-70_000, 12, 3))
+    );
+    assert_eq!(Block::Leaves, round_trip(&Block::Leaves));
+    assert!(chunk == round_trip(&chunk));
+  }
+
+  // Check that invalid values are rejected rather than decoded.
+  #[test]
+  fn test_invalid_values_rejected() {
+    assert!(f32::decode(&mut ByteReader::new(&f32::NAN.to_le_bytes())).is_err());
+    assert!(Block::decode(&mut ByteReader::new(&[255])).is_err());
+    assert!(String::decode(&mut ByteReader::new(&[2, 0, 0xFF, 0xFE])).is_err());
+    assert!(String::decode(&mut ByteReader::new(&[5, 0, b'a'])).is_err());
+  }
+
+  // Check that frames are only decoded once all of their bytes have arrived.
+  #[test]
+  fn test_partial_frames() {
+    let mut bytes = encode_frame(&"first".to_owned());
+    bytes.extend(encode_frame(&"second".to_owned()));
+    let first_len = 4 + 2 + "first".len();
+
+    for len in 0..first_len {
+      assert_eq!(None, decode_frame::<String>(&bytes[..len]).unwrap());
+    }
+
+    let (first, len) = decode_frame::<String>(&bytes).unwrap().unwrap();
+    assert_eq!(("first", first_len), (first.as_str(), len));
+    let (second, _) = decode_frame::<String>(&bytes[len..]).unwrap().unwrap();
+    assert_eq!("second", second);
+  }
+
+  // Check that frames which are too long, or longer than their message, are
+  // rejected.
+  #[test]
+  fn test_invalid_frames_rejected() {
+    let too_long: u32 = (MAX_FRAME_LEN + 1).coerce();
+    assert!(decode_frame::<u8>(&too_long.to_le_bytes()).is_err());
+
+    let mut trailing = encode_frame(&7u8);
+    trailing[0] += 1;
+    trailing.push(0);
+    assert!(decode_frame::<u8>(&trailing).is_err());
+  }
+}
@@ -0,0 +1,54 @@
+use crate::net::codec::{self, Decode, Encode};
+use crate::platform::Socket;
+use anyhow::{Result, bail};
+
+/// A stream of framed messages over a [`Socket`], which never blocks.
+pub struct Connection {
+  socket: Socket,
+  /// Bytes received which don't yet make up a whole frame.
+  received: Vec<u8>,
+  is_open: bool,
+}
+
+impl Connection {
+  pub fn new(socket: Socket) -> Self {
+    Self {
+      socket,
+      received: Vec::new(),
+      is_open: true,
+    }
+  }
+
+  pub fn connect(address: &str) -> Result<Self> {
+    Ok(Self::new(Socket::connect(address)?))
+  }
+
+  pub fn send(&mut self, message: &impl Encode) -> Result<()> {
+    self.socket.send(&codec::encode_frame(message))
+  }
+
+  /// Sends any messages which couldn't be sent immediately.
+  pub fn flush(&mut self) -> Result<()> {
+    self.socket.flush()
+  }
+
+  /// Returns the next message received, or [`None`] if there isn't a whole
+  /// message yet.
+  ///
+  /// Messages received before the connection closed are still returned, after
+  /// which this fails.
+  pub fn receive<M: Decode>(&mut self) -> Result<Option<M>> {
+    if self.is_open {
+      self.is_open = self.socket.receive(&mut self.received)?;
+    }
+
+    match codec::decode_frame(&self.received)? {
+      Some((message, len)) => {
+        self.received.drain(..len);
+        Ok(Some(message))
+      }
+      None if self.is_open => Ok(None),
+      None => bail!("connection closed"),
+    }
+  }
+}
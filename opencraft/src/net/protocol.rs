@@ -0,0 +1,405 @@
+//! Messages exchanged between clients and servers.
+//!
+//! A client opens a connection by sending [`ClientMessage::Handshake`] then
+//! [`ClientMessage::Login`]. The server answers the handshake with its own, or
+//! with [`ServerMessage::Disconnect`] if the protocol versions differ, then
+//! answers the login with [`ServerMessage::LoginSuccess`] and starts streaming
+//! chunks around the player, along with the [`ServerMessage::Time`] every
+//! second.
+//!
+//! Each message is encoded as a `u8` tag identifying its type, followed by its
+//! fields in order, using the encodings in [`codec`](super::codec).
+
+use crate::core::bytes::{ByteReader, ByteWriter};
+use crate::core::math::angle::Angle;
+use crate::core::math::vec3::Vec3;
+use crate::net::codec::{Decode, Encode};
+use crate::world::block::Block;
+use crate::world::chunk::Chunk;
+use crate::world::position::{BlockPos, ChunkPos};
+use crate::world::time::WorldTime;
+use anyhow::{Result, bail};
+use std::time::Duration;
+
+/// Version of the protocol, which is bumped whenever it changes.
+pub const PROTOCOL_VERSION: u32 = 2;
+pub const DEFAULT_PORT: u16 = 25_570;
+/// Port for clients connecting over WebSockets, such as browsers.
+pub const DEFAULT_WEBSOCKET_PORT: u16 = 25_571;
+/// How often clients report their player's position.
+pub const POSITION_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Identifies a player for as long as they're connected to a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PlayerId(pub u32);
+
+impl Encode for PlayerId {
+  fn encode(&self, writer: &mut ByteWriter) {
+    self.0.encode(writer);
+  }
+}
+
+impl Decode for PlayerId {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    Ok(Self(u32::decode(reader)?))
+  }
+}
+
+/// Messages sent from clients to the server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientMessage {
+  Handshake {
+    protocol_version: u32,
+  },
+  Login {
+    name: String,
+  },
+  /// Asks to place or break a block, which the server confirms with a
+  /// [`ServerMessage::BlockChange`].
+  SetBlock {
+    pos: BlockPos,
+    block: Block,
+  },
+  /// Reports where the client's player is, and where they're looking.
+  PlayerPosition {
+    position: Vec3,
+    yaw: Angle,
+    pitch: Angle,
+  },
+  Chat {
+    text: String,
+  },
+  Disconnect {
+    reason: String,
+  },
+}
+
+/// Messages sent from the server to clients.
+#[derive(Debug, PartialEq)]
+pub enum ServerMessage {
+  Handshake {
+    protocol_version: u32,
+  },
+  LoginSuccess {
+    player: PlayerId,
+    /// Seed of the world, which clients use to look up biomes.
+    seed: u64,
+    time: WorldTime,
+    position: Vec3,
+    yaw: Angle,
+    pitch: Angle,
+  },
+  ChunkData {
+    pos: ChunkPos,
+    chunk: Box<Chunk>,
+  },
+  /// A block which changed, or which a client asked to change but couldn't.
+  BlockChange {
+    pos: BlockPos,
+    block: Block,
+  },
+  /// Reports where another player is, or moves the client's own player back
+  /// after a move the server rejected.
+  PlayerPosition {
+    player: PlayerId,
+    position: Vec3,
+    yaw: Angle,
+    pitch: Angle,
+  },
+  PlayerLeft {
+    player: PlayerId,
+  },
+  Chat {
+    sender: String,
+    text: String,
+  },
+  Disconnect {
+    reason: String,
+  },
+  /// The world's time, which clients advance themselves between updates.
+  Time {
+    time: WorldTime,
+  },
+}
+
+impl Encode for ClientMessage {
+  fn encode(&self, writer: &mut ByteWriter) {
+    match self {
+      ClientMessage::Handshake { protocol_version } => {
+        writer.u8(0);
+        protocol_version.encode(writer);
+      }
+      ClientMessage::Login { name } => {
+        writer.u8(1);
+        name.encode(writer);
+      }
+      ClientMessage::SetBlock { pos, block } => {
+        writer.u8(2);
+        pos.encode(writer);
+        block.encode(writer);
+      }
+      ClientMessage::PlayerPosition {
+        position,
+        yaw,
+        pitch,
+      } => {
+        writer.u8(3);
+        position.encode(writer);
+        yaw.encode(writer);
+        pitch.encode(writer);
+      }
+      ClientMessage::Chat { text } => {
+        writer.u8(4);
+        text.encode(writer);
+      }
+      ClientMessage::Disconnect { reason } => {
+        writer.u8(5);
+        reason.encode(writer);
+      }
+    }
+  }
+}
+
+impl Decode for ClientMessage {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    Ok(match reader.u8()? {
+      0 => ClientMessage::Handshake {
+        protocol_version: Decode::decode(reader)?,
+      },
+      1 => ClientMessage::Login {
+        name: Decode::decode(reader)?,
+      },
+      2 => ClientMessage::SetBlock {
+        pos: Decode::decode(reader)?,
+        block: Decode::decode(reader)?,
+      },
+      3 => ClientMessage::PlayerPosition {
+        position: Decode::decode(reader)?,
+        yaw: Decode::decode(reader)?,
+        pitch: Decode::decode(reader)?,
+      },
+      4 => ClientMessage::Chat {
+        text: Decode::decode(reader)?,
+      },
+      5 => ClientMessage::Disconnect {
+        reason: Decode::decode(reader)?,
+      },
+      tag => bail!("unknown client message {tag}"),
+    })
+  }
+}
+
+impl Encode for ServerMessage {
+  fn encode(&self, writer: &mut ByteWriter) {
+    match self {
+      ServerMessage::Handshake { protocol_version } => {
+        writer.u8(0);
+        protocol_version.encode(writer);
+      }
+      ServerMessage::LoginSuccess {
+        player,
+        seed,
+        time,
+        position,
+        yaw,
+        pitch,
+      } => {
+        writer.u8(1);
+        player.encode(writer);
+        seed.encode(writer);
+        time.encode(writer);
+        position.encode(writer);
+        yaw.encode(writer);
+        pitch.encode(writer);
+      }
+      ServerMessage::ChunkData { pos, chunk } => {
+        writer.u8(2);
+        pos.encode(writer);
+        chunk.encode(writer);
+      }
+      ServerMessage::BlockChange { pos, block } => {
+        writer.u8(3);
+        pos.encode(writer);
+        block.encode(writer);
+      }
+      ServerMessage::PlayerPosition {
+        player,
+        position,
+        yaw,
+        pitch,
+      } => {
+        writer.u8(4);
+        player.encode(writer);
+        position.encode(writer);
+        yaw.encode(writer);
+        pitch.encode(writer);
+      }
+      ServerMessage::PlayerLeft { player } => {
+        writer.u8(5);
+        player.encode(writer);
+      }
+      ServerMessage::Chat { sender, text } => {
+        writer.u8(6);
+        sender.encode(writer);
+        text.encode(writer);
+      }
+      ServerMessage::Disconnect { reason } => {
+        writer.u8(7);
+        reason.encode(writer);
+      }
+      ServerMessage::Time { time } => {
+        writer.u8(8);
+        time.encode(writer);
+      }
+    }
+  }
+}
+
+impl Decode for ServerMessage {
+  fn decode(reader: &mut ByteReader) -> Result<Self> {
+    Ok(match reader.u8()? {
+      0 => ServerMessage::Handshake {
+        protocol_version: Decode::decode(reader)?,
+      },
+      1 => ServerMessage::LoginSuccess {
+        player: Decode::decode(reader)?,
+        seed: Decode::decode(reader)?,
+        time: Decode::decode(reader)?,
+        position: Decode::decode(reader)?,
+        yaw: Decode::decode(reader)?,
+        pitch: Decode::decode(reader)?,
+      },
+      2 => ServerMessage::ChunkData {
+        pos: Decode::decode(reader)?,
+        chunk: Box::new(Decode::decode(reader)?),
+      },
+      3 => ServerMessage::BlockChange {
+        pos: Decode::decode(reader)?,
+        block: Decode::decode(reader)?,
+      },
+      4 => ServerMessage::PlayerPosition {
+        player: Decode::decode(reader)?,
+        position: Decode::decode(reader)?,
+        yaw: Decode::decode(reader)?,
+        pitch: Decode::decode(reader)?,
+      },
+      5 => ServerMessage::PlayerLeft {
+        player: Decode::decode(reader)?,
+      },
+      6 => ServerMessage::Chat {
+        sender: Decode::decode(reader)?,
+        text: Decode::decode(reader)?,
+      },
+      7 => ServerMessage::Disconnect {
+        reason: Decode::decode(reader)?,
+      },
+      8 => ServerMessage::Time {
+        time: Decode::decode(reader)?,
+      },
+      tag => bail!("unknown server message {tag}"),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::net::codec::{decode_frame, encode_frame};
+  use crate::world::generation::TerrainGenerator;
+
+  fn round_trip<M: Encode + Decode>(message: &M) -> M {
+    let frame = encode_frame(message);
+    let (decoded, len) = decode_frame(&frame).unwrap().unwrap();
+    assert_eq!(frame.len(), len);
+    decoded
+  }
+
+  // Check that every client message decodes to what was encoded.
+  #[test]
+  fn test_client_messages_round_trip() {
+    let messages = [
+      ClientMessage::Handshake {
+        protocol_version: PROTOCOL_VERSION,
+      },
+      ClientMessage::Login {
+        name: "Steve".to_owned(),
+      },
+      ClientMessage::SetBlock {
+        pos: BlockPos::new(-4, 17, 9),
+        block: Block::Sand,
+      },
+      ClientMessage::PlayerPosition {
+        position: Vec3::new(0.5, 64.0, -12.25),
+        yaw: Angle::radians(3.0),
+        pitch: Angle::radians(-0.5),
+      },
+      ClientMessage::Chat {
+        text: "hello".to_owned(),
+      },
+      ClientMessage::Disconnect {
+        reason: "quit".to_owned(),
+      },
+    ];
+
+    for message in messages {
+      assert_eq!(message, round_trip(&message));
+    }
+  }
+
+  // Check that every server message decodes to what was encoded.
+  #[test]
+  fn test_server_messages_round_trip() {
+    let chunk_pos = ChunkPos::new(1, -1, 2);
+    let messages = [
+      ServerMessage::Handshake {
+        protocol_version: PROTOCOL_VERSION,
+      },
+      ServerMessage::LoginSuccess {
+        player: PlayerId(7),
+        seed: 0xDEAD_BEEF,
+        time: WorldTime::from_millis(86_400_000),
+        position: Vec3::new(1.0, 2.0, 3.0),
+        yaw: Angle::radians(0.25),
+        pitch: Angle::radians(0.0),
+      },
+      ServerMessage::ChunkData {
+        pos: chunk_pos,
+        chunk: Box::new(TerrainGenerator::new(42).generate(chunk_pos)),
+      },
+      ServerMessage::BlockChange {
+        pos: BlockPos::new(8, -3, 100),
+        block: Block::Air,
+      },
+      ServerMessage::PlayerPosition {
+        player: PlayerId(3),
+        position: Vec3::new(-5.0, 10.5, 0.0),
+        yaw: Angle::radians(1.0),
+        pitch: Angle::radians(-1.0),
+      },
+      ServerMessage::PlayerLeft {
+        player: PlayerId(3),
+      },
+      ServerMessage::Chat {
+        sender: "Alex".to_owned(),
+        text: "hi".to_owned(),
+      },
+      ServerMessage::Disconnect {
+        reason: "server stopped".to_owned(),
+      },
+      ServerMessage::Time {
+        time: WorldTime::from_millis(1_234),
+      },
+    ];
+
+    for message in messages {
+      assert_eq!(message, round_trip(&message));
+    }
+  }
+
+  // Check that unknown message types are rejected.
+  #[test]
+  fn test_unknown_message_rejected() {
+    assert!(ClientMessage::decode(&mut ByteReader::new(&[200])).is_err());
+    assert!(ServerMessage::decode(&mut ByteReader::new(&[200])).is_err());
+  }
+}
@@ -1,7 +1,17 @@
 use anyhow::{Result, bail};
-use std::path::PathBuf;
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "client")]
+use std::thread;
+#[cfg(feature = "client")]
 use std::time::Duration;
-use std::{env, fs, thread};
+use std::{env, fs, mem};
+use tungstenite::handshake::client::ClientHandshake;
+use tungstenite::handshake::server::{NoCallback, ServerHandshake};
+use tungstenite::handshake::{HandshakeError, MidHandshake};
+use tungstenite::{Error as WebSocketError, Message, WebSocket};
+#[cfg(feature = "client")]
 use winit::window::WindowAttributes;
 
 #[rustfmt::skip]
@@ -22,10 +32,19 @@ pub fn init_logging() {
   env_logger::init();
 }
 
+/// Returns the address of the multiplayer server to join, given as the first
+/// command line argument, or [`None`] to play a local world.
+#[cfg(feature = "client")]
+pub fn server_address() -> Option<String> {
+  env::args().nth(1)
+}
+
+#[cfg(feature = "client")]
 pub fn init_window_attributes(window_attributes: WindowAttributes) -> WindowAttributes {
   window_attributes
 }
 
+#[cfg(feature = "client")]
 pub fn run_future<F>(future: F)
 where
   F: Future<Output = ()> + 'static,
@@ -35,14 +54,17 @@ where
 
 // Note that, as per `run_future`, futures on desktop platforms block so using a
 // blocking sleep is not a problem.
+#[cfg(feature = "client")]
 pub async fn sleep(duration: Duration) {
   thread::sleep(duration)
 }
 
+#[cfg(feature = "client")]
 pub struct ResourceReader {
   assets: PathBuf,
 }
 
+#[cfg(feature = "client")]
 impl ResourceReader {
   pub fn new() -> Result<Self> {
     let mut path = env::current_exe()?.parent().unwrap().to_owned();
@@ -75,3 +97,253 @@ impl ResourceReader {
     Ok(fs::read(self.assets.join(path))?)
   }
 }
+
+/// Persistent key-value storage for saved data, with `/` separated keys.
+///
+/// Each key is stored as a file of the same path under the `saves` folder next
+/// to the executable.
+pub struct Storage {
+  root: PathBuf,
+}
+
+impl Storage {
+  pub fn new() -> Result<Self> {
+    Ok(Self::at(
+      env::current_exe()?.parent().unwrap().join("saves"),
+    ))
+  }
+
+  pub fn at(root: impl AsRef<Path>) -> Self {
+    Self {
+      root: root.as_ref().to_owned(),
+    }
+  }
+
+  /// Returns the value of `key`, or [`None`] if it has never been written.
+  pub fn read(&self, key: &str) -> Result<Option<Vec<u8>>> {
+    let path = self.root.join(key);
+
+    if fs::exists(&path)? {
+      Ok(Some(fs::read(path)?))
+    } else {
+      Ok(None)
+    }
+  }
+
+  pub fn write(&self, key: &str, value: &[u8]) -> Result<()> {
+    let path = self.root.join(key);
+    fs::create_dir_all(path.parent().unwrap())?;
+
+    // Write to a temporary file first, so that a crash mid-write can't leave a
+    // half written file behind
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, value)?;
+    fs::rename(&temporary, path)?;
+
+    Ok(())
+  }
+
+  /// Returns the names of the keys directly within `directory`, so for keys
+  /// `a/b` and `a/c/d`, listing `a` returns only `b`.
+  pub fn list(&self, directory: &str) -> Result<Vec<String>> {
+    let path = self.root.join(directory);
+    if !fs::exists(&path)? {
+      return Ok(Vec::new());
+    }
+
+    let mut names = Vec::new();
+    for entry in fs::read_dir(path)? {
+      let entry = entry?;
+
+      if entry.file_type()?.is_file()
+        && let Some(name) = entry.file_name().to_str()
+      {
+        names.push(name.to_owned());
+      }
+    }
+
+    Ok(names)
+  }
+}
+
+/// A connection which never blocks, over either TCP or a WebSocket, queueing
+/// any bytes which can't be sent immediately.
+///
+/// Over a WebSocket, each send is a binary message, so the bytes form the same
+/// stream as they would over TCP.
+pub struct Socket {
+  transport: Transport,
+  unsent: Vec<u8>,
+}
+
+enum Transport {
+  Tcp(TcpStream),
+  /// A WebSocket whose opening handshake hasn't completed yet.
+  Handshaking(Option<Box<PendingWebSocket>>),
+  WebSocket(Box<WebSocket<TcpStream>>),
+}
+
+enum PendingWebSocket {
+  Client(MidHandshake<ClientHandshake<TcpStream>>),
+  Server(MidHandshake<ServerHandshake<TcpStream, NoCallback>>),
+}
+
+impl PendingWebSocket {
+  /// Continues the handshake, returning the WebSocket once it has completed,
+  /// or the pending handshake if it needs more bytes.
+  fn handshake(self) -> Result<Transport> {
+    let result = match self {
+      PendingWebSocket::Client(handshake) => match handshake.handshake() {
+        Ok((websocket, _)) => Ok(websocket),
+        Err(HandshakeError::Interrupted(handshake)) => Err(PendingWebSocket::Client(handshake)),
+        Err(HandshakeError::Failure(err)) => bail!("WebSocket handshake failed: {}", err),
+      },
+      PendingWebSocket::Server(handshake) => match handshake.handshake() {
+        Ok(websocket) => Ok(websocket),
+        Err(HandshakeError::Interrupted(handshake)) => Err(PendingWebSocket::Server(handshake)),
+        Err(HandshakeError::Failure(err)) => bail!("WebSocket handshake failed: {}", err),
+      },
+    };
+
+    Ok(match result {
+      Ok(websocket) => Transport::WebSocket(Box::new(websocket)),
+      Err(pending) => Transport::Handshaking(Some(Box::new(pending))),
+    })
+  }
+}
+
+impl Socket {
+  /// Connects to `address`, over a WebSocket if it's a `ws://` URL, or
+  /// otherwise over TCP.
+  pub fn connect(address: &str) -> Result<Self> {
+    let Some(host) = address.strip_prefix("ws://") else {
+      return Self::from_stream(TcpStream::connect(address)?);
+    };
+
+    let stream = Self::prepare(TcpStream::connect(host.split('/').next().unwrap())?)?;
+    Self::new(match tungstenite::client(address, stream) {
+      Ok((websocket, _)) => Transport::WebSocket(Box::new(websocket)),
+      Err(HandshakeError::Interrupted(handshake)) => {
+        Transport::Handshaking(Some(Box::new(PendingWebSocket::Client(handshake))))
+      }
+      Err(HandshakeError::Failure(err)) => bail!("WebSocket handshake failed: {}", err),
+    })
+  }
+
+  pub fn from_stream(stream: TcpStream) -> Result<Self> {
+    Self::new(Transport::Tcp(Self::prepare(stream)?))
+  }
+
+  /// Accepts a WebSocket connection from a client, completing the handshake
+  /// as its bytes arrive.
+  pub fn accept_websocket(stream: TcpStream) -> Result<Self> {
+    Self::new(match tungstenite::accept(Self::prepare(stream)?) {
+      Ok(websocket) => Transport::WebSocket(Box::new(websocket)),
+      Err(HandshakeError::Interrupted(handshake)) => {
+        Transport::Handshaking(Some(Box::new(PendingWebSocket::Server(handshake))))
+      }
+      Err(HandshakeError::Failure(err)) => bail!("WebSocket handshake failed: {}", err),
+    })
+  }
+
+  fn prepare(stream: TcpStream) -> Result<TcpStream> {
+    stream.set_nonblocking(true)?;
+    stream.set_nodelay(true)?;
+    Ok(stream)
+  }
+
+  fn new(transport: Transport) -> Result<Self> {
+    Ok(Self {
+      transport,
+      unsent: Vec::new(),
+    })
+  }
+
+  /// Queues `bytes` to be sent, and sends as many queued bytes as possible.
+  pub fn send(&mut self, bytes: &[u8]) -> Result<()> {
+    self.unsent.extend_from_slice(bytes);
+    self.flush()
+  }
+
+  /// Sends as many queued bytes as possible.
+  pub fn flush(&mut self) -> Result<()> {
+    match &mut self.transport {
+      Transport::Tcp(stream) => {
+        while !self.unsent.is_empty() {
+          match stream.write(&self.unsent) {
+            Ok(0) => bail!("connection closed"),
+            Ok(written) => {
+              self.unsent.drain(..written);
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+          }
+        }
+
+        Ok(())
+      }
+      // Bytes are kept until the handshake completes
+      Transport::Handshaking(_) => Ok(()),
+      Transport::WebSocket(websocket) => {
+        // Messages which can't be written yet are buffered by the WebSocket
+        if !self.unsent.is_empty() {
+          websocket_result(websocket.write(Message::binary(mem::take(&mut self.unsent))))?;
+        }
+
+        websocket_result(websocket.flush())
+      }
+    }
+  }
+
+  /// Appends every byte received so far to `buffer`, and returns whether the
+  /// connection is still open.
+  pub fn receive(&mut self, buffer: &mut Vec<u8>) -> Result<bool> {
+    if let Transport::Handshaking(pending) = &mut self.transport {
+      self.transport = pending.take().unwrap().handshake()?;
+      if let Transport::Handshaking(_) = self.transport {
+        return Ok(true);
+      }
+      self.flush()?;
+    }
+
+    match &mut self.transport {
+      Transport::Tcp(stream) => {
+        let mut chunk = [0; 4096];
+
+        loop {
+          match stream.read(&mut chunk) {
+            Ok(0) => return Ok(false),
+            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(true),
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+          }
+        }
+      }
+      Transport::Handshaking(_) => unreachable!(),
+      Transport::WebSocket(websocket) => loop {
+        match websocket.read() {
+          Ok(Message::Binary(bytes)) => buffer.extend_from_slice(&bytes),
+          Ok(Message::Close(_)) => return Ok(false),
+          // Pings are answered automatically, and nothing else is used
+          Ok(_) => {}
+          Err(WebSocketError::Io(err)) if err.kind() == ErrorKind::WouldBlock => return Ok(true),
+          Err(WebSocketError::ConnectionClosed | WebSocketError::AlreadyClosed) => {
+            return Ok(false);
+          }
+          Err(err) => return Err(err.into()),
+        }
+      },
+    }
+  }
+}
+
+/// Treats a WebSocket operation which would block as successful, since its
+/// data is buffered until the next flush.
+fn websocket_result(result: Result<(), WebSocketError>) -> Result<()> {
+  match result {
+    Err(WebSocketError::Io(err)) if err.kind() == ErrorKind::WouldBlock => Ok(()),
+    result => Ok(result?),
+  }
+}
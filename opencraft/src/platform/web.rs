@@ -1,12 +1,23 @@
+#[cfg(feature = "client")]
 mod entry;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+#[cfg(feature = "client")]
 use gloo_timers::future::TimeoutFuture;
+use js_sys::{ArrayBuffer, Uint8Array};
 use log::Level;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+#[cfg(feature = "client")]
 use std::time::Duration;
+use wasm_bindgen::closure::Closure;
 use wasm_bindgen::{JsCast, UnwrapThrowExt};
-use web_sys::Window;
+#[cfg(feature = "client")]
+use web_sys::UrlSearchParams;
+use web_sys::{BinaryType, DomException, MessageEvent, WebSocket, Window};
+#[cfg(feature = "client")]
 use winit::platform::web::WindowAttributesExtWebSys;
+#[cfg(feature = "client")]
 use winit::window::WindowAttributes;
 
 #[rustfmt::skip]
@@ -31,6 +42,16 @@ fn window() -> Window {
   web_sys::window().expect_throw("could not get browser window object")
 }
 
+/// Returns the address of the multiplayer server to join, given as the
+/// `server` query parameter of the page's URL, or [`None`] to play a local
+/// world.
+#[cfg(feature = "client")]
+pub fn server_address() -> Option<String> {
+  let search = window().location().search().ok()?;
+  UrlSearchParams::new_with_str(&search).ok()?.get("server")
+}
+
+#[cfg(feature = "client")]
 pub fn init_window_attributes(window_attributes: WindowAttributes) -> WindowAttributes {
   const CANVAS_ID: &str = "app";
 
@@ -44,6 +65,7 @@ pub fn init_window_attributes(window_attributes: WindowAttributes) -> WindowAttr
   ))
 }
 
+#[cfg(feature = "client")]
 pub fn run_future<F>(future: F)
 where
   F: Future<Output = ()> + 'static,
@@ -51,6 +73,7 @@ where
   wasm_bindgen_futures::spawn_local(future)
 }
 
+#[cfg(feature = "client")]
 pub async fn sleep(duration: Duration) {
   if duration.as_millis() > i32::MAX.try_into().unwrap_throw() {
     panic!("sleep duration in milliseconds must fit into i32");
@@ -59,10 +82,12 @@ pub async fn sleep(duration: Duration) {
   TimeoutFuture::new(duration.as_millis().try_into().unwrap_throw()).await
 }
 
+#[cfg(feature = "client")]
 pub struct ResourceReader {
   origin: String,
 }
 
+#[cfg(feature = "client")]
 impl ResourceReader {
   pub fn new() -> Result<Self> {
     Ok(Self {
@@ -83,3 +108,198 @@ impl ResourceReader {
     )
   }
 }
+
+/// Persistent key-value storage for saved data, with `/` separated keys.
+///
+/// Values are kept in the browser's local storage, base64 encoded since local
+/// storage only holds strings, under keys prefixed with [`Storage::PREFIX`].
+pub struct Storage {
+  local_storage: web_sys::Storage,
+}
+
+impl Storage {
+  const PREFIX: &str = "opencraft/";
+
+  pub fn new() -> Result<Self> {
+    Ok(Self {
+      local_storage: window()
+        .local_storage()
+        .map_err(|err| anyhow!("could not access local storage: {:?}", err))?
+        .ok_or_else(|| anyhow!("local storage is unavailable"))?,
+    })
+  }
+
+  /// Returns the value of `key`, or [`None`] if it has never been written.
+  pub fn read(&self, key: &str) -> Result<Option<Vec<u8>>> {
+    let Some(encoded) = self
+      .local_storage
+      .get_item(&format!("{}{}", Self::PREFIX, key))
+      .map_err(|err| anyhow!("could not read {:?} from local storage: {:?}", key, err))?
+    else {
+      return Ok(None);
+    };
+
+    let decoded = window()
+      .atob(&encoded)
+      .map_err(|err| anyhow!("could not decode {:?}: {:?}", key, err))?;
+
+    // `atob` returns a "binary string", with one character per byte
+    Ok(Some(
+      decoded
+        .chars()
+        .map(u8::try_from)
+        .collect::<Result<_, _>>()?,
+    ))
+  }
+
+  pub fn write(&self, key: &str, value: &[u8]) -> Result<()> {
+    let binary: String = value.iter().copied().map(char::from).collect();
+    let encoded = window()
+      .btoa(&binary)
+      .map_err(|err| anyhow!("could not encode {:?}: {:?}", key, err))?;
+
+    self
+      .local_storage
+      .set_item(&format!("{}{}", Self::PREFIX, key), &encoded)
+      .map_err(|err| {
+        // Browsers only give each site a few megabytes of local storage
+        let quota_exceeded = err.dyn_ref::<DomException>().is_some_and(|err| {
+          matches!(
+            err.name().as_str(),
+            "QuotaExceededError" | "NS_ERROR_DOM_QUOTA_REACHED"
+          )
+        });
+
+        if quota_exceeded {
+          anyhow!(
+            "could not write {:?}, as its {} bytes would exceed the local storage quota",
+            key,
+            encoded.len()
+          )
+        } else {
+          anyhow!("could not write {:?} to local storage: {:?}", key, err)
+        }
+      })
+  }
+
+  /// Returns the names of the keys directly within `directory`, so for keys
+  /// `a/b` and `a/c/d`, listing `a` returns only `b`.
+  pub fn list(&self, directory: &str) -> Result<Vec<String>> {
+    let prefix = format!("{}{}/", Self::PREFIX, directory);
+    let length = self
+      .local_storage
+      .length()
+      .map_err(|err| anyhow!("could not list local storage: {:?}", err))?;
+
+    let mut names = Vec::new();
+    for index in 0..length {
+      let key = self
+        .local_storage
+        .key(index)
+        .map_err(|err| anyhow!("could not list local storage: {:?}", err))?;
+
+      if let Some(name) = key.as_deref().and_then(|key| key.strip_prefix(&prefix))
+        && !name.contains('/')
+      {
+        names.push(name.to_owned());
+      }
+    }
+
+    Ok(names)
+  }
+}
+
+/// A connection to a multiplayer server over a WebSocket, since browsers can't
+/// open TCP connections.
+///
+/// Each send is a binary message, so the bytes form the same stream as they
+/// would over TCP. Bytes sent before the WebSocket opens are queued.
+pub struct Socket {
+  websocket: WebSocket,
+  unsent: Vec<u8>,
+  received: Rc<RefCell<Vec<u8>>>,
+  is_closed: Rc<Cell<bool>>,
+  // Kept alive for as long as the WebSocket calls them
+  _on_message: Closure<dyn FnMut(MessageEvent)>,
+  _on_close: Closure<dyn FnMut()>,
+}
+
+impl Socket {
+  /// Connects to `address`, which is a `ws://` or `wss://` URL, or else a host
+  /// and port to connect to with `ws://`.
+  pub fn connect(address: &str) -> Result<Self> {
+    let url = if address.contains("://") {
+      address.to_owned()
+    } else {
+      format!("ws://{address}")
+    };
+    let websocket =
+      WebSocket::new(&url).map_err(|err| anyhow!("could not connect to {:?}: {:?}", url, err))?;
+    websocket.set_binary_type(BinaryType::Arraybuffer);
+
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let on_message = Closure::<dyn FnMut(MessageEvent)>::new({
+      let received = received.clone();
+      move |event: MessageEvent| {
+        if let Ok(bytes) = event.data().dyn_into::<ArrayBuffer>() {
+          received
+            .borrow_mut()
+            .extend(Uint8Array::new(&bytes).to_vec());
+        }
+      }
+    });
+    websocket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+    // Errors are always followed by the WebSocket closing
+    let is_closed = Rc::new(Cell::new(false));
+    let on_close = Closure::<dyn FnMut()>::new({
+      let is_closed = is_closed.clone();
+      move || is_closed.set(true)
+    });
+    websocket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+    Ok(Self {
+      websocket,
+      unsent: Vec::new(),
+      received,
+      is_closed,
+      _on_message: on_message,
+      _on_close: on_close,
+    })
+  }
+
+  /// Queues `bytes` to be sent, and sends them if the WebSocket is open.
+  pub fn send(&mut self, bytes: &[u8]) -> Result<()> {
+    self.unsent.extend_from_slice(bytes);
+    self.flush()
+  }
+
+  /// Sends the queued bytes if the WebSocket is open.
+  pub fn flush(&mut self) -> Result<()> {
+    if self.unsent.is_empty() || self.websocket.ready_state() != WebSocket::OPEN {
+      return Ok(());
+    }
+
+    self
+      .websocket
+      .send_with_u8_array(&self.unsent)
+      .map_err(|err| anyhow!("could not send over WebSocket: {:?}", err))?;
+    self.unsent.clear();
+    Ok(())
+  }
+
+  /// Appends every byte received so far to `buffer`, and returns whether the
+  /// connection is still open.
+  pub fn receive(&mut self, buffer: &mut Vec<u8>) -> Result<bool> {
+    buffer.append(&mut self.received.borrow_mut());
+    Ok(!self.is_closed.get())
+  }
+}
+
+impl Drop for Socket {
+  fn drop(&mut self) {
+    self.websocket.set_onmessage(None);
+    self.websocket.set_onclose(None);
+    self.websocket.close().ok();
+  }
+}
@@ -1,18 +1,26 @@
 use crate::camera::Direction;
-use crate::core;
-use crate::core::math;
+use crate::core::math::aligned_box3::AlignedBox3;
 use crate::core::math::angle::Angle;
-use crate::core::math::mat4;
 use crate::core::math::mat4::Mat4x4;
 use crate::core::math::vec2::Vec2;
+use crate::core::math::vec3::Vec3;
+use crate::core::math::{self, mat4};
 use crate::core::type_conversions::{Coerce, CoerceLossy};
-use crate::model::{BLOCK_LIMIT, CUBE_EXTENT, Scene};
+use crate::hotbar::HotbarVertex;
+use crate::mesh::{BlockVertex, MeshOptions, Mesher};
+use crate::model::Scene;
 use crate::platform::ResourceReader;
 use crate::resources::Texture;
+use crate::settings::Settings;
 use crate::text::{FontAtlas, TextVertex};
-use anyhow::Result;
+use crate::world::World;
+use crate::world::chunk::CHUNK_SIZE;
+use crate::world::position::ChunkPos;
+use crate::{hotbar, mesh};
+use anyhow::{Result, bail};
 use image::GenericImageView;
-use std::sync::{Arc, LazyLock};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::{iter, mem};
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 use wgpu::wgt::TextureDataOrder;
@@ -38,207 +46,176 @@ use zerocopy::{Immutable, IntoBytes};
 
 const FONT_SCALE: f32 = 24.0;
 
-static FOV: LazyLock<Angle> = LazyLock::new(|| Angle::degrees(75.0));
 const Z_NEAR: f32 = 0.01;
-const Z_FAR: f32 = 1000.0;
-
-const BACK: f32 = CUBE_EXTENT;
-const FRONT: f32 = -CUBE_EXTENT;
-const BOTTOM: f32 = -CUBE_EXTENT;
-const TOP: f32 = CUBE_EXTENT;
-const LEFT: f32 = -CUBE_EXTENT;
-const RIGHT: f32 = CUBE_EXTENT;
-
-const TEX_WIDTH: f32 = 48.0;
-const TEX_HEIGHT: f32 = 64.0;
-
-const TEX_FRONT_LEFT: f32 = 16.0 / TEX_WIDTH;
-const TEX_FRONT_RIGHT: f32 = 32.0 / TEX_WIDTH;
-const TEX_FRONT_TOP: f32 = 32.0 / TEX_HEIGHT;
-const TEX_FRONT_BOTTOM: f32 = 48.0 / TEX_HEIGHT;
-
-const TEX_BACK_LEFT: f32 = 16.0 / TEX_WIDTH;
-const TEX_BACK_RIGHT: f32 = 32.0 / TEX_WIDTH;
-const TEX_BACK_TOP: f32 = 0.0 / TEX_HEIGHT;
-const TEX_BACK_BOTTOM: f32 = 16.0 / TEX_HEIGHT;
-
-const TEX_TOP_LEFT: f32 = 16.0 / TEX_WIDTH;
-const TEX_TOP_RIGHT: f32 = 32.0 / TEX_WIDTH;
-const TEX_TOP_TOP: f32 = 16.0 / TEX_HEIGHT;
-const TEX_TOP_BOTTOM: f32 = 32.0 / TEX_HEIGHT;
-
-const TEX_BOTTOM_LEFT: f32 = 16.0 / TEX_WIDTH;
-const TEX_BOTTOM_RIGHT: f32 = 32.0 / TEX_WIDTH;
-const TEX_BOTTOM_TOP: f32 = 48.0 / TEX_HEIGHT;
-const TEX_BOTTOM_BOTTOM: f32 = 64.0 / TEX_HEIGHT;
-
-const TEX_LEFT_LEFT: f32 = 0.0 / TEX_WIDTH;
-const TEX_LEFT_RIGHT: f32 = 16.0 / TEX_WIDTH;
-const TEX_LEFT_TOP: f32 = 16.0 / TEX_HEIGHT;
-const TEX_LEFT_BOTTOM: f32 = 32.0 / TEX_HEIGHT;
-
-const TEX_RIGHT_LEFT: f32 = 32.0 / TEX_WIDTH;
-const TEX_RIGHT_RIGHT: f32 = 48.0 / TEX_WIDTH;
-const TEX_RIGHT_TOP: f32 = 16.0 / TEX_HEIGHT;
-const TEX_RIGHT_BOTTOM: f32 = 32.0 / TEX_HEIGHT;
+
+/// Colours of the night sky straight up and at the horizon.
+const NIGHT_ZENITH_COLOUR: Vec3 = Vec3::new(0.005, 0.007, 0.02);
+const NIGHT_HORIZON_COLOUR: Vec3 = Vec3::new(0.02, 0.03, 0.07);
+/// How far the daytime sky pales towards white at the horizon.
+const DAY_HORIZON_HAZE: f32 = 0.35;
+/// Fraction of the render distance at which fog starts to thicken, reaching
+/// the horizon colour at the edge of the loaded chunks.
+const FOG_START: f32 = 0.6;
+
+const CUBE_SIZE: f32 = 1.0;
+const CUBE_HALF: f32 = CUBE_SIZE / 2.0;
+
+const BACK: f32 = CUBE_HALF;
+const FRONT: f32 = -CUBE_HALF;
+const BOTTOM: f32 = -CUBE_HALF;
+const TOP: f32 = CUBE_HALF;
+const LEFT: f32 = -CUBE_HALF;
+const RIGHT: f32 = CUBE_HALF;
 
 #[repr(C)]
 #[derive(Clone, Copy, Immutable, IntoBytes)]
 struct Vertex {
   position: [f32; 3],
-  texture_coordinate: [f32; 2],
+}
+
+/// How the skybox shader draws the sky.
+#[repr(C)]
+#[derive(Clone, Copy, Immutable, IntoBytes)]
+struct Sky {
+  zenith_colour: [f32; 3],
+  /// How light the sky is, from 0 at night to 1 during the day.
+  daylight: f32,
+  /// Colour of the sky at the horizon, which distant blocks fade into.
+  horizon_colour: [f32; 3],
+  _padding0: f32,
+  sun_direction: [f32; 3],
+  _padding1: f32,
+}
+
+/// The lighting and fog which the block shader shades every block with.
+#[repr(C)]
+#[derive(Clone, Copy, Immutable, IntoBytes)]
+struct BlockEnvironment {
+  camera_position: [f32; 3],
+  /// Factor which sky light is scaled by at the time of day.
+  ambient_light: f32,
+  fog_colour: [f32; 3],
+  /// Horizontal distances from the camera at which fog starts, and at which
+  /// it hides blocks completely.
+  fog_start: f32,
+  fog_end: f32,
+  _padding: [f32; 3],
 }
 
 const VERTICES: &[Vertex] = &[
   // Front face
   Vertex {
     position: [LEFT, TOP, FRONT],
-    texture_coordinate: [TEX_FRONT_LEFT, TEX_FRONT_TOP],
   },
   Vertex {
     position: [LEFT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_FRONT_LEFT, TEX_FRONT_BOTTOM],
   },
   Vertex {
     position: [RIGHT, TOP, FRONT],
-    texture_coordinate: [TEX_FRONT_RIGHT, TEX_FRONT_TOP],
   },
   Vertex {
     position: [RIGHT, TOP, FRONT],
-    texture_coordinate: [TEX_FRONT_RIGHT, TEX_FRONT_TOP],
   },
   Vertex {
     position: [LEFT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_FRONT_LEFT, TEX_FRONT_BOTTOM],
   },
   Vertex {
     position: [RIGHT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_FRONT_RIGHT, TEX_FRONT_BOTTOM],
   },
   // Back face
   Vertex {
     position: [LEFT, TOP, BACK],
-    texture_coordinate: [TEX_BACK_LEFT, TEX_BACK_BOTTOM],
   },
   Vertex {
     position: [RIGHT, TOP, BACK],
-    texture_coordinate: [TEX_BACK_RIGHT, TEX_BACK_BOTTOM],
   },
   Vertex {
     position: [LEFT, BOTTOM, BACK],
-    texture_coordinate: [TEX_BACK_LEFT, TEX_BACK_TOP],
   },
   Vertex {
     position: [RIGHT, BOTTOM, BACK],
-    texture_coordinate: [TEX_BACK_RIGHT, TEX_BACK_TOP],
   },
   Vertex {
     position: [LEFT, BOTTOM, BACK],
-    texture_coordinate: [TEX_BACK_LEFT, TEX_BACK_TOP],
   },
   Vertex {
     position: [RIGHT, TOP, BACK],
-    texture_coordinate: [TEX_BACK_RIGHT, TEX_BACK_BOTTOM],
   },
   // Top face
   Vertex {
     position: [LEFT, TOP, BACK],
-    texture_coordinate: [TEX_TOP_LEFT, TEX_TOP_TOP],
   },
   Vertex {
     position: [LEFT, TOP, FRONT],
-    texture_coordinate: [TEX_TOP_LEFT, TEX_TOP_BOTTOM],
   },
   Vertex {
     position: [RIGHT, TOP, BACK],
-    texture_coordinate: [TEX_TOP_RIGHT, TEX_TOP_TOP],
   },
   Vertex {
     position: [RIGHT, TOP, BACK],
-    texture_coordinate: [TEX_TOP_RIGHT, TEX_TOP_TOP],
   },
   Vertex {
     position: [LEFT, TOP, FRONT],
-    texture_coordinate: [TEX_TOP_LEFT, TEX_TOP_BOTTOM],
   },
   Vertex {
     position: [RIGHT, TOP, FRONT],
-    texture_coordinate: [TEX_TOP_RIGHT, TEX_TOP_BOTTOM],
   },
   // Bottom face
   Vertex {
     position: [RIGHT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_BOTTOM_RIGHT, TEX_BOTTOM_TOP],
   },
   Vertex {
     position: [LEFT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_BOTTOM_LEFT, TEX_BOTTOM_TOP],
   },
   Vertex {
     position: [LEFT, BOTTOM, BACK],
-    texture_coordinate: [TEX_BOTTOM_LEFT, TEX_BOTTOM_BOTTOM],
   },
   Vertex {
     position: [LEFT, BOTTOM, BACK],
-    texture_coordinate: [TEX_BOTTOM_LEFT, TEX_BOTTOM_BOTTOM],
   },
   Vertex {
     position: [RIGHT, BOTTOM, BACK],
-    texture_coordinate: [TEX_BOTTOM_RIGHT, TEX_BOTTOM_BOTTOM],
   },
   Vertex {
     position: [RIGHT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_BOTTOM_RIGHT, TEX_BOTTOM_TOP],
   },
   // Left face
   Vertex {
     position: [LEFT, TOP, BACK],
-    texture_coordinate: [TEX_LEFT_RIGHT, TEX_LEFT_TOP],
   },
   Vertex {
     position: [LEFT, BOTTOM, BACK],
-    texture_coordinate: [TEX_LEFT_LEFT, TEX_LEFT_TOP],
   },
   Vertex {
     position: [LEFT, TOP, FRONT],
-    texture_coordinate: [TEX_LEFT_RIGHT, TEX_LEFT_BOTTOM],
   },
   Vertex {
     position: [LEFT, TOP, FRONT],
-    texture_coordinate: [TEX_LEFT_RIGHT, TEX_LEFT_BOTTOM],
   },
   Vertex {
     position: [LEFT, BOTTOM, BACK],
-    texture_coordinate: [TEX_LEFT_LEFT, TEX_LEFT_TOP],
   },
   Vertex {
     position: [LEFT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_LEFT_LEFT, TEX_LEFT_BOTTOM],
   },
   // Right face
   Vertex {
     position: [RIGHT, TOP, BACK],
-    texture_coordinate: [TEX_RIGHT_LEFT, TEX_RIGHT_TOP],
   },
   Vertex {
     position: [RIGHT, TOP, FRONT],
-    texture_coordinate: [TEX_RIGHT_LEFT, TEX_RIGHT_BOTTOM],
   },
   Vertex {
     position: [RIGHT, BOTTOM, BACK],
-    texture_coordinate: [TEX_RIGHT_RIGHT, TEX_RIGHT_TOP],
   },
   Vertex {
     position: [RIGHT, BOTTOM, BACK],
-    texture_coordinate: [TEX_RIGHT_RIGHT, TEX_RIGHT_TOP],
   },
   Vertex {
     position: [RIGHT, TOP, FRONT],
-    texture_coordinate: [TEX_RIGHT_LEFT, TEX_RIGHT_BOTTOM],
   },
   Vertex {
     position: [RIGHT, BOTTOM, FRONT],
-    texture_coordinate: [TEX_RIGHT_RIGHT, TEX_RIGHT_BOTTOM],
   },
 ];
 
@@ -288,6 +265,8 @@ impl ScreenSpaceResources {
     config: &SurfaceConfiguration,
     fullscreen_copy_texture_bind_group_layout: &BindGroupLayout,
     default_sampler: &Sampler,
+    field_of_view: Angle,
+    render_distance: i32,
   ) -> Self {
     let width = config.width;
     let height = config.height;
@@ -339,13 +318,7 @@ impl ScreenSpaceResources {
     });
 
     Self {
-      perspective: mat4::perspective(
-        width.coerce_lossy(),
-        height.coerce_lossy(),
-        *FOV,
-        Z_NEAR,
-        Z_FAR,
-      ),
+      perspective: perspective(config, field_of_view, render_distance),
       depth_view: depth_texture.create_view(&TextureViewDescriptor::default()),
       render_view,
       fullscreen_copy_texture_bind_group,
@@ -353,6 +326,51 @@ impl ScreenSpaceResources {
   }
 }
 
+fn perspective(
+  config: &SurfaceConfiguration,
+  field_of_view: Angle,
+  render_distance: i32,
+) -> Mat4x4 {
+  mat4::perspective(
+    config.width.coerce_lossy(),
+    config.height.coerce_lossy(),
+    field_of_view,
+    Z_NEAR,
+    far_plane(render_distance),
+  )
+}
+
+/// Returns the horizontal distance from the camera at which fog hides blocks
+/// completely: the nearest edge of the chunks loaded with `render_distance`.
+fn fog_distance(render_distance: i32) -> f32 {
+  (render_distance * CHUNK_SIZE).coerce_lossy()
+}
+
+/// Returns the distance to the far plane, which is far enough to take in
+/// every chunk loaded with `render_distance`, even diagonally above the camera.
+fn far_plane(render_distance: i32) -> f32 {
+  2.0 * fog_distance(render_distance + 2)
+}
+
+fn present_mode(vsync: bool) -> PresentMode {
+  if vsync {
+    PresentMode::AutoVsync
+  } else {
+    PresentMode::AutoNoVsync
+  }
+}
+
+struct ChunkMesh {
+  vertex_buffer: Buffer,
+  vertex_count: u32,
+  /// Per-instance vertex buffer holding the chunk's origin, so that a chunk is
+  /// drawn as a single instance without a transform of its own.
+  origin_buffer: Buffer,
+  /// Box around the chunk, which is only drawn if the box may be on screen.
+  bounds: AlignedBox3,
+}
+
+/// Draws [`Scene`]s to a window, keeping the GPU resources they need.
 pub struct Renderer {
   font_atlas: FontAtlas,
 
@@ -361,21 +379,31 @@ pub struct Renderer {
   queue: Queue,
   config: SurfaceConfiguration,
   default_sampler: Sampler,
+  field_of_view: Angle,
+  render_distance: i32,
 
   screen: ScreenSpaceResources,
 
   transform_buffer: Buffer,
+  environment_buffer: Buffer,
   transform_bind_group: BindGroup,
   pipeline: RenderPipeline,
   vertex_buffer: Buffer,
-  grass_bind_group: BindGroup,
+  block_texture_layers: Vec<&'static str>,
+  block_texture_bind_group: BindGroup,
+  mesh_options: MeshOptions,
+  chunk_meshes: HashMap<ChunkPos, ChunkMesh>,
+  /// Chunks whose meshes were built with different options, which are rebuilt
+  /// along with the next frame's dirty chunks.
+  stale_chunks: HashSet<ChunkPos>,
 
   outline_transform_buffer: Buffer,
   outline_transform_bind_group: BindGroup,
   outline_pipeline: RenderPipeline,
 
   skybox_transform_buffer: Buffer,
-  skybox_transform_bind_group: BindGroup,
+  sky_buffer: Buffer,
+  skybox_bind_group: BindGroup,
   skybox_pipeline: RenderPipeline,
 
   fullscreen_copy_texture_bind_group_layout: BindGroupLayout,
@@ -386,13 +414,16 @@ pub struct Renderer {
   crosshair_bind_group: BindGroup,
   crosshair_pipeline: RenderPipeline,
 
+  hotbar_buffer: Option<Buffer>,
+  hotbar_pipeline: RenderPipeline,
+
   text_buffer: Option<Buffer>,
   text_bind_group: BindGroup,
   text_pipeline: RenderPipeline,
 }
 
 impl Renderer {
-  pub async fn new(window: Arc<Window>) -> Result<Self> {
+  pub async fn new(window: Arc<Window>, settings: &Settings) -> Result<Self> {
     let instance = Instance::new(&InstanceDescriptor {
       backends: Backends::all(),
       ..Default::default()
@@ -430,7 +461,7 @@ impl Renderer {
       format: surface_format,
       width: size.width,
       height: size.height,
-      present_mode: PresentMode::AutoVsync,
+      present_mode: present_mode(settings.vsync),
       desired_maximum_frame_latency: 3,
       alpha_mode: capabilities.alpha_modes[0],
       view_formats: Vec::new(),
@@ -442,18 +473,34 @@ impl Renderer {
 
     let assets = ResourceReader::new()?;
 
-    let grass_image = assets.load_texture(Texture::Grass).await?;
-    let grass_rgba = grass_image.to_rgba8();
-    let (grass_width, grass_height) = grass_image.dimensions();
+    let block_texture_layers = mesh::texture_layers();
+    let mut block_texture_size = None;
+    let mut block_texture_rgba = Vec::new();
+    for name in &block_texture_layers {
+      let image = assets.load_texture(Texture::Block(name)).await?;
+      let dimensions = image.dimensions();
+
+      if *block_texture_size.get_or_insert(dimensions) != dimensions {
+        bail!(
+          "block texture {} has dimensions {:?}, expected {:?}",
+          name,
+          dimensions,
+          block_texture_size.unwrap()
+        );
+      }
 
-    let grass_texture = device.create_texture_with_data(
+      block_texture_rgba.extend_from_slice(&image.to_rgba8());
+    }
+    let (block_texture_width, block_texture_height) = block_texture_size.unwrap();
+
+    let block_texture = device.create_texture_with_data(
       &queue,
       &TextureDescriptor {
-        label: Some("Grass Texture"),
+        label: Some("Block Texture Array"),
         size: Extent3d {
-          width: grass_width,
-          height: grass_height,
-          depth_or_array_layers: 1,
+          width: block_texture_width,
+          height: block_texture_height,
+          depth_or_array_layers: block_texture_layers.len().coerce(),
         },
         mip_level_count: 1,
         sample_count: 1,
@@ -463,38 +510,42 @@ impl Renderer {
         view_formats: &[],
       },
       TextureDataOrder::default(),
-      &grass_rgba,
+      &block_texture_rgba,
     );
 
-    let grass_texture_view = grass_texture.create_view(&TextureViewDescriptor::default());
-    let grass_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-      label: Some("Grass Bind Group Layout"),
-      entries: &[
-        BindGroupLayoutEntry {
-          binding: 0,
-          visibility: ShaderStages::FRAGMENT,
-          ty: BindingType::Texture {
-            sample_type: TextureSampleType::Float { filterable: true },
-            view_dimension: TextureViewDimension::D2,
-            multisampled: false,
-          },
-          count: None,
-        },
-        BindGroupLayoutEntry {
-          binding: 1,
-          visibility: ShaderStages::FRAGMENT,
-          ty: BindingType::Sampler(SamplerBindingType::Filtering),
-          count: None,
-        },
-      ],
+    let block_texture_view = block_texture.create_view(&TextureViewDescriptor {
+      dimension: Some(TextureViewDimension::D2Array),
+      ..Default::default()
     });
-    let grass_bind_group = device.create_bind_group(&BindGroupDescriptor {
-      label: Some("Grass Bind Group"),
-      layout: &grass_bind_group_layout,
+    let block_texture_bind_group_layout =
+      device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("Block Texture Bind Group Layout"),
+        entries: &[
+          BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+              sample_type: TextureSampleType::Float { filterable: true },
+              view_dimension: TextureViewDimension::D2Array,
+              multisampled: false,
+            },
+            count: None,
+          },
+          BindGroupLayoutEntry {
+            binding: 1,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Sampler(SamplerBindingType::Filtering),
+            count: None,
+          },
+        ],
+      });
+    let block_texture_bind_group = device.create_bind_group(&BindGroupDescriptor {
+      label: Some("Block Texture Bind Group"),
+      layout: &block_texture_bind_group_layout,
       entries: &[
         BindGroupEntry {
           binding: 0,
-          resource: BindingResource::TextureView(&grass_texture_view),
+          resource: BindingResource::TextureView(&block_texture_view),
         },
         BindGroupEntry {
           binding: 1,
@@ -504,37 +555,61 @@ impl Renderer {
     });
 
     let transform_buffer = device.create_buffer(&BufferDescriptor {
-      label: Some("Model -> Clip Space Transform Buffer"),
-      size: (mem::size_of::<Mat4x4>() * BLOCK_LIMIT).coerce(),
+      label: Some("World -> Clip Space Transform Buffer"),
+      size: mem::size_of::<Mat4x4>().coerce(),
+      usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+      mapped_at_creation: false,
+    });
+    let environment_buffer = device.create_buffer(&BufferDescriptor {
+      label: Some("Block Environment Buffer"),
+      size: mem::size_of::<BlockEnvironment>().coerce(),
       usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
       mapped_at_creation: false,
     });
     let transform_buffer_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
       label: Some("Transform Buffer Bind Group Layout"),
-      entries: &[BindGroupLayoutEntry {
-        binding: 0,
-        visibility: ShaderStages::VERTEX,
-        ty: BindingType::Buffer {
-          ty: BufferBindingType::Uniform,
-          has_dynamic_offset: false,
-          min_binding_size: None,
+      entries: &[
+        BindGroupLayoutEntry {
+          binding: 0,
+          visibility: ShaderStages::VERTEX,
+          ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+          },
+          count: None,
         },
-        count: None,
-      }],
+        BindGroupLayoutEntry {
+          binding: 1,
+          visibility: ShaderStages::VERTEX_FRAGMENT,
+          ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+          },
+          count: None,
+        },
+      ],
     });
     let transform_bind_group = device.create_bind_group(&BindGroupDescriptor {
       label: Some("Transform Buffer Bind Group"),
       layout: &transform_buffer_layout,
-      entries: &[BindGroupEntry {
-        binding: 0,
-        resource: transform_buffer.as_entire_binding(),
-      }],
+      entries: &[
+        BindGroupEntry {
+          binding: 0,
+          resource: transform_buffer.as_entire_binding(),
+        },
+        BindGroupEntry {
+          binding: 1,
+          resource: environment_buffer.as_entire_binding(),
+        },
+      ],
     });
 
     let shader = device.create_shader_module(include_wgsl!("shaders/cube.wgsl"));
     let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
       label: Some("Render Pipeline Layout"),
-      bind_group_layouts: &[&transform_buffer_layout, &grass_bind_group_layout],
+      bind_group_layouts: &[&transform_buffer_layout, &block_texture_bind_group_layout],
       immediate_size: 0,
     });
     let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
@@ -544,11 +619,25 @@ impl Renderer {
         module: &shader,
         entry_point: Some("vs_main"),
         compilation_options: PipelineCompilationOptions::default(),
-        buffers: &[VertexBufferLayout {
-          array_stride: mem::size_of::<Vertex>().coerce(),
-          step_mode: VertexStepMode::Vertex,
-          attributes: &vertex_attr_array![0 => Float32x3, 1 => Float32x2],
-        }],
+        buffers: &[
+          VertexBufferLayout {
+            array_stride: mem::size_of::<BlockVertex>().coerce(),
+            step_mode: VertexStepMode::Vertex,
+            attributes: &vertex_attr_array![
+              0 => Float32x3,
+              1 => Float32x2,
+              2 => Float32x2,
+              3 => Uint32,
+              4 => Float32x2,
+              5 => Float32,
+            ],
+          },
+          VertexBufferLayout {
+            array_stride: mem::size_of::<Vec3>().coerce(),
+            step_mode: VertexStepMode::Instance,
+            attributes: &vertex_attr_array![6 => Float32x3],
+          },
+        ],
       },
       fragment: Some(FragmentState {
         module: &shader,
@@ -586,7 +675,7 @@ impl Renderer {
     });
 
     let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-      label: Some("Vertex Buffer"),
+      label: Some("Cube Vertex Buffer"),
       contents: VERTICES.as_bytes(),
       usage: BufferUsages::VERTEX,
     });
@@ -680,10 +769,16 @@ impl Renderer {
       usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
       mapped_at_creation: false,
     });
-    let skybox_transform_buffer_layout =
-      device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-        label: Some("Skybox Transform Buffer Bind Group Layout"),
-        entries: &[BindGroupLayoutEntry {
+    let sky_buffer = device.create_buffer(&BufferDescriptor {
+      label: Some("Sky Buffer"),
+      size: mem::size_of::<Sky>().coerce(),
+      usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+      mapped_at_creation: false,
+    });
+    let skybox_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+      label: Some("Skybox Bind Group Layout"),
+      entries: &[
+        BindGroupLayoutEntry {
           binding: 0,
           visibility: ShaderStages::VERTEX,
           ty: BindingType::Buffer {
@@ -692,21 +787,38 @@ impl Renderer {
             min_binding_size: None,
           },
           count: None,
-        }],
-      });
-    let skybox_transform_bind_group = device.create_bind_group(&BindGroupDescriptor {
-      label: Some("Skybox Transform Buffer Bind Group"),
-      layout: &skybox_transform_buffer_layout,
-      entries: &[BindGroupEntry {
-        binding: 0,
-        resource: skybox_transform_buffer.as_entire_binding(),
-      }],
+        },
+        BindGroupLayoutEntry {
+          binding: 1,
+          visibility: ShaderStages::FRAGMENT,
+          ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+          },
+          count: None,
+        },
+      ],
+    });
+    let skybox_bind_group = device.create_bind_group(&BindGroupDescriptor {
+      label: Some("Skybox Bind Group"),
+      layout: &skybox_bind_group_layout,
+      entries: &[
+        BindGroupEntry {
+          binding: 0,
+          resource: skybox_transform_buffer.as_entire_binding(),
+        },
+        BindGroupEntry {
+          binding: 1,
+          resource: sky_buffer.as_entire_binding(),
+        },
+      ],
     });
 
     let skybox_shader = device.create_shader_module(include_wgsl!("shaders/skybox.wgsl"));
     let skybox_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
       label: Some("Skybox Render Pipeline Layout"),
-      bind_group_layouts: &[&skybox_transform_buffer_layout],
+      bind_group_layouts: &[&skybox_bind_group_layout],
       immediate_size: 0,
     });
     let skybox_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
@@ -955,6 +1067,60 @@ impl Renderer {
       cache: None,
     });
 
+    let hotbar_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+      label: Some("Hotbar Render Pipeline Layout"),
+      bind_group_layouts: &[&block_texture_bind_group_layout],
+      immediate_size: 0,
+    });
+    let hotbar_shader = device.create_shader_module(include_wgsl!("shaders/hotbar.wgsl"));
+    let hotbar_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+      label: Some("Hotbar Render Pipeline"),
+      layout: Some(&hotbar_layout),
+      vertex: VertexState {
+        module: &hotbar_shader,
+        entry_point: Some("vs_main"),
+        compilation_options: PipelineCompilationOptions::default(),
+        buffers: &[VertexBufferLayout {
+          array_stride: mem::size_of::<HotbarVertex>().coerce(),
+          step_mode: VertexStepMode::Vertex,
+          attributes: &vertex_attr_array![
+            0 => Float32x2,
+            1 => Float32x2,
+            2 => Float32x2,
+            3 => Sint32,
+            4 => Float32x4,
+          ],
+        }],
+      },
+      fragment: Some(FragmentState {
+        module: &hotbar_shader,
+        entry_point: Some("fs_main"),
+        compilation_options: PipelineCompilationOptions::default(),
+        targets: &[Some(ColorTargetState {
+          format: config.format,
+          blend: Some(BlendState::ALPHA_BLENDING),
+          write_mask: ColorWrites::ALL,
+        })],
+      }),
+      primitive: PrimitiveState {
+        topology: PrimitiveTopology::TriangleList,
+        strip_index_format: None,
+        front_face: FrontFace::Ccw,
+        cull_mode: None,
+        unclipped_depth: false,
+        polygon_mode: PolygonMode::Fill,
+        conservative: false,
+      },
+      depth_stencil: None,
+      multisample: MultisampleState {
+        count: 1,
+        mask: !0,
+        alpha_to_coverage_enabled: false,
+      },
+      multiview_mask: None,
+      cache: None,
+    });
+
     let (font_atlas, font_atlas_alpha) = FontAtlas::load(&assets, FONT_SCALE).await?;
     let (font_atlas_width, font_atlas_height) = font_atlas.dimensions();
 
@@ -1067,6 +1233,8 @@ impl Renderer {
       &config,
       &fullscreen_copy_texture_bind_group_layout,
       &default_sampler,
+      settings.field_of_view,
+      settings.render_distance,
     );
 
     Ok(Self {
@@ -1076,17 +1244,28 @@ impl Renderer {
       queue,
       config,
       default_sampler,
+      field_of_view: settings.field_of_view,
+      render_distance: settings.render_distance,
       screen,
       transform_buffer,
+      environment_buffer,
       transform_bind_group,
       pipeline,
       vertex_buffer,
-      grass_bind_group,
+      block_texture_layers,
+      block_texture_bind_group,
+      mesh_options: MeshOptions {
+        ambient_occlusion: settings.ambient_occlusion,
+        ..MeshOptions::default()
+      },
+      chunk_meshes: HashMap::new(),
+      stale_chunks: HashSet::new(),
       outline_transform_buffer,
       outline_transform_bind_group,
       outline_pipeline,
       skybox_transform_buffer,
-      skybox_transform_bind_group,
+      sky_buffer,
+      skybox_bind_group,
       skybox_pipeline,
       fullscreen_copy_texture_bind_group_layout,
       fullscreen_copy_pipeline,
@@ -1094,6 +1273,8 @@ impl Renderer {
       crosshair_quad_buffer,
       crosshair_bind_group,
       crosshair_pipeline,
+      hotbar_buffer: None,
+      hotbar_pipeline,
       text_buffer: None,
       text_bind_group,
       text_pipeline,
@@ -1125,6 +1306,8 @@ impl Renderer {
       &self.config,
       &self.fullscreen_copy_texture_bind_group_layout,
       &self.default_sampler,
+      self.field_of_view,
+      self.render_distance,
     );
 
     self.queue.write_buffer(
@@ -1138,6 +1321,28 @@ impl Renderer {
     );
   }
 
+  /// Applies the settings which affect rendering, if they've changed.
+  pub fn apply_settings(&mut self, settings: &Settings) {
+    if (settings.field_of_view != self.field_of_view)
+      || (settings.render_distance != self.render_distance)
+    {
+      self.field_of_view = settings.field_of_view;
+      self.render_distance = settings.render_distance;
+      self.screen.perspective = perspective(&self.config, self.field_of_view, self.render_distance);
+    }
+
+    let present_mode = present_mode(settings.vsync);
+    if present_mode != self.config.present_mode {
+      self.config.present_mode = present_mode;
+      self.surface.configure(&self.device, &self.config);
+    }
+
+    if settings.ambient_occlusion != self.mesh_options.ambient_occlusion {
+      self.mesh_options.ambient_occlusion = settings.ambient_occlusion;
+      self.stale_chunks.extend(self.chunk_meshes.keys());
+    }
+  }
+
   pub fn render(&mut self, scene: &Scene<'_>, view_direction: Direction) -> Result<()> {
     let output = self.surface.get_current_texture()?;
     let view = output
@@ -1154,15 +1359,45 @@ impl Renderer {
       0,
       skybox_transform.as_bytes(),
     );
+    let daylight = scene.time.daylight();
+    let day_horizon_colour =
+      Vec3::lerp(scene.sky_colour, Vec3::new(1.0, 1.0, 1.0), DAY_HORIZON_HAZE);
+    let horizon_colour = Vec3::lerp(NIGHT_HORIZON_COLOUR, day_horizon_colour, daylight);
+    let sky = Sky {
+      zenith_colour: Vec3::lerp(NIGHT_ZENITH_COLOUR, scene.sky_colour, daylight).into(),
+      daylight,
+      horizon_colour: horizon_colour.into(),
+      _padding0: 0.0,
+      sun_direction: scene.time.sun_direction().into(),
+      _padding1: 0.0,
+    };
+    self.queue.write_buffer(&self.sky_buffer, 0, sky.as_bytes());
 
-    let transforms: Vec<Mat4x4> = scene
-      .blocks
-      .iter()
-      .map(|block| &world_to_screen_space * &mat4::translate(*block))
-      .collect();
+    self.update_chunk_meshes(scene.world, &scene.dirty_chunks);
+
+    self
+      .queue
+      .write_buffer(&self.transform_buffer, 0, world_to_screen_space.as_bytes());
+    let fog_end = fog_distance(self.render_distance);
+    let environment = BlockEnvironment {
+      camera_position: scene.player_camera.position().into(),
+      ambient_light: scene.time.ambient_light(),
+      fog_colour: horizon_colour.into(),
+      fog_start: FOG_START * fog_end,
+      fog_end,
+      _padding: [0.0; 3],
+    };
     self
       .queue
-      .write_buffer(&self.transform_buffer, 0, transforms.as_bytes());
+      .write_buffer(&self.environment_buffer, 0, environment.as_bytes());
+
+    // Skip the chunks which are entirely off screen
+    let frustum = mat4::frustum(&world_to_screen_space);
+    let visible_meshes: Vec<&ChunkMesh> = self
+      .chunk_meshes
+      .values()
+      .filter(|mesh| frustum.may_contain(&mesh.bounds))
+      .collect();
 
     let mut encoder = self
       .device
@@ -1195,24 +1430,30 @@ impl Renderer {
       });
 
       render_pass.set_pipeline(&self.skybox_pipeline);
-      render_pass.set_bind_group(0, &self.skybox_transform_bind_group, &[]);
+      render_pass.set_bind_group(0, &self.skybox_bind_group, &[]);
       render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
       render_pass.draw(0..VERTICES.len().coerce(), 0..1);
 
       render_pass.set_pipeline(&self.pipeline);
       render_pass.set_bind_group(0, &self.transform_bind_group, &[]);
-      render_pass.set_bind_group(1, &self.grass_bind_group, &[]);
-      render_pass.draw(0..VERTICES.len().coerce(), 0..scene.blocks.len().coerce());
+      render_pass.set_bind_group(1, &self.block_texture_bind_group, &[]);
+
+      for mesh in &visible_meshes {
+        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, mesh.origin_buffer.slice(..));
+        render_pass.draw(0..mesh.vertex_count, 0..1);
+      }
 
-      if let Some(index) = scene.target_block_index {
+      if let Some(block) = scene.target_block {
         self.queue.write_buffer(
           &self.outline_transform_buffer,
           0,
-          transforms.get(index).unwrap().as_bytes(),
+          (&world_to_screen_space * &mat4::translate(block.center())).as_bytes(),
         );
 
         render_pass.set_pipeline(&self.outline_pipeline);
         render_pass.set_bind_group(0, &self.outline_transform_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.draw(0..VERTICES.len().coerce(), 0..1);
       }
     }
@@ -1242,31 +1483,87 @@ impl Renderer {
       render_pass.set_bind_group(1, &self.crosshair_bind_group, &[]);
       render_pass.draw(0..4, 0..1);
 
+      let screen_size = PhysicalSize::new(self.config.width, self.config.height);
+
+      let mut hotbar_vertices = Vec::new();
+      hotbar::push_hotbar_vertices(
+        scene.inventory,
+        &self.block_texture_layers,
+        screen_size,
+        &mut hotbar_vertices,
+      );
+      write_vertex_buffer(
+        &self.device,
+        &self.queue,
+        &mut self.hotbar_buffer,
+        "Hotbar Vertices",
+        hotbar_vertices.as_bytes(),
+      );
+
+      render_pass.set_pipeline(&self.hotbar_pipeline);
+      render_pass.set_bind_group(0, &self.block_texture_bind_group, &[]);
+      render_pass.set_vertex_buffer(0, self.hotbar_buffer.as_ref().unwrap().slice(..));
+      render_pass.draw(0..hotbar_vertices.len().coerce(), 0..1);
+
+      let mut text_vertices = Vec::new();
+      hotbar::push_count_vertices(
+        scene.inventory,
+        &self.font_atlas,
+        screen_size,
+        &mut text_vertices,
+      );
+
       if let Some(debug_display) = &scene.debug_display {
         let fps_text = format!(
           "FPS: {} ({:.3}ms)",
-          debug_display.frames_per_second, debug_display.mean_frame_time_ms
+          debug_display.frames_per_second.round(),
+          debug_display.mean_frame_time_ms
         );
 
-        let mut text_vertices = Vec::new();
         self.font_atlas.push_text_vertices(
           &fps_text,
           PhysicalSize::new(5, 5),
-          PhysicalSize::new(self.config.width, self.config.height),
+          screen_size,
+          &mut text_vertices,
+        );
+
+        let line_height: u32 = self.font_atlas.glyph_pixel_height().coerce_lossy();
+        self.font_atlas.push_text_vertices(
+          &format!("Time: {}", scene.time),
+          PhysicalSize::new(5, 5 + line_height),
+          screen_size,
           &mut text_vertices,
         );
 
-        if let Some(text_buffer) = &self.text_buffer {
-          if text_buffer.size() < core::slice_byte_len(&text_vertices).coerce() {
-            self.create_text_buffer(&text_vertices);
-          } else {
-            self
-              .queue
-              .write_buffer(text_buffer, 0, text_vertices.as_bytes());
-          }
-        } else {
-          self.create_text_buffer(&text_vertices);
+        self.font_atlas.push_text_vertices(
+          &format!(
+            "Chunks: {} of {} drawn",
+            visible_meshes.len(),
+            self.chunk_meshes.len()
+          ),
+          PhysicalSize::new(5, 5 + (2 * line_height)),
+          screen_size,
+          &mut text_vertices,
+        );
+
+        if let Some(cursor) = debug_display.cursor_position {
+          self.font_atlas.push_text_vertices(
+            &format!("Cursor: {} {}", cursor.x().round(), cursor.y().round()),
+            PhysicalSize::new(5, 5 + (3 * line_height)),
+            screen_size,
+            &mut text_vertices,
+          );
         }
+      }
+
+      if !text_vertices.is_empty() {
+        write_vertex_buffer(
+          &self.device,
+          &self.queue,
+          &mut self.text_buffer,
+          "Text Vertices",
+          text_vertices.as_bytes(),
+        );
 
         render_pass.set_pipeline(&self.text_pipeline);
         render_pass.set_bind_group(0, &self.text_bind_group, &[]);
@@ -1282,11 +1579,60 @@ impl Renderer {
     Ok(())
   }
 
-  fn create_text_buffer(&mut self, text_vertices: &[TextVertex]) {
-    self.text_buffer = Some(self.device.create_buffer_init(&BufferInitDescriptor {
-      label: Some("Text Vertices"),
-      contents: text_vertices.as_bytes(),
-      usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-    }));
+  /// Rebuilds the meshes of the chunks of `world` which changed since the last
+  /// frame, and of any stale chunks.
+  fn update_chunk_meshes(&mut self, world: &World, dirty_chunks: &HashSet<ChunkPos>) {
+    let mesher = Mesher::new(world, &self.block_texture_layers, &self.mesh_options);
+    let stale_chunks = mem::take(&mut self.stale_chunks);
+
+    for &pos in dirty_chunks.union(&stale_chunks) {
+      let vertices = mesher.mesh(pos);
+
+      if vertices.is_empty() {
+        self.chunk_meshes.remove(&pos);
+        continue;
+      }
+
+      self.chunk_meshes.insert(
+        pos,
+        ChunkMesh {
+          vertex_buffer: self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Chunk Vertex Buffer"),
+            contents: vertices.as_bytes(),
+            usage: BufferUsages::VERTEX,
+          }),
+          vertex_count: vertices.len().coerce(),
+          origin_buffer: self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Chunk Origin Buffer"),
+            contents: pos.origin().center().as_bytes(),
+            usage: BufferUsages::VERTEX,
+          }),
+          bounds: pos.bounding_box(),
+        },
+      );
+    }
+  }
+}
+
+/// Writes `contents` to the vertex buffer in `buffer`, replacing it with a new
+/// buffer if there isn't one yet or it's too small.
+fn write_vertex_buffer(
+  device: &Device,
+  queue: &Queue,
+  buffer: &mut Option<Buffer>,
+  label: &str,
+  contents: &[u8],
+) {
+  if let Some(buffer) = buffer
+    && buffer.size() >= contents.len().coerce()
+  {
+    queue.write_buffer(buffer, 0, contents);
+    return;
   }
+
+  *buffer = Some(device.create_buffer_init(&BufferInitDescriptor {
+    label: Some(label),
+    contents,
+    usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+  }));
 }
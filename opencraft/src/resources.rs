@@ -9,15 +9,16 @@ const MONOCRAFT_WOFF2_PATH: &str = "fonts/monocraft_v4.2.1.woff2";
 
 #[derive(Clone, Copy)]
 pub enum Texture {
-  Grass,
+  /// A block cube net, by name.
+  Block(&'static str),
   Crosshair,
 }
 
 impl Texture {
-  fn path(self) -> &'static str {
+  fn path(self) -> String {
     match self {
-      Texture::Grass => "textures/block/grass.png",
-      Texture::Crosshair => "textures/ui/crosshair.png",
+      Texture::Block(name) => format!("textures/block/{}.png", name),
+      Texture::Crosshair => "textures/ui/crosshair.png".to_owned(),
     }
   }
 }
@@ -31,7 +32,7 @@ impl ResourceReader {
   }
 
   pub async fn load_texture(&self, texture: Texture) -> Result<DynamicImage> {
-    self.decode_png(texture.path()).await
+    self.decode_png(&texture.path()).await
   }
 
   pub async fn load_font(&self) -> Result<Font<'static>> {
@@ -0,0 +1,498 @@
+//! The authoritative simulation of a world shared between players, run by the
+//! dedicated server.
+//!
+//! Nothing here depends on a window or GPU, so that servers can run headlessly
+//! on modest hardware.
+
+pub mod network;
+
+use crate::core::math::angle::Angle;
+use crate::core::math::vec3::Vec3;
+use crate::net::protocol::{DEFAULT_PORT, DEFAULT_WEBSOCKET_PORT, POSITION_INTERVAL, PlayerId};
+use crate::platform::{self, Instant, Storage, error, info, warn};
+use crate::server::network::Network;
+use crate::simulation::Simulation;
+use crate::world::World;
+use crate::world::block::Block;
+use crate::world::generation::{DEFAULT_SEED, GENERATION_RADIUS, TerrainGenerator, UNLOAD_MARGIN};
+use crate::world::player::Player;
+use crate::world::position::{BlockPos, ChunkPos};
+use crate::world::save::{AUTOSAVE_INTERVAL, WorldMetadata, WorldSave};
+use crate::world::time::{self, WorldTime};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Duration;
+use std::{env, io, thread};
+
+/// Time simulated by each tick, for 20 ticks per second.
+pub const TICK_DURATION: Duration = Duration::from_millis(50);
+/// Limits the number of chunks loaded or generated per tick, shared between all
+/// players.
+const CHUNKS_LOADED_PER_TICK: usize = 8;
+/// Furthest the server may fall behind before it skips ticks to catch up.
+const MAX_TICK_LAG: Duration = Duration::from_secs(1);
+
+/// A player in the world, moved by their client, whose body is kept by the
+/// [`Simulation`].
+#[derive(Debug, Clone)]
+pub struct ConnectedPlayer {
+  pub name: String,
+  pub yaw: Angle,
+  pub pitch: Angle,
+  /// The last position reported by the player's client which was accepted,
+  /// and the tick it arrived on.
+  reported_position: Vec3,
+  reported_at: u64,
+}
+
+pub struct Server {
+  save: WorldSave,
+  last_save: Instant,
+  /// The world's seed and where players join it, whose time is only brought up
+  /// to date when saving.
+  metadata: WorldMetadata,
+  simulation: Simulation,
+  players: BTreeMap<PlayerId, ConnectedPlayer>,
+  next_player_id: u32,
+  ticks: u64,
+}
+
+impl Server {
+  /// Loads the world saved in `storage`, or creates a new world there.
+  pub fn new(storage: Storage) -> Result<Self> {
+    let save = WorldSave::new(storage);
+    let metadata = match save.read_metadata()? {
+      Some(metadata) => metadata,
+      None => {
+        let spawn = TerrainGenerator::new(DEFAULT_SEED).spawn_position();
+
+        WorldMetadata {
+          seed: DEFAULT_SEED,
+          camera_position: spawn + Vec3::new(0.0, Player::EYE_HEIGHT, 0.0),
+          camera_yaw: Default::default(),
+          camera_pitch: Default::default(),
+          time: WorldTime::default(),
+        }
+      }
+    };
+    let terrain_generator = TerrainGenerator::new(metadata.seed);
+
+    Ok(Self {
+      save,
+      last_save: Instant::now(),
+      simulation: Simulation::new(
+        World::with_terrain(terrain_generator),
+        terrain_generator,
+        metadata.time,
+      ),
+      metadata,
+      players: BTreeMap::new(),
+      next_player_id: 0,
+      ticks: 0,
+    })
+  }
+
+  pub fn simulation(&self) -> &Simulation {
+    &self.simulation
+  }
+
+  pub fn world(&self) -> &World {
+    self.simulation.world()
+  }
+
+  /// Returns the number of ticks simulated since the server started.
+  pub fn ticks(&self) -> u64 {
+    self.ticks
+  }
+
+  pub fn seed(&self) -> u64 {
+    self.metadata.seed
+  }
+
+  pub fn time(&self) -> WorldTime {
+    self.simulation.time()
+  }
+
+  /// Changes the time of day, staying on the current day.
+  pub fn set_time_of_day(&mut self, time_of_day: f32) {
+    let mut time = self.simulation.time();
+    time.set_time_of_day(time_of_day);
+    self.simulation.set_time(time);
+  }
+
+  /// Returns where joining players appear, which is wherever the world's
+  /// camera was when it was last saved.
+  pub fn spawn_position(&self) -> Vec3 {
+    self.metadata.camera_position - Vec3::new(0.0, Player::EYE_HEIGHT, 0.0)
+  }
+
+  pub fn join(&mut self, name: &str) -> PlayerId {
+    let id = PlayerId(self.next_player_id);
+    self.next_player_id += 1;
+
+    self.players.insert(
+      id,
+      ConnectedPlayer {
+        name: name.to_owned(),
+        yaw: self.metadata.camera_yaw,
+        pitch: self.metadata.camera_pitch,
+        reported_position: self.spawn_position(),
+        reported_at: self.ticks,
+      },
+    );
+    self.simulation.add_player(id, self.spawn_position());
+    info!("{} joined as player {}", name, id.0);
+
+    id
+  }
+
+  pub fn leave(&mut self, id: PlayerId) {
+    if let Some(connected) = self.players.remove(&id) {
+      info!("{} left", connected.name);
+    }
+    self.simulation.remove_player(id);
+  }
+
+  pub fn player(&self, id: PlayerId) -> Option<&ConnectedPlayer> {
+    self.players.get(&id)
+  }
+
+  pub fn players(&self) -> impl Iterator<Item = (PlayerId, &ConnectedPlayer)> {
+    self.players.iter().map(|(&id, connected)| (id, connected))
+  }
+
+  /// Moves a player to where their client reports them to be, unless they
+  /// couldn't have got there since their last accepted report. Returns whether
+  /// they were moved.
+  pub fn move_player(&mut self, id: PlayerId, position: Vec3, yaw: Angle, pitch: Angle) -> bool {
+    let (Some(connected), Some(player)) =
+      (self.players.get_mut(&id), self.simulation.player_mut(id))
+    else {
+      return false;
+    };
+    connected.yaw = yaw;
+    connected.pitch = pitch;
+
+    // Reports can be held up and then arrive together, so allow for the time
+    // between them as well
+    let ticks = u32::try_from(self.ticks - connected.reported_at).unwrap_or(u32::MAX);
+    let elapsed = (TICK_DURATION.saturating_mul(ticks) + POSITION_INTERVAL).as_secs_f32();
+    let offset = position - connected.reported_position;
+    let horizontal = Vec3::new(offset.x(), 0.0, offset.z()).len();
+    if (horizontal > Player::MAX_SPEED * elapsed)
+      || (offset.y() > Player::MAX_SPEED * elapsed)
+      || (-offset.y() > Player::MAX_FALL_SPEED * elapsed)
+    {
+      return false;
+    }
+
+    player.body.teleport(position);
+    connected.reported_position = position;
+    connected.reported_at = self.ticks;
+    true
+  }
+
+  /// Makes a block change which the player `id` asked for, unless the
+  /// simulation's rules forbid it. Returns whether the block was changed.
+  pub fn set_block(&mut self, id: PlayerId, pos: BlockPos, block: Block) -> bool {
+    if !self.simulation.can_change_block(id, pos, block) {
+      return false;
+    }
+
+    self.simulation.change_block(id, pos, block);
+    true
+  }
+
+  /// Advances the world by [`TICK_DURATION`].
+  pub fn tick(&mut self) {
+    // Players keep falling between reports from their clients, so that they
+    // don't hang in the air if their client stops responding
+    self
+      .simulation
+      .tick(TICK_DURATION, |_| (Vec3::default(), false));
+
+    // Load around the spawn too, so that joining players have ground to
+    // land on, and keep it loaded when nobody is playing
+    let centers: Vec<ChunkPos> = [self.spawn_position()]
+      .into_iter()
+      .chain(
+        self
+          .simulation
+          .players()
+          .map(|(_, player)| player.body.position()),
+      )
+      .map(|position| BlockPos::containing(position).chunk())
+      .collect();
+    if let Err(err) = self.simulation.load_around(
+      &mut self.save,
+      &centers,
+      GENERATION_RADIUS,
+      CHUNKS_LOADED_PER_TICK,
+    ) {
+      error!("Could not load the world: {:?}", err);
+    }
+    if let Err(err) = self.simulation.unload_distant(
+      Some(&mut self.save),
+      &centers,
+      GENERATION_RADIUS,
+      UNLOAD_MARGIN,
+    ) {
+      error!("Could not unload the world: {:?}", err);
+    }
+
+    if self.last_save.elapsed() >= AUTOSAVE_INTERVAL {
+      if let Err(err) = self.save() {
+        error!("Could not autosave the world: {:?}", err);
+      }
+      self.last_save = Instant::now();
+    }
+
+    self.ticks += 1;
+  }
+
+  pub fn save(&mut self) -> Result<()> {
+    self.metadata.time = self.simulation.time();
+    self.save.write(&self.metadata, self.simulation.world_mut())
+  }
+}
+
+/// Runs a dedicated server in the `saves` folder next to the executable, until
+/// `stop` is entered on standard input.
+///
+/// The server listens on the address given as the first command line argument,
+/// or on every interface at [`DEFAULT_PORT`]. Browsers connect over WebSockets
+/// at the second argument, or at [`DEFAULT_WEBSOCKET_PORT`].
+pub fn start() -> Result<()> {
+  platform::init_logging();
+
+  let mut server = Server::new(Storage::new()?)?;
+  let address = env::args()
+    .nth(1)
+    .unwrap_or_else(|| format!("0.0.0.0:{DEFAULT_PORT}"));
+  let websocket_address = env::args()
+    .nth(2)
+    .unwrap_or_else(|| format!("0.0.0.0:{DEFAULT_WEBSOCKET_PORT}"));
+  let mut network = Network::bind(&address)?;
+  network.bind_websocket(&websocket_address)?;
+  info!(
+    "Server started on {} (WebSockets on {}) with seed {}, {} ticks per second",
+    network.local_address()?,
+    websocket_address,
+    server.seed(),
+    1.0 / TICK_DURATION.as_secs_f32()
+  );
+
+  let commands = read_commands();
+  let mut next_tick = Instant::now();
+  loop {
+    match commands.try_recv() {
+      Ok(command) => match command.trim() {
+        "stop" => break,
+        "save" => match server.save() {
+          Ok(()) => info!("Saved the world"),
+          Err(err) => error!("Could not save the world: {:?}", err),
+        },
+        "" => {}
+        command => match command.split_whitespace().collect::<Vec<_>>()[..] {
+          ["time", time_of_day] => match time::parse_time_of_day(time_of_day) {
+            Ok(time_of_day) => {
+              server.set_time_of_day(time_of_day);
+              network.send_time(&server);
+              info!("Set the time to {}", server.time());
+            }
+            Err(err) => warn!("Could not set the time: {:#}", err),
+          },
+          _ => warn!(
+            "Unknown command {:?}, expected \"save\", \"stop\" or \"time <HH:MM>\"",
+            command
+          ),
+        },
+      },
+      // Without a console, the server runs until it is killed
+      Err(TryRecvError::Empty | TryRecvError::Disconnected) => {}
+    }
+
+    network.update(&mut server);
+    server.tick();
+
+    next_tick += TICK_DURATION;
+    let now = Instant::now();
+    if now > next_tick + MAX_TICK_LAG {
+      warn!(
+        "Server is overloaded, skipping {:?} of ticks",
+        now - next_tick
+      );
+      next_tick = now;
+    }
+    thread::sleep(next_tick.saturating_duration_since(now));
+  }
+
+  info!("Stopping server");
+  network.disconnect_all(&mut server, "server stopped");
+  server.save()
+}
+
+/// Reads lines from standard input on a separate thread, so that the server
+/// can check for commands without blocking.
+fn read_commands() -> Receiver<String> {
+  let (sender, receiver) = mpsc::channel();
+
+  thread::spawn(move || {
+    for line in io::stdin().lock().lines() {
+      let Ok(line) = line else {
+        break;
+      };
+
+      if sender.send(line).is_err() {
+        break;
+      }
+    }
+  });
+
+  receiver
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::path::PathBuf;
+  use std::{env, fs};
+
+  fn temp_dir(name: &str) -> PathBuf {
+    env::temp_dir().join(format!(
+      "opencraft-server-test-{}-{}",
+      name,
+      std::process::id()
+    ))
+  }
+
+  fn tick(server: &mut Server, count: u32) {
+    for _ in 0..count {
+      server.tick();
+    }
+  }
+
+  // Check that a joining player falls onto the terrain generated beneath them.
+  #[test]
+  fn test_player_lands_on_generated_terrain() {
+    let path = temp_dir("land");
+    let mut server = Server::new(Storage::at(&path)).unwrap();
+    let id = server.join("test");
+
+    tick(&mut server, 40);
+    fs::remove_dir_all(&path).ok();
+
+    let player = &server.simulation().player(id).unwrap().body;
+    assert!(player.is_on_ground());
+    assert!(player.position().y() <= server.spawn_position().y() + 1e-3);
+    assert_eq!(40, server.ticks());
+  }
+
+  // Check that players can only place blocks they carry, within reach and
+  // never inside a walking player.
+  #[test]
+  fn test_set_block_checked() {
+    let path = temp_dir("place");
+    let mut server = Server::new(Storage::at(&path)).unwrap();
+    let id = server.join("test");
+    tick(&mut server, 40);
+    let feet = BlockPos::containing(server.simulation().player(id).unwrap().body.position());
+
+    assert!(!server.set_block(id, feet, Block::Stone));
+    server
+      .simulation
+      .player_mut(id)
+      .unwrap()
+      .inventory
+      .add(Block::Stone);
+    assert!(!server.set_block(id, feet, Block::Stone));
+    assert!(!server.set_block(id, feet.offset(0, 8, 0), Block::Stone));
+
+    server.simulation.player_mut(id).unwrap().body.toggle_mode();
+    assert!(server.set_block(id, feet, Block::Stone));
+    assert_eq!(Block::Stone, server.world().get(feet));
+    assert!(!server.set_block(id, feet.offset(0, 1, 0), Block::Stone));
+    fs::remove_dir_all(&path).ok();
+  }
+
+  // Check that players can't move further than they could have since their
+  // last accepted move.
+  #[test]
+  fn test_move_player_limited_by_speed() {
+    let path = temp_dir("move");
+    let mut server = Server::new(Storage::at(&path)).unwrap();
+    let id = server.join("test");
+    let (spawn, ahead) = (server.spawn_position(), Vec3::new(20.0, 0.0, 0.0));
+
+    assert!(!server.move_player(id, spawn + ahead, Angle::default(), Angle::default()));
+    assert_eq!(
+      spawn,
+      server.simulation().player(id).unwrap().body.position()
+    );
+
+    tick(&mut server, 40);
+    assert!(server.move_player(id, spawn + ahead, Angle::default(), Angle::default()));
+    assert_eq!(
+      spawn + ahead,
+      server.simulation().player(id).unwrap().body.position()
+    );
+    assert!(!server.move_player(id, spawn + ahead * 2.0, Angle::default(), Angle::default()));
+    fs::remove_dir_all(&path).ok();
+  }
+
+  // Check that chunks far from every player are unloaded, and keep their
+  // changes once they're loaded again.
+  #[test]
+  fn test_distant_chunks_unloaded() {
+    let path = temp_dir("unload");
+    let mut server = Server::new(Storage::at(&path)).unwrap();
+    let id = server.join("test");
+    let (spawn, away) = (server.spawn_position(), Vec3::new(300.0, 0.0, 0.0));
+    let pos = BlockPos::containing(away).offset(0, 3, 0);
+    let teleport = |server: &mut Server, position| {
+      server
+        .simulation
+        .player_mut(id)
+        .unwrap()
+        .body
+        .teleport(position);
+      tick(server, 1);
+    };
+
+    // Finish loading around the spawn, which is loaded first
+    tick(&mut server, 60);
+    teleport(&mut server, away);
+    server.simulation.world_mut().set(pos, Block::Glass);
+    teleport(&mut server, spawn);
+    let unloaded = server.world().chunk(pos.chunk()).is_none();
+    teleport(&mut server, away);
+    fs::remove_dir_all(&path).ok();
+
+    assert!(unloaded);
+    assert_eq!(Block::Glass, server.world().get(pos));
+  }
+
+  // Check that changes to the world survive a restart, once their chunks are
+  // loaded again.
+  #[test]
+  fn test_restart_keeps_changes() {
+    let path = temp_dir("restart");
+    let mut server = Server::new(Storage::at(&path)).unwrap();
+    let id = server.join("test");
+    tick(&mut server, 40);
+    let feet = server.simulation().player(id).unwrap().body.position();
+    let pos = BlockPos::containing(feet).offset(0, -1, 0);
+    assert!(server.set_block(id, pos, Block::Air));
+    server.save().unwrap();
+
+    let mut restarted = Server::new(Storage::at(&path)).unwrap();
+    assert_eq!(0, restarted.world().chunk_count());
+    tick(&mut restarted, 10);
+    fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!(Block::Air, restarted.world().get(pos));
+    assert_eq!(server.spawn_position(), restarted.spawn_position());
+  }
+}
@@ -0,0 +1,517 @@
+use crate::net::connection::Connection;
+use crate::net::protocol::{ClientMessage, PROTOCOL_VERSION, PlayerId, ServerMessage};
+use crate::platform::{Socket, info, warn};
+use crate::server::Server;
+use crate::world::generation::{self, GENERATION_HEIGHT, GENERATION_RADIUS, UNLOAD_MARGIN};
+use crate::world::position::{BlockPos, ChunkPos};
+use anyhow::{Result, bail};
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::iter;
+use std::net::{SocketAddr, TcpListener};
+
+/// Limits the number of chunks sent to each client per update, so that block
+/// changes aren't stuck behind a long queue of chunks.
+const CHUNKS_SENT_PER_UPDATE: usize = 16;
+/// Number of ticks between sending clients the world's time: once a second.
+const TIME_UPDATE_TICKS: u64 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClientState {
+  /// Waiting for the client's handshake.
+  Connected,
+  /// Waiting for the client to log in.
+  Handshaken,
+  Playing(PlayerId),
+}
+
+struct Client {
+  connection: Connection,
+  address: SocketAddr,
+  state: ClientState,
+  /// Chunks which the client has been sent, and so must be kept up to date.
+  sent_chunks: HashSet<ChunkPos>,
+}
+
+/// Accepts clients over TCP, or WebSockets for browsers, and keeps them in
+/// sync with a [`Server`].
+pub struct Network {
+  listener: TcpListener,
+  websocket_listener: Option<TcpListener>,
+  clients: Vec<Client>,
+}
+
+impl Network {
+  pub fn bind(address: &str) -> Result<Self> {
+    let listener = TcpListener::bind(address)?;
+    listener.set_nonblocking(true)?;
+
+    Ok(Self {
+      listener,
+      websocket_listener: None,
+      clients: Vec::new(),
+    })
+  }
+
+  /// Also accepts clients connecting over WebSockets at `address`.
+  pub fn bind_websocket(&mut self, address: &str) -> Result<()> {
+    let listener = TcpListener::bind(address)?;
+    listener.set_nonblocking(true)?;
+
+    self.websocket_listener = Some(listener);
+    Ok(())
+  }
+
+  pub fn local_address(&self) -> Result<SocketAddr> {
+    Ok(self.listener.local_addr()?)
+  }
+
+  pub fn websocket_address(&self) -> Option<Result<SocketAddr>> {
+    self
+      .websocket_listener
+      .as_ref()
+      .map(|listener| Ok(listener.local_addr()?))
+  }
+
+  /// Accepts new clients, handles every message received, and sends clients
+  /// the chunks and players around them.
+  pub fn update(&mut self, server: &mut Server) {
+    self.accept();
+
+    let mut index = 0;
+    while index < self.clients.len() {
+      match self.update_client(index, server) {
+        Ok(()) => index += 1,
+        Err(err) => {
+          let client = self.clients.swap_remove(index);
+          info!("Disconnecting {}: {:#}", client.address, err);
+          self.remove_player(&client, server);
+        }
+      }
+    }
+
+    self.send_player_positions(server);
+    if server.ticks().is_multiple_of(TIME_UPDATE_TICKS) {
+      self.send_time(server);
+    }
+  }
+
+  /// Sends every client the world's time, so that their clocks don't drift
+  /// and they see changes to the time of day.
+  pub fn send_time(&mut self, server: &Server) {
+    self.broadcast(&ServerMessage::Time {
+      time: server.time(),
+    });
+  }
+
+  /// Tells every client why they are being disconnected, then disconnects
+  /// them.
+  pub fn disconnect_all(&mut self, server: &mut Server, reason: &str) {
+    for mut client in self.clients.drain(..) {
+      let _ = client.connection.send(&ServerMessage::Disconnect {
+        reason: reason.to_owned(),
+      });
+
+      if let ClientState::Playing(player) = client.state {
+        server.leave(player);
+      }
+    }
+  }
+
+  fn accept(&mut self) {
+    let listeners = iter::once((&self.listener, Socket::from_stream as fn(_) -> _)).chain(
+      self
+        .websocket_listener
+        .iter()
+        .map(|listener| (listener, Socket::accept_websocket as fn(_) -> _)),
+    );
+
+    for (listener, new_socket) in listeners {
+      loop {
+        match listener.accept() {
+          Ok((stream, address)) => match new_socket(stream) {
+            Ok(socket) => {
+              info!("Accepted connection from {}", address);
+              self.clients.push(Client {
+                connection: Connection::new(socket),
+                address,
+                state: ClientState::Connected,
+                sent_chunks: HashSet::new(),
+              });
+            }
+            Err(err) => warn!("Could not set up connection from {}: {:?}", address, err),
+          },
+          Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+          Err(err) => {
+            warn!("Could not accept a connection: {:?}", err);
+            break;
+          }
+        }
+      }
+    }
+  }
+
+  fn update_client(&mut self, index: usize, server: &mut Server) -> Result<()> {
+    while let Some(message) = self.clients[index].connection.receive()? {
+      self.handle(index, message, server)?;
+    }
+
+    self.send_chunks(index, server)?;
+    self.clients[index].connection.flush()
+  }
+
+  fn handle(&mut self, index: usize, message: ClientMessage, server: &mut Server) -> Result<()> {
+    let client = &mut self.clients[index];
+
+    match (client.state, message) {
+      (ClientState::Connected, ClientMessage::Handshake { protocol_version }) => {
+        if protocol_version != PROTOCOL_VERSION {
+          client.connection.send(&ServerMessage::Disconnect {
+            reason: format!(
+              "unsupported protocol version {protocol_version}, the server uses {PROTOCOL_VERSION}"
+            ),
+          })?;
+          bail!("unsupported protocol version {protocol_version}");
+        }
+
+        client.state = ClientState::Handshaken;
+        client.connection.send(&ServerMessage::Handshake {
+          protocol_version: PROTOCOL_VERSION,
+        })?;
+      }
+      (ClientState::Handshaken, ClientMessage::Login { name }) => {
+        let player = server.join(&name);
+        let connected = server.player(player).unwrap();
+
+        client.state = ClientState::Playing(player);
+        client.connection.send(&ServerMessage::LoginSuccess {
+          player,
+          seed: server.seed(),
+          time: server.time(),
+          position: server.simulation().player(player).unwrap().body.position(),
+          yaw: connected.yaw,
+          pitch: connected.pitch,
+        })?;
+      }
+      (ClientState::Playing(player), ClientMessage::SetBlock { pos, block }) => {
+        if server.set_block(player, pos, block) {
+          self.broadcast_block(pos, server);
+        } else {
+          // Tell the client the block is unchanged, so it stops waiting
+          client.connection.send(&ServerMessage::BlockChange {
+            pos,
+            block: server.world().get(pos),
+          })?;
+          info!("Rejected block change from player {}", player.0);
+        }
+      }
+      (
+        ClientState::Playing(player),
+        ClientMessage::PlayerPosition {
+          position,
+          yaw,
+          pitch,
+        },
+      ) => {
+        if !server.move_player(player, position, yaw, pitch) {
+          // Put the client's player back where the server believes they are
+          client.connection.send(&ServerMessage::PlayerPosition {
+            player,
+            position: server.simulation().player(player).unwrap().body.position(),
+            yaw,
+            pitch,
+          })?;
+          info!("Rejected movement from player {}", player.0);
+        }
+      }
+      (ClientState::Playing(player), ClientMessage::Chat { text }) => {
+        let sender = server.player(player).unwrap().name.clone();
+        info!("<{}> {}", sender, text);
+
+        self.broadcast(&ServerMessage::Chat { sender, text });
+      }
+      (_, ClientMessage::Disconnect { reason }) => bail!("client disconnected: {reason}"),
+      (state, message) => bail!("unexpected message {:?} while {:?}", message, state),
+    }
+
+    Ok(())
+  }
+
+  /// Sends the client the nearest chunks generated around their player which
+  /// they haven't been sent yet.
+  fn send_chunks(&mut self, index: usize, server: &Server) -> Result<()> {
+    let client = &mut self.clients[index];
+    let ClientState::Playing(player) = client.state else {
+      return Ok(());
+    };
+    let position = server.simulation().player(player).unwrap().body.position();
+    let center = BlockPos::containing(position).chunk();
+    // Clients unload distant chunks, so they're sent again once they're needed
+    client
+      .sent_chunks
+      .retain(|&pos| !generation::is_distant(center, pos, GENERATION_RADIUS, UNLOAD_MARGIN));
+
+    let mut unsent = Vec::new();
+    for x in -GENERATION_RADIUS..=GENERATION_RADIUS {
+      for y in -GENERATION_HEIGHT..=GENERATION_HEIGHT {
+        for z in -GENERATION_RADIUS..=GENERATION_RADIUS {
+          let pos = center.offset(x, y, z);
+
+          if !client.sent_chunks.contains(&pos) && server.world().chunk(pos).is_some() {
+            unsent.push((x * x + y * y + z * z, pos));
+          }
+        }
+      }
+    }
+    unsent.sort_unstable_by_key(|&(dist_sq, _)| dist_sq);
+
+    for (_, pos) in unsent.into_iter().take(CHUNKS_SENT_PER_UPDATE) {
+      let chunk = server.world().chunk(pos).unwrap();
+      client.connection.send(&ServerMessage::ChunkData {
+        pos,
+        chunk: Box::new(chunk.clone()),
+      })?;
+      client.sent_chunks.insert(pos);
+    }
+
+    Ok(())
+  }
+
+  /// Sends a changed block to every client which has its chunk.
+  fn broadcast_block(&mut self, pos: BlockPos, server: &Server) {
+    let message = ServerMessage::BlockChange {
+      pos,
+      block: server.world().get(pos),
+    };
+
+    for client in &mut self.clients {
+      if client.sent_chunks.contains(&pos.chunk()) {
+        // Failures are noticed on the client's next update
+        let _ = client.connection.send(&message);
+      }
+    }
+  }
+
+  fn broadcast(&mut self, message: &ServerMessage) {
+    for client in &mut self.clients {
+      if matches!(client.state, ClientState::Playing(_)) {
+        let _ = client.connection.send(message);
+      }
+    }
+  }
+
+  /// Sends every client where the other players are.
+  fn send_player_positions(&mut self, server: &Server) {
+    for (player, connected) in server.players() {
+      let message = ServerMessage::PlayerPosition {
+        player,
+        position: server.simulation().player(player).unwrap().body.position(),
+        yaw: connected.yaw,
+        pitch: connected.pitch,
+      };
+
+      for client in &mut self.clients {
+        if let ClientState::Playing(own_player) = client.state
+          && own_player != player
+        {
+          let _ = client.connection.send(&message);
+        }
+      }
+    }
+  }
+
+  fn remove_player(&mut self, client: &Client, server: &mut Server) {
+    if let ClientState::Playing(player) = client.state {
+      server.leave(player);
+      self.broadcast(&ServerMessage::PlayerLeft { player });
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::net::client::ServerConnection;
+  use crate::platform::Storage;
+  use crate::world::block::Block;
+  use std::path::PathBuf;
+  use std::time::Duration;
+  use std::{env, fs, thread};
+
+  /// A server on a loopback port, with a new world in a temporary folder.
+  fn start(name: &str) -> (Server, Network, PathBuf) {
+    let path = env::temp_dir().join(format!(
+      "opencraft-network-test-{}-{}",
+      name,
+      std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&path);
+
+    let mut network = Network::bind("127.0.0.1:0").unwrap();
+    network.bind_websocket("127.0.0.1:0").unwrap();
+
+    (Server::new(Storage::at(&path)).unwrap(), network, path)
+  }
+
+  /// Runs the server until a message satisfying `predicate` reaches `client`,
+  /// and returns it.
+  fn run_until(
+    server: &mut Server,
+    network: &mut Network,
+    client: &mut ServerConnection,
+    predicate: impl Fn(&ServerMessage) -> bool,
+  ) -> Result<ServerMessage> {
+    for _ in 0..500 {
+      network.update(server);
+      server.tick();
+
+      while let Some(message) = client.receive()? {
+        if predicate(&message) {
+          return Ok(message);
+        }
+      }
+
+      thread::sleep(Duration::from_millis(2));
+    }
+
+    panic!("message never arrived");
+  }
+
+  // Check that a client logs in and is sent the chunks around them.
+  #[test]
+  fn test_login_receives_chunks() {
+    let (mut server, mut network, path) = start("login");
+    let address = network.local_address().unwrap().to_string();
+    let mut client = ServerConnection::connect(&address, "Alex").unwrap();
+
+    let login = run_until(&mut server, &mut network, &mut client, |message| {
+      matches!(message, ServerMessage::LoginSuccess { .. })
+    })
+    .unwrap();
+    let chunk = run_until(&mut server, &mut network, &mut client, |message| {
+      matches!(message, ServerMessage::ChunkData { .. })
+    })
+    .unwrap();
+    fs::remove_dir_all(&path).ok();
+
+    let ServerMessage::LoginSuccess { player, seed, .. } = login else {
+      unreachable!();
+    };
+    assert_eq!(server.seed(), seed);
+    assert_eq!("Alex", server.player(player).unwrap().name);
+    let ServerMessage::ChunkData { pos, chunk } = chunk else {
+      unreachable!();
+    };
+    assert!(*chunk == *server.world().chunk(pos).unwrap());
+  }
+
+  // Check that a client can log in over a WebSocket, as browsers do.
+  #[test]
+  fn test_websocket_login() {
+    let (mut server, mut network, path) = start("websocket");
+    let address = format!("ws://{}", network.websocket_address().unwrap().unwrap());
+    let mut client = ServerConnection::connect(&address, "Browser").unwrap();
+
+    let chunk = run_until(&mut server, &mut network, &mut client, |message| {
+      matches!(message, ServerMessage::ChunkData { .. })
+    })
+    .unwrap();
+    fs::remove_dir_all(&path).ok();
+
+    let ServerMessage::ChunkData { pos, chunk } = chunk else {
+      unreachable!();
+    };
+    assert!(*chunk == *server.world().chunk(pos).unwrap());
+    assert_eq!(1, server.players().count());
+  }
+
+  // Check that a block placed by one client is confirmed to it, and sent to
+  // the other clients which can see it.
+  #[test]
+  fn test_block_change_confirmed() {
+    let (mut server, mut network, path) = start("block");
+    let address = network.local_address().unwrap().to_string();
+    let mut placer = ServerConnection::connect(&address, "placer").unwrap();
+    let mut watcher = ServerConnection::connect(&address, "watcher").unwrap();
+    let beside = BlockPos::containing(server.spawn_position()).offset(2, 0, 0);
+
+    // Wait until both clients have the chunk which will change
+    for client in [&mut placer, &mut watcher] {
+      run_until(&mut server, &mut network, client, |message| {
+        matches!(message, ServerMessage::ChunkData { pos: chunk, .. } if *chunk == beside.chunk())
+      })
+      .unwrap();
+    }
+
+    // Place glass carried by the placer on top of the ground beside the spawn,
+    // which is made solid in case the spawn is by water
+    let (id, _) = server
+      .players()
+      .find(|(_, connected)| connected.name == "placer")
+      .unwrap();
+    server
+      .simulation
+      .player_mut(id)
+      .unwrap()
+      .inventory
+      .add(Block::Glass);
+    let ground = (-8..8)
+      .rev()
+      .map(|dy| beside.offset(0, dy, 0))
+      .find(|&pos| !server.world().get(pos).is_air())
+      .unwrap();
+    server.simulation.world_mut().set(ground, Block::Stone);
+    let pos = ground.offset(0, 1, 0);
+    placer
+      .send(&ClientMessage::SetBlock {
+        pos,
+        block: Block::Glass,
+      })
+      .unwrap();
+
+    let expected = ServerMessage::BlockChange {
+      pos,
+      block: Block::Glass,
+    };
+    for client in [&mut placer, &mut watcher] {
+      run_until(&mut server, &mut network, client, |message| {
+        *message == expected
+      })
+      .unwrap();
+    }
+    fs::remove_dir_all(&path).ok();
+
+    assert_eq!(Block::Glass, server.world().get(pos));
+  }
+
+  // Check that clients with a different protocol version are disconnected,
+  // with a reason.
+  #[test]
+  fn test_protocol_version_mismatch() {
+    let (mut server, mut network, path) = start("version");
+    let address = network.local_address().unwrap().to_string();
+    let mut connection = Connection::connect(&address).unwrap();
+    connection
+      .send(&ClientMessage::Handshake {
+        protocol_version: PROTOCOL_VERSION + 1,
+      })
+      .unwrap();
+
+    let mut reply = None;
+    for _ in 0..500 {
+      network.update(&mut server);
+      if let Some(message) = connection.receive::<ServerMessage>().unwrap() {
+        reply = Some(message);
+        break;
+      }
+      thread::sleep(Duration::from_millis(2));
+    }
+    fs::remove_dir_all(&path).ok();
+
+    assert!(matches!(
+      reply,
+      Some(ServerMessage::Disconnect { reason }) if reason.contains("protocol version")
+    ));
+    assert_eq!(0, server.players().count());
+  }
+}
@@ -0,0 +1,174 @@
+//! The player's settings, kept in a `settings.txt` file in [`Storage`] as
+//! `key = value` lines. Blank lines and lines starting with `#` are ignored,
+//! settings which aren't listed keep their defaults, and unknown keys are
+//! skipped with a warning. The keys are:
+//!
+//! - `field_of_view`: the vertical field of view, in degrees.
+//! - `mouse_sensitivity`: multiplies how fast the mouse turns the camera.
+//! - `render_distance`: how many chunks around the player are loaded in each
+//!   horizontal direction. Fog hides the world beyond them.
+//! - `vsync`: `true` to wait for the display before showing each frame, or
+//!   `false` to show frames as soon as they're rendered.
+//! - `ambient_occlusion`: `true` to darken the corners of blocks next to other
+//!   blocks, or `false` to light faces evenly.
+
+use crate::core::math::angle::Angle;
+use crate::platform::{Storage, info, warn};
+use crate::world::generation::GENERATION_RADIUS;
+use anyhow::{Context, Result, ensure};
+use std::ops::RangeInclusive;
+
+const SETTINGS_KEY: &str = "settings.txt";
+
+const FIELD_OF_VIEW_DEGREES: RangeInclusive<f32> = 30.0..=120.0;
+const MOUSE_SENSITIVITY: RangeInclusive<f32> = 0.05..=10.0;
+const RENDER_DISTANCE: RangeInclusive<i32> = 1..=32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+  pub field_of_view: Angle,
+  pub mouse_sensitivity: f32,
+  /// Number of chunks loaded in each horizontal direction from the player.
+  pub render_distance: i32,
+  pub vsync: bool,
+  pub ambient_occlusion: bool,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self {
+      field_of_view: Angle::degrees(75.0),
+      mouse_sensitivity: 1.0,
+      render_distance: GENERATION_RADIUS,
+      vsync: true,
+      ambient_occlusion: true,
+    }
+  }
+}
+
+impl Settings {
+  /// Loads the settings file, writing one with the default settings for the
+  /// player to edit if there isn't one yet.
+  ///
+  /// Falls back to the defaults if the file can't be read.
+  pub fn load(storage: &Storage) -> Self {
+    let result = storage
+      .read(SETTINGS_KEY)
+      .and_then(|settings| match settings {
+        Some(settings) => Self::decode(&String::from_utf8(settings)?),
+        None => {
+          info!("Writing the default settings to {}", SETTINGS_KEY);
+          let settings = Self::default();
+          storage.write(SETTINGS_KEY, settings.encode().as_bytes())?;
+
+          Ok(settings)
+        }
+      });
+
+    result.unwrap_or_else(|err| {
+      warn!(
+        "Using the default settings, as {} could not be loaded: {:#}",
+        SETTINGS_KEY, err
+      );
+      Self::default()
+    })
+  }
+
+  pub fn encode(&self) -> String {
+    format!(
+      "# Opencraft settings\n\
+       field_of_view = {}\n\
+       mouse_sensitivity = {}\n\
+       render_distance = {}\n\
+       vsync = {}\n\
+       ambient_occlusion = {}\n",
+      self.field_of_view.as_degrees().round(),
+      self.mouse_sensitivity,
+      self.render_distance,
+      self.vsync,
+      self.ambient_occlusion,
+    )
+  }
+
+  /// Decodes a settings file, overriding the defaults of the settings it
+  /// lists.
+  pub fn decode(text: &str) -> Result<Self> {
+    let mut settings = Self::default();
+
+    for line in text.lines().map(str::trim) {
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+
+      let (key, value) = line
+        .split_once('=')
+        .with_context(|| format!("settings line has no '=': {line:?}"))?;
+      let (key, value) = (key.trim(), value.trim());
+
+      match key {
+        "field_of_view" => {
+          settings.field_of_view = Angle::degrees(parse_in(value, FIELD_OF_VIEW_DEGREES, key)?);
+        }
+        "mouse_sensitivity" => {
+          settings.mouse_sensitivity = parse_in(value, MOUSE_SENSITIVITY, key)?;
+        }
+        "render_distance" => settings.render_distance = parse_in(value, RENDER_DISTANCE, key)?,
+        "vsync" => settings.vsync = value.parse()?,
+        "ambient_occlusion" => settings.ambient_occlusion = value.parse()?,
+        _ => warn!("Ignoring unknown setting {:?} in {}", key, SETTINGS_KEY),
+      }
+    }
+
+    Ok(settings)
+  }
+}
+
+/// Parses the value of the setting `key`, which must lie within `range`.
+fn parse_in<T>(value: &str, range: RangeInclusive<T>, key: &str) -> Result<T>
+where
+  T: std::str::FromStr + PartialOrd + std::fmt::Display,
+  T::Err: std::error::Error + Send + Sync + 'static,
+{
+  let value: T = value.parse()?;
+  ensure!(
+    range.contains(&value),
+    "{key} {value} is not between {} and {}",
+    range.start(),
+    range.end()
+  );
+
+  Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Check that the default settings survive being written and read back.
+  #[test]
+  fn test_encode_decode_defaults() {
+    let settings = Settings::default();
+
+    assert_eq!(settings, Settings::decode(&settings.encode()).unwrap());
+  }
+
+  // Check that listed settings are overridden, out of range values are
+  // refused, and unknown settings are skipped.
+  #[test]
+  fn test_decode_overrides() {
+    let settings = Settings::decode("field_of_view = 90\nvsync = false\n").unwrap();
+
+    assert_eq!(Angle::degrees(90.0), settings.field_of_view);
+    assert!(!settings.vsync);
+    assert_eq!(
+      Settings::default().render_distance,
+      settings.render_distance
+    );
+
+    assert!(Settings::decode("render_distance = 0").is_err());
+    assert_eq!(
+      Settings::decode("vsync = false").unwrap(),
+      Settings::decode("brightness = 1\nvsync = false").unwrap()
+    );
+  }
+}
@@ -1,31 +1,103 @@
 @group(0) @binding(0)
-var<uniform> transform: array<mat4x4<f32>, 256>;
+var<uniform> transform: mat4x4<f32>;
+
+struct Environment {
+  camera_position: vec3<f32>,
+  // Scales sky light with the time of day, from dim at night to 1 during the day
+  ambient_light: f32,
+  // Matches the sky at the horizon, so that the edge of the world fades away
+  fog_colour: vec3<f32>,
+  // Horizontal distances from the camera between which fog thickens
+  fog_start: f32,
+  fog_end: f32,
+}
+
+@group(0) @binding(1)
+var<uniform> environment: Environment;
 
 struct VertexInput {
-  @builtin(instance_index) instance: u32,
   @location(0) position: vec3<f32>,
-  @location(1) texture_coordinate: vec2<f32>,
+  @location(1) tile_coordinate: vec2<f32>,
+  @location(2) tile_origin: vec2<f32>,
+  @location(3) texture_layer: u32,
+  // Sky light and block light levels, from 0 to 1
+  @location(4) light: vec2<f32>,
+  // Ambient occlusion, from 0 in a corner between blocks to 1 in the open
+  @location(5) occlusion: f32,
+  // Per-instance: vertex positions are relative to their chunk's origin
+  @location(6) chunk_origin: vec3<f32>,
 }
 
 struct VertexOutput {
   @builtin(position) position: vec4<f32>,
-  @location(0) texture_coordinate: vec2<f32>,
+  @location(0) tile_coordinate: vec2<f32>,
+  @location(1) @interpolate(flat) tile_origin: vec2<f32>,
+  @location(2) @interpolate(flat) texture_layer: u32,
+  @location(3) brightness: f32,
+  @location(4) world_position: vec3<f32>,
 }
 
 @vertex
 fn vs_main(vertex: VertexInput) -> VertexOutput {
   var out: VertexOutput;
-  out.position = transform[vertex.instance] * vec4<f32>(vertex.position, 1.0);
-  out.texture_coordinate = vertex.texture_coordinate;
+  let world_position = vertex.position + vertex.chunk_origin;
+  out.position = transform * vec4<f32>(world_position, 1.0);
+  out.world_position = world_position;
+  out.tile_coordinate = vertex.tile_coordinate;
+  out.tile_origin = vertex.tile_origin;
+  out.texture_layer = vertex.texture_layer;
+  out.brightness = brightness(max(vertex.light.x * environment.ambient_light, vertex.light.y))
+    * mix(OCCLUDED_BRIGHTNESS, 1.0, vertex.occlusion);
   return out;
 }
 
+// Each light level is this much brighter than the level below it
+const LIGHT_LEVEL_RATIO: f32 = 1.25;
+const LIGHT_LEVELS: f32 = 15.0;
+// Keeps unlit faces from being completely black
+const MIN_BRIGHTNESS: f32 = 0.05;
+// Brightness of a fully occluded corner, relative to an open one
+const OCCLUDED_BRIGHTNESS: f32 = 0.5;
+
+// Maps a light level from 0 to 1 to a brightness, which grows geometrically as
+// the eye perceives brightness
+fn brightness(light: f32) -> f32 {
+  let brightness = pow(LIGHT_LEVEL_RATIO, (light - 1.0) * LIGHT_LEVELS);
+  return mix(MIN_BRIGHTNESS, 1.0, brightness);
+}
+
 @group(1) @binding(0)
-var texture: texture_2d<f32>;
+var texture: texture_2d_array<f32>;
 @group(1) @binding(1)
 var texture_sampler: sampler;
 
+// Size of one face tile in a block texture cube net
+const TILE_SIZE = vec2(1.0 / 3.0, 1.0 / 4.0);
+// Keeps samples from bleeding into the neighbouring tile at the far edges
+const TILE_EDGE = 0.9999;
+const ALPHA_CUTOFF: f32 = 0.5;
+
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
-  return textureSample(texture, texture_sampler, vertex.texture_coordinate);
+  // Merged faces span several blocks, so repeat the tile once per block
+  let tile_position = min(fract(vertex.tile_coordinate), vec2(TILE_EDGE));
+
+  let colour = textureSample(
+    texture,
+    texture_sampler,
+    vertex.tile_origin + (tile_position * TILE_SIZE),
+    vertex.texture_layer
+  );
+
+  // Transparent blocks are drawn with cut-out rather than blended textures
+  if (colour.a < ALPHA_CUTOFF) {
+    discard;
+  }
+
+  // Fog is by horizontal distance, like the chunks loaded around the camera
+  let distance = length(vertex.world_position.xz - environment.camera_position.xz);
+  let fog_range = environment.fog_end - environment.fog_start;
+  let fog = clamp((distance - environment.fog_start) / fog_range, 0.0, 1.0);
+
+  return vec4(mix(colour.rgb * vertex.brightness, environment.fog_colour, fog), colour.a);
 }
@@ -0,0 +1,53 @@
+struct VertexInput {
+  @location(0) screen_position: vec2<f32>,
+  @location(1) tile_coordinate: vec2<f32>,
+  @location(2) tile_origin: vec2<f32>,
+  // Negative for quads filled with their colour alone
+  @location(3) texture_layer: i32,
+  @location(4) colour: vec4<f32>,
+}
+
+struct VertexOutput {
+  @builtin(position) position: vec4<f32>,
+  @location(0) tile_coordinate: vec2<f32>,
+  @location(1) @interpolate(flat) tile_origin: vec2<f32>,
+  @location(2) @interpolate(flat) texture_layer: i32,
+  @location(3) colour: vec4<f32>,
+}
+
+@vertex
+fn vs_main(vertex: VertexInput) -> VertexOutput {
+  var out: VertexOutput;
+  out.position = vec4<f32>(vertex.screen_position, 0.0, 1.0);
+  out.tile_coordinate = vertex.tile_coordinate;
+  out.tile_origin = vertex.tile_origin;
+  out.texture_layer = vertex.texture_layer;
+  out.colour = vertex.colour;
+  return out;
+}
+
+@group(0) @binding(0)
+var texture: texture_2d_array<f32>;
+@group(0) @binding(1)
+var texture_sampler: sampler;
+
+// Size of one face tile in a block texture cube net
+const TILE_SIZE = vec2(1.0 / 3.0, 1.0 / 4.0);
+// Keeps samples from bleeding into the neighbouring tile at the far edges
+const TILE_EDGE = 0.9999;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  // Sampled outside the branch, since sampling needs uniform control flow
+  let colour = textureSample(
+    texture,
+    texture_sampler,
+    vertex.tile_origin + (min(vertex.tile_coordinate, vec2(TILE_EDGE)) * TILE_SIZE),
+    max(vertex.texture_layer, 0)
+  );
+
+  if (vertex.texture_layer < 0) {
+    return vertex.colour;
+  }
+  return colour * vertex.colour;
+}
@@ -1,5 +1,18 @@
+struct Sky {
+  // Blended between night and the biome around the camera by day
+  zenith_colour: vec3<f32>,
+  // How light the sky is, from 0 at night to 1 during the day
+  daylight: f32,
+  // Distant blocks fade into this colour too
+  horizon_colour: vec3<f32>,
+  // Direction towards the sun, with the moon opposite
+  sun_direction: vec3<f32>,
+}
+
 @group(0) @binding(0)
 var<uniform> transform: mat4x4<f32>;
+@group(0) @binding(1)
+var<uniform> sky: Sky;
 
 struct VertexInput {
   @location(0) position: vec3<f32>,
@@ -18,12 +31,41 @@ fn vs_main(vertex: VertexInput) -> VertexOutput {
   return out;
 }
 
+const SUNSET_COLOUR = vec3(1.0, 0.45, 0.15);
+const SUN_COLOUR = vec3(1.0, 0.95, 0.8);
+const MOON_COLOUR = vec3(0.8, 0.83, 0.9);
+// Cosines of the angles from the centre of the sun and moon to their edges
+const SUN_EDGE: f32 = 0.9996;
+const MOON_EDGE: f32 = 0.9997;
+// Width of the blurred rim of the discs, in the same units
+const DISC_RIM: f32 = 0.00005;
+
+// Returns 1 inside a disc around `centre` and 0 outside it
+fn disc(direction: vec3<f32>, centre: vec3<f32>, edge: f32) -> f32 {
+  return smoothstep(edge - DISC_RIM, edge, dot(direction, centre));
+}
+
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
-  if (vertex.world_position.y < 0.0) {
-    discard;
-  }
+  let direction = normalize(vertex.world_position);
+  let height = max(direction.y, 0.0);
+
+  // Below the horizon, the sky stays the colour which distant blocks fade into
+  let towards_horizon = pow(1.0 - height, 3.0);
+  var colour = mix(sky.zenith_colour, sky.horizon_colour, towards_horizon);
+  let above_horizon = smoothstep(-0.01, 0.01, direction.y);
+
+  // While the sun is near the horizon, the sky around it glows
+  let twilight = 1.0 - abs((2.0 * sky.daylight) - 1.0);
+  let towards_sun = max(dot(direction, sky.sun_direction), 0.0);
+  let glow = twilight * pow(towards_sun, 4.0) * towards_horizon * above_horizon;
+  colour = mix(colour, SUNSET_COLOUR, glow);
+
+  // The sun and moon set behind the horizon, and the moon fades by day
+  let sun = disc(direction, sky.sun_direction, SUN_EDGE);
+  let moon = disc(direction, -sky.sun_direction, MOON_EDGE) * (1.0 - (0.7 * sky.daylight));
+  colour = mix(colour, SUN_COLOUR, sun * above_horizon);
+  colour = mix(colour, MOON_COLOUR, moon * above_horizon);
 
-  // Plains sky colour: #78A7FF
-  return vec4(0.471, 0.655, 1.0, 1.0);
+  return vec4(colour, 1.0);
 }
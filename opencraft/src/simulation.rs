@@ -0,0 +1,354 @@
+//! The rules of the world and the players in it, shared by the game and the
+//! dedicated server so that both simulate the world the same way.
+//!
+//! Only the game looks through a camera, so players here are just bodies with
+//! inventories, moved in directions already turned into world space.
+
+use crate::core::math::aligned_box3::{AlignedBox3, BoxFace};
+use crate::core::math::segment3::Segment3;
+use crate::core::math::vec3::Vec3;
+use crate::net::protocol::PlayerId;
+use crate::world::World;
+use crate::world::block::Block;
+use crate::world::generation::{self, TerrainGenerator};
+use crate::world::inventory::Inventory;
+use crate::world::player::{MovementMode, Player};
+use crate::world::position::{BlockPos, ChunkPos};
+use crate::world::raycast::raycast;
+use crate::world::save::WorldSave;
+use crate::world::time::WorldTime;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::iter;
+use std::time::Duration;
+
+/// Furthest distance from a player's eyes at which they can reach blocks.
+pub const REACH_DISTANCE: f32 = 5.0;
+/// Distance beyond [`REACH_DISTANCE`] allowed when checking a change, since
+/// the part of a block which was reached may be further than the points
+/// checked, and a server only hears where players are after they've moved.
+const REACH_TOLERANCE: f32 = 1.0;
+
+/// A player's body and the blocks they carry.
+#[derive(Debug, Clone)]
+pub struct SimulatedPlayer {
+  pub body: Player,
+  pub inventory: Inventory,
+}
+
+/// A world, the time in it and the players exploring it.
+pub struct Simulation {
+  world: World,
+  terrain_generator: TerrainGenerator,
+  time: WorldTime,
+  // Ordered, so that players are simulated in the same order every tick
+  players: BTreeMap<PlayerId, SimulatedPlayer>,
+}
+
+impl Simulation {
+  /// Creates a simulation of `world` at `time`, without any players.
+  pub fn new(world: World, terrain_generator: TerrainGenerator, time: WorldTime) -> Self {
+    Self {
+      world,
+      terrain_generator,
+      time,
+      players: BTreeMap::new(),
+    }
+  }
+
+  pub fn world(&self) -> &World {
+    &self.world
+  }
+
+  pub fn world_mut(&mut self) -> &mut World {
+    &mut self.world
+  }
+
+  pub fn terrain_generator(&self) -> &TerrainGenerator {
+    &self.terrain_generator
+  }
+
+  /// Replaces the seed which the world's terrain is generated with, for a
+  /// world generated elsewhere.
+  pub fn set_seed(&mut self, seed: u64) {
+    self.terrain_generator = TerrainGenerator::new(seed);
+    self.world.set_terrain(self.terrain_generator);
+  }
+
+  pub fn time(&self) -> WorldTime {
+    self.time
+  }
+
+  /// Replaces the world's time, such as with the time kept by a server.
+  pub fn set_time(&mut self, time: WorldTime) {
+    self.time = time;
+  }
+
+  /// Adds a player standing at `position`, carrying nothing.
+  pub fn add_player(&mut self, id: PlayerId, position: Vec3) {
+    self.players.insert(
+      id,
+      SimulatedPlayer {
+        body: Player::new(position),
+        inventory: Inventory::new(),
+      },
+    );
+  }
+
+  pub fn remove_player(&mut self, id: PlayerId) -> Option<SimulatedPlayer> {
+    self.players.remove(&id)
+  }
+
+  pub fn player(&self, id: PlayerId) -> Option<&SimulatedPlayer> {
+    self.players.get(&id)
+  }
+
+  pub fn player_mut(&mut self, id: PlayerId) -> Option<&mut SimulatedPlayer> {
+    self.players.get_mut(&id)
+  }
+
+  pub fn players(&self) -> impl Iterator<Item = (PlayerId, &SimulatedPlayer)> {
+    self.players.iter().map(|(&id, player)| (id, player))
+  }
+
+  /// Advances the world by `delta`, moving each player in the direction in
+  /// world space which `movement` returns for them, and jumping if it says
+  /// so.
+  pub fn tick(&mut self, delta: Duration, mut movement: impl FnMut(PlayerId) -> (Vec3, bool)) {
+    self.time.advance(delta);
+
+    for (&id, player) in &mut self.players {
+      let (direction, jump) = movement(id);
+      player
+        .body
+        .update(&self.world, direction, jump, delta.as_secs_f32());
+    }
+  }
+
+  /// Loads up to `limit` chunks missing within `radius` chunks of each of
+  /// `centers` in turn from `save`, nearest first, generating those which were
+  /// never saved.
+  pub fn load_around(
+    &mut self,
+    save: &mut WorldSave,
+    centers: &[ChunkPos],
+    radius: i32,
+    limit: usize,
+  ) -> Result<()> {
+    let mut budget = limit;
+    for &center in centers {
+      budget -= save.load_around(
+        &mut self.world,
+        &self.terrain_generator,
+        center,
+        radius,
+        budget,
+      )?;
+    }
+
+    Ok(())
+  }
+
+  /// Unloads the chunks more than `margin` chunks further from every one of
+  /// `centers` than [`Simulation::load_around`] loads them with `radius`,
+  /// saving any unsaved changes to them in `save` first.
+  pub fn unload_distant(
+    &mut self,
+    mut save: Option<&mut WorldSave>,
+    centers: &[ChunkPos],
+    radius: i32,
+    margin: i32,
+  ) -> Result<()> {
+    let distant: Vec<ChunkPos> = self
+      .world
+      .chunks()
+      .map(|(pos, _)| pos)
+      .filter(|&pos| {
+        centers
+          .iter()
+          .all(|&center| generation::is_distant(center, pos, radius, margin))
+      })
+      .collect();
+
+    for pos in distant {
+      match &mut save {
+        Some(save) => save.unload_chunk(&mut self.world, pos)?,
+        None => {
+          self.world.remove_chunk(pos);
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Returns whether the player `id` may change the block at `pos` to
+  /// `block`.
+  ///
+  /// Only solid blocks can be broken, and blocks can only be placed against
+  /// solid blocks, from the player's inventory, and never inside a walking
+  /// player. Either way, the block must be loaded, within the player's reach
+  /// and not hidden behind other blocks.
+  pub fn can_change_block(&self, id: PlayerId, pos: BlockPos, block: Block) -> bool {
+    let Some(player) = self.players.get(&id) else {
+      return false;
+    };
+    if self.world.chunk(pos.chunk()).is_none() || !self.can_reach(player, pos) {
+      return false;
+    }
+
+    let previous = self.world.get(pos);
+    if block.is_air() {
+      return previous.is_solid();
+    }
+
+    let block_box = AlignedBox3::cube(pos.center(), 0.5);
+    let traps_player = self.players.values().any(|player| {
+      (player.body.mode() == MovementMode::Walking)
+        && block_box.intersects(&player.body.bounding_box())
+    });
+    let supported = BoxFace::ALL
+      .into_iter()
+      .any(|face| self.world.get(pos.adjacent(face)).is_solid());
+
+    !previous.is_solid()
+      && supported
+      && player.inventory.contains(block)
+      && !(block.is_solid() && traps_player)
+  }
+
+  /// Makes a block change which the player `id` asked for, moving a broken
+  /// block into their inventory or taking a placed block out of it. The change
+  /// is made whether or not [`Simulation::can_change_block`] allows it, since
+  /// a server may already have made it.
+  pub fn change_block(&mut self, id: PlayerId, pos: BlockPos, block: Block) {
+    if let Some(player) = self.players.get_mut(&id) {
+      if block.is_air() {
+        // Blocks which don't fit in the inventory are lost, and air broken by
+        // someone else first isn't collected at all
+        let broken = self.world.get(pos);
+        if !broken.is_air() {
+          player.inventory.add(broken);
+        }
+      } else {
+        player.inventory.take(block);
+      }
+    }
+
+    self.world.set(pos, block);
+  }
+
+  /// Returns whether `player` can reach the block at `pos` from their eyes
+  /// without other blocks in the way.
+  fn can_reach(&self, player: &SimulatedPlayer, pos: BlockPos) -> bool {
+    let eye_position = player.body.eye_position();
+
+    // Part of a block may be hidden while the rest can be seen, so look for
+    // its centre and just inside the middle of each of its faces
+    iter::once(Vec3::default())
+      .chain(BoxFace::ALL.map(|face| 0.45 * face.normal()))
+      .map(|offset| pos.center() + offset - eye_position)
+      .filter(|offset| offset.len_sq() > 0.0)
+      .any(|offset| {
+        let ray = Segment3::start_direction_len(eye_position, offset, offset.len());
+
+        (offset.len() <= REACH_DISTANCE + REACH_TOLERANCE)
+          && raycast(&self.world, &ray).is_none_or(|hit| hit.block == pos)
+      })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::world::chunk::Chunk;
+
+  /// Returns a simulation of a stone floor at y = 0 in a single chunk, with a
+  /// player standing on it.
+  fn floor_simulation() -> Simulation {
+    let mut world = World::new();
+    world.insert_chunk(ChunkPos::new(0, 0, 0), Chunk::default());
+    for x in 0..4 {
+      for z in 0..4 {
+        world.set(BlockPos::new(x, 0, z), Block::Stone);
+      }
+    }
+
+    let mut simulation = Simulation::new(world, TerrainGenerator::new(0), WorldTime::default());
+    simulation.add_player(PlayerId(0), Vec3::new(1.0, 0.5, 1.0));
+    simulation
+  }
+
+  // Check that only solid blocks can be broken, and that blocks can only be
+  // placed from the inventory against solid blocks, outside walking players
+  // and loaded chunks.
+  #[test]
+  fn test_can_change_block() {
+    let mut simulation = floor_simulation();
+    let (id, feet) = (PlayerId(0), BlockPos::new(1, 1, 1));
+    let beside = feet.offset(2, 0, 0);
+
+    assert!(simulation.can_change_block(id, BlockPos::new(3, 0, 3), Block::Air));
+    assert!(!simulation.can_change_block(id, beside, Block::Air));
+    assert!(!simulation.can_change_block(id, beside, Block::Stone));
+
+    simulation
+      .player_mut(id)
+      .unwrap()
+      .inventory
+      .add(Block::Stone);
+    assert!(simulation.can_change_block(id, beside, Block::Stone));
+    assert!(!simulation.can_change_block(id, beside.offset(0, 1, 0), Block::Stone));
+    assert!(!simulation.can_change_block(id, BlockPos::new(3, 0, 3), Block::Stone));
+    assert!(!simulation.can_change_block(id, feet, Block::Stone));
+    assert!(!simulation.can_change_block(id, BlockPos::new(-1, 1, 1), Block::Stone));
+    assert!(!simulation.can_change_block(PlayerId(1), beside, Block::Stone));
+
+    simulation.player_mut(id).unwrap().body.toggle_mode();
+    assert!(simulation.can_change_block(id, feet, Block::Stone));
+  }
+
+  // Check that blocks beyond the player's reach, or hidden behind other blocks,
+  // can't be changed.
+  #[test]
+  fn test_change_needs_reach_and_sight() {
+    let mut simulation = floor_simulation();
+    let id = PlayerId(0);
+    let (far, hidden) = (BlockPos::new(12, 1, 1), BlockPos::new(4, 1, 1));
+    for pos in [far, hidden] {
+      simulation.world_mut().set(pos, Block::Stone);
+    }
+
+    assert!(!simulation.can_change_block(id, far, Block::Air));
+    assert!(simulation.can_change_block(id, hidden, Block::Air));
+
+    for pos in [BlockPos::new(3, 1, 1), BlockPos::new(3, 2, 1)] {
+      simulation.world_mut().set(pos, Block::Stone);
+    }
+    assert!(!simulation.can_change_block(id, hidden, Block::Air));
+  }
+
+  // Check that broken blocks are carried by the player who broke them, and
+  // that placing takes them out of their inventory again.
+  #[test]
+  fn test_change_block_moves_blocks_through_inventory() {
+    let mut simulation = floor_simulation();
+    let pos = BlockPos::new(3, 0, 3);
+    let selected = |simulation: &Simulation| {
+      simulation
+        .player(PlayerId(0))
+        .unwrap()
+        .inventory
+        .selected()
+        .map(|stack| stack.block)
+    };
+
+    simulation.change_block(PlayerId(0), pos, Block::Air);
+    assert_eq!(Block::Air, simulation.world().get(pos));
+    assert_eq!(Some(Block::Stone), selected(&simulation));
+
+    simulation.change_block(PlayerId(0), pos, Block::Stone);
+    assert_eq!(Block::Stone, simulation.world().get(pos));
+    assert_eq!(None, selected(&simulation));
+  }
+}
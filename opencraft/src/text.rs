@@ -110,6 +110,21 @@ impl FontAtlas {
     )
   }
 
+  pub fn glyph_pixel_height(&self) -> f32 {
+    self.glyph_pixel_height
+  }
+
+  /// Returns how many pixels wide `text` is drawn.
+  pub fn text_pixel_width(&self, text: &str) -> f32 {
+    text
+      .chars()
+      .map(|char| match char {
+        SPACE => self.space_pixel_width,
+        char => self.get_glyph_mapping(char).pixel_width,
+      })
+      .sum()
+  }
+
   pub fn push_text_vertices(
     &self,
     text: &str,
@@ -0,0 +1,292 @@
+pub mod biome;
+pub mod block;
+pub mod chunk;
+pub mod generation;
+pub mod inventory;
+pub mod light;
+pub mod noise;
+pub mod player;
+pub mod position;
+pub mod raycast;
+pub mod save;
+pub mod time;
+
+use crate::core::math::aligned_box3::BoxFace;
+use crate::world::block::Block;
+use crate::world::chunk::Chunk;
+use crate::world::generation::TerrainGenerator;
+use crate::world::light::ChunkLight;
+use crate::world::position::{BlockPos, ChunkPos};
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+/// The voxel world, stored as a sparse set of fixed-size chunks keyed by their
+/// chunk coordinates.
+#[derive(Default)]
+pub struct World {
+  chunks: HashMap<ChunkPos, Chunk>,
+  /// Light levels of each loaded chunk, which are derived from the blocks and
+  /// so never saved.
+  light: HashMap<ChunkPos, ChunkLight>,
+  /// Chunks whose blocks, or whose neighbours' bordering blocks, have changed
+  /// since they were last taken.
+  dirty_chunks: HashSet<ChunkPos>,
+  /// Chunks whose blocks have been changed since they were generated, which
+  /// are the only chunks that need saving.
+  modified_chunks: HashSet<ChunkPos>,
+  /// Chunks whose blocks have changed since the world was last saved.
+  unsaved_chunks: HashSet<ChunkPos>,
+  /// Generates the terrain in the chunks which aren't loaded, which decides
+  /// where sky light falls into the loaded chunks below them. Without it, the
+  /// sky is open above every loaded chunk.
+  terrain: Option<TerrainGenerator>,
+}
+
+impl World {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Creates an empty world whose chunks are generated by `terrain`.
+  pub fn with_terrain(terrain: TerrainGenerator) -> Self {
+    Self {
+      terrain: Some(terrain),
+      ..Self::default()
+    }
+  }
+
+  /// Replaces the generator of the world's terrain, for a world generated
+  /// elsewhere. Chunks which are already loaded aren't lit again.
+  pub fn set_terrain(&mut self, terrain: TerrainGenerator) {
+    self.terrain = Some(terrain);
+  }
+
+  pub fn get(&self, pos: BlockPos) -> Block {
+    self
+      .chunks
+      .get(&pos.chunk())
+      .map_or(Block::Air, |chunk| chunk.get(pos.local()))
+  }
+
+  /// Sets the block at `pos`, first loading the chunk holding it if necessary
+  /// as the world's terrain generates it, or empty without any terrain.
+  pub fn set(&mut self, pos: BlockPos, block: Block) {
+    if !self.chunks.contains_key(&pos.chunk()) {
+      let chunk = match self.terrain {
+        Some(terrain) => terrain.generate(pos.chunk()),
+        // Setting air in empty space changes nothing
+        None if block == Block::Air => return,
+        None => Chunk::default(),
+      };
+
+      self.insert_chunk(pos.chunk(), chunk);
+    }
+
+    let chunk = self.chunks.get_mut(&pos.chunk()).unwrap();
+    let previous = chunk.get(pos.local());
+    chunk.set(pos.local(), block);
+
+    self.modified_chunks.insert(pos.chunk());
+    self.unsaved_chunks.insert(pos.chunk());
+    self.mark_dirty(pos);
+
+    if (previous.is_transparent() != block.is_transparent())
+      || (previous.emission() != block.emission())
+    {
+      self.relight_block(pos);
+    }
+  }
+
+  /// Loads a whole chunk at once, replacing any chunk already at `pos`.
+  ///
+  /// Unlike [`World::set`], empty chunks are kept, to record that the space
+  /// has been loaded.
+  pub fn insert_chunk(&mut self, pos: ChunkPos, chunk: Chunk) {
+    let relight = self
+      .chunks
+      .contains_key(&pos)
+      .then(|| self.darken_chunk(pos));
+
+    self.chunks.insert(pos, chunk);
+    // A saved chunk replaced by a generated one is left out of the next save
+    if self.modified_chunks.remove(&pos) {
+      self.unsaved_chunks.insert(pos);
+    }
+    self.mark_chunk_dirty(pos);
+
+    self.light_new_chunk(pos);
+    if let Some(relight) = relight {
+      self.relight(relight);
+    }
+  }
+
+  /// Unloads the chunk at `pos`, returning it if it was loaded. The world is
+  /// lit as if the chunk had never been loaded.
+  ///
+  /// Unsaved changes to the chunk are lost, unless it is unloaded by
+  /// [`WorldSave::unload_chunk`](save::WorldSave::unload_chunk).
+  pub fn remove_chunk(&mut self, pos: ChunkPos) -> Option<Chunk> {
+    // Taken out before its light, so that the blocks below which it left lit
+    // by the sky keep their light rather than being darkened and lit again
+    let chunk = self.chunks.remove(&pos)?;
+    let relight = self.darken_chunk(pos);
+    self.light.remove(&pos);
+    self.modified_chunks.remove(&pos);
+    self.unsaved_chunks.remove(&pos);
+    self.mark_chunk_dirty(pos);
+
+    self.relight(relight);
+    self.uncover_chunk(pos.adjacent(BoxFace::Bottom));
+
+    Some(chunk)
+  }
+
+  /// Marks the chunk at `pos` as dirty, along with its loaded neighbours.
+  fn mark_chunk_dirty(&mut self, pos: ChunkPos) {
+    self.dirty_chunks.insert(pos);
+    for face in BoxFace::ALL {
+      let neighbour = pos.adjacent(face);
+
+      if self.chunks.contains_key(&neighbour) {
+        self.dirty_chunks.insert(neighbour);
+      }
+    }
+  }
+
+  /// Marks the chunk holding `pos` as dirty, along with any neighbouring
+  /// chunks whose faces are next to it.
+  fn mark_dirty(&mut self, pos: BlockPos) {
+    self.dirty_chunks.insert(pos.chunk());
+    for face in BoxFace::ALL {
+      let neighbour = pos.adjacent(face).chunk();
+
+      if (neighbour != pos.chunk()) && self.chunks.contains_key(&neighbour) {
+        self.dirty_chunks.insert(neighbour);
+      }
+    }
+  }
+
+  /// Returns the chunks which have changed since the last call, so that
+  /// anything derived from their blocks can be rebuilt.
+  pub fn take_dirty_chunks(&mut self) -> HashSet<ChunkPos> {
+    mem::take(&mut self.dirty_chunks)
+  }
+
+  /// Marks a loaded chunk as modified, such as when it was loaded from a save
+  /// rather than generated.
+  pub fn mark_modified(&mut self, pos: ChunkPos) {
+    assert!(self.chunks.contains_key(&pos));
+    self.modified_chunks.insert(pos);
+  }
+
+  pub fn is_modified(&self, pos: ChunkPos) -> bool {
+    self.modified_chunks.contains(&pos)
+  }
+
+  pub fn modified_chunks(&self) -> impl Iterator<Item = (ChunkPos, &Chunk)> {
+    self
+      .modified_chunks
+      .iter()
+      .map(|&pos| (pos, &self.chunks[&pos]))
+  }
+
+  /// Returns the chunks whose blocks have changed since the world was last
+  /// saved.
+  pub fn unsaved_chunks(&self) -> impl Iterator<Item = ChunkPos> {
+    self.unsaved_chunks.iter().copied()
+  }
+
+  pub fn is_unsaved(&self, pos: ChunkPos) -> bool {
+    self.unsaved_chunks.contains(&pos)
+  }
+
+  /// Records that the changes to the chunks at `positions` have been saved.
+  pub fn mark_saved(&mut self, positions: impl IntoIterator<Item = ChunkPos>) {
+    for pos in positions {
+      self.unsaved_chunks.remove(&pos);
+    }
+  }
+
+  pub fn chunk(&self, pos: ChunkPos) -> Option<&Chunk> {
+    self.chunks.get(&pos)
+  }
+
+  pub fn chunks(&self) -> impl Iterator<Item = (ChunkPos, &Chunk)> {
+    self.chunks.iter().map(|(&pos, chunk)| (pos, chunk))
+  }
+
+  /// Iterates over every non-air block in all loaded chunks.
+  pub fn blocks(&self) -> impl Iterator<Item = (BlockPos, Block)> {
+    self.chunks().flat_map(|(chunk_pos, chunk)| {
+      chunk
+        .blocks()
+        .map(move |(local, block)| (BlockPos::from_chunk_local(chunk_pos, local), block))
+    })
+  }
+
+  pub fn chunk_count(&self) -> usize {
+    self.chunks.len()
+  }
+
+  pub fn block_count(&self) -> usize {
+    self.chunks.values().map(Chunk::block_count).sum()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Check that blocks on either side of the origin land in different chunks and
+  // can be read back.
+  #[test]
+  fn test_set_get_across_chunk_boundary() {
+    let mut world = World::new();
+    let negative = BlockPos::new(-1, -1, -1);
+    let positive = BlockPos::new(0, 0, 0);
+
+    world.set(negative, Block::Grass);
+
+    assert_eq!(Block::Grass, world.get(negative));
+    assert_eq!(Block::Air, world.get(positive));
+    assert_ne!(negative.chunk(), positive.chunk());
+  }
+
+  // Check that setting a block in a chunk which isn't loaded generates the
+  // rest of the chunk, rather than leaving a hole in the terrain.
+  #[test]
+  fn test_set_generates_unloaded_chunk() {
+    let terrain = TerrainGenerator::new(3);
+    let mut world = World::with_terrain(terrain);
+    let pos = BlockPos::new(5, terrain.surface_height(5, 9) - 2, 9);
+
+    world.set(pos, Block::Air);
+
+    let mut expected = terrain.generate(pos.chunk());
+    expected.set(pos.local(), Block::Air);
+    assert!(world.chunk(pos.chunk()) == Some(&expected));
+    assert!(world.is_modified(pos.chunk()));
+  }
+
+  // Check that every block placed is returned by iteration, with its position.
+  #[test]
+  fn test_blocks_iteration() {
+    let mut world = World::new();
+    let positions = [
+      BlockPos::new(0, 0, 3),
+      BlockPos::new(17, -40, 2),
+      BlockPos::new(-16, 15, -17),
+    ];
+
+    for pos in positions {
+      world.set(pos, Block::Grass);
+    }
+    world.set(positions[0], Block::Air);
+
+    let mut blocks: Vec<BlockPos> = world.blocks().map(|(pos, _)| pos).collect();
+    blocks.sort_by_key(|pos| (pos.x(), pos.y(), pos.z()));
+
+    assert_eq!(vec![positions[2], positions[1]], blocks);
+    assert_eq!(2, world.block_count());
+  }
+}
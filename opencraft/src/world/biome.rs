@@ -0,0 +1,131 @@
+use crate::world::block::Block;
+
+pub struct BiomeDefinition {
+  pub name: &'static str,
+  /// Block at the top of each terrain column.
+  pub surface: Block,
+  /// Blocks between the surface and the stone below.
+  pub subsurface: Block,
+  /// Chance of any surface block growing a tree.
+  pub tree_density: f32,
+  /// RGB colour of the sky while the camera is in the biome.
+  pub sky_colour: [f32; 3],
+}
+
+/// Every type of biome, identified by its numeric ID.
+///
+/// IDs are stable, so new biomes must only ever be appended.
+#[repr(u8)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Biome {
+  #[default]
+  Plains,
+  Desert,
+  Forest,
+  Tundra,
+  Ocean,
+}
+
+/// Biome definitions, indexed by biome ID.
+static REGISTRY: [BiomeDefinition; Biome::ALL.len()] = [
+  BiomeDefinition {
+    name: "plains",
+    surface: Block::Grass,
+    subsurface: Block::Dirt,
+    tree_density: 0.002,
+    // #78A7FF
+    sky_colour: [0.471, 0.655, 1.0],
+  },
+  BiomeDefinition {
+    name: "desert",
+    surface: Block::Sand,
+    subsurface: Block::Sand,
+    tree_density: 0.0,
+    // #B4C8E6
+    sky_colour: [0.706, 0.784, 0.902],
+  },
+  BiomeDefinition {
+    name: "forest",
+    surface: Block::Grass,
+    subsurface: Block::Dirt,
+    tree_density: 0.03,
+    // #6A9CE8
+    sky_colour: [0.416, 0.612, 0.91],
+  },
+  BiomeDefinition {
+    name: "tundra",
+    surface: Block::Snow,
+    subsurface: Block::Dirt,
+    tree_density: 0.001,
+    // #C2D4F0
+    sky_colour: [0.761, 0.831, 0.941],
+  },
+  BiomeDefinition {
+    name: "ocean",
+    surface: Block::Sand,
+    subsurface: Block::Sand,
+    tree_density: 0.0,
+    // #5C94F5
+    sky_colour: [0.361, 0.58, 0.961],
+  },
+];
+
+impl Biome {
+  pub const ALL: [Biome; 5] = [
+    Biome::Plains,
+    Biome::Desert,
+    Biome::Forest,
+    Biome::Tundra,
+    Biome::Ocean,
+  ];
+
+  /// Humidity above which the land sinks into ocean.
+  pub const OCEAN_HUMIDITY: f32 = 0.4;
+
+  /// Selects the biome for a climate, where `temperature` and `humidity` are
+  /// both in [-1, 1].
+  pub fn from_climate(temperature: f32, humidity: f32) -> Self {
+    if humidity > Self::OCEAN_HUMIDITY {
+      Biome::Ocean
+    } else if temperature < -0.35 {
+      Biome::Tundra
+    } else if (temperature > 0.3) && (humidity < 0.0) {
+      Biome::Desert
+    } else if humidity > 0.1 {
+      Biome::Forest
+    } else {
+      Biome::Plains
+    }
+  }
+
+  pub fn id(self) -> u8 {
+    self as u8
+  }
+
+  pub fn definition(self) -> &'static BiomeDefinition {
+    &REGISTRY[usize::from(self.id())]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Check that every climate extreme selects a distinct biome, so none of
+  // them are unreachable.
+  #[test]
+  fn test_every_biome_reachable() {
+    let mut biomes: Vec<Biome> = [-1.0, 0.0, 1.0]
+      .into_iter()
+      .flat_map(|temperature| {
+        [-1.0, 0.0, 0.3, 1.0]
+          .into_iter()
+          .map(move |humidity| Biome::from_climate(temperature, humidity))
+      })
+      .collect();
+    biomes.sort_by_key(|biome| biome.id());
+    biomes.dedup();
+
+    assert_eq!(Biome::ALL.to_vec(), biomes);
+  }
+}
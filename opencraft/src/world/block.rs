@@ -0,0 +1,234 @@
+use crate::core::math::aligned_box3::BoxFace;
+use crate::world::light::MAX_LIGHT;
+
+/// A reference to one tile of a block texture.
+///
+/// Block textures are cube nets laid out like `textures/block/grass.png`, so a
+/// face can be textured with any tile of any net, not only its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaceTexture {
+  pub texture: &'static str,
+  pub tile: BoxFace,
+}
+
+impl FaceTexture {
+  pub const fn new(texture: &'static str, tile: BoxFace) -> Self {
+    Self { texture, tile }
+  }
+}
+
+/// Textures for each face of a block, indexed by [`BoxFace::index`].
+pub type FaceTextures = [FaceTexture; 6];
+
+/// Textures every face with its own tile of the `texture` net.
+const fn net(texture: &'static str) -> FaceTextures {
+  [
+    FaceTexture::new(texture, BoxFace::Left),
+    FaceTexture::new(texture, BoxFace::Right),
+    FaceTexture::new(texture, BoxFace::Top),
+    FaceTexture::new(texture, BoxFace::Bottom),
+    FaceTexture::new(texture, BoxFace::Back),
+    FaceTexture::new(texture, BoxFace::Front),
+  ]
+}
+
+/// Textures every face with the same tile of the `texture` net.
+const fn uniform(texture: &'static str, tile: BoxFace) -> FaceTextures {
+  [FaceTexture::new(texture, tile); 6]
+}
+
+pub struct BlockDefinition {
+  pub name: &'static str,
+  /// Whether the block collides with entities and can be targeted.
+  pub solid: bool,
+  /// Whether faces behind the block can be seen through it.
+  pub transparent: bool,
+  /// Block light level given off by the block, up to [`MAX_LIGHT`].
+  pub emission: u8,
+  /// [`None`] for blocks which are never drawn.
+  pub textures: Option<FaceTextures>,
+}
+
+impl BlockDefinition {
+  pub fn texture(&self, face: BoxFace) -> Option<FaceTexture> {
+    self.textures.map(|textures| textures[face.index()])
+  }
+}
+
+/// Every type of block, identified by its numeric ID.
+///
+/// IDs are stable, so new blocks must only ever be appended.
+#[repr(u8)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Block {
+  #[default]
+  Air,
+  Grass,
+  Dirt,
+  Stone,
+  Sand,
+  Wood,
+  Glass,
+  Snow,
+  Water,
+  Leaves,
+  Torch,
+  Lava,
+}
+
+/// Block definitions, indexed by block ID.
+static REGISTRY: [BlockDefinition; Block::ALL.len()] = [
+  BlockDefinition {
+    name: "air",
+    solid: false,
+    transparent: true,
+    emission: 0,
+    textures: None,
+  },
+  BlockDefinition {
+    name: "grass",
+    solid: true,
+    transparent: false,
+    emission: 0,
+    textures: Some(net("grass")),
+  },
+  BlockDefinition {
+    name: "dirt",
+    solid: true,
+    transparent: false,
+    emission: 0,
+    textures: Some(uniform("grass", BoxFace::Bottom)),
+  },
+  BlockDefinition {
+    name: "stone",
+    solid: true,
+    transparent: false,
+    emission: 0,
+    textures: Some(net("stone")),
+  },
+  BlockDefinition {
+    name: "sand",
+    solid: true,
+    transparent: false,
+    emission: 0,
+    textures: Some(net("sand")),
+  },
+  BlockDefinition {
+    name: "wood",
+    solid: true,
+    transparent: false,
+    emission: 0,
+    textures: Some(net("wood")),
+  },
+  BlockDefinition {
+    name: "glass",
+    solid: true,
+    transparent: true,
+    emission: 0,
+    textures: Some(net("glass")),
+  },
+  BlockDefinition {
+    name: "snow",
+    solid: true,
+    transparent: false,
+    emission: 0,
+    textures: Some(net("snow")),
+  },
+  BlockDefinition {
+    name: "water",
+    solid: false,
+    transparent: true,
+    emission: 0,
+    textures: Some(uniform("water", BoxFace::Top)),
+  },
+  BlockDefinition {
+    name: "leaves",
+    solid: true,
+    transparent: true,
+    emission: 0,
+    textures: Some(uniform("leaves", BoxFace::Top)),
+  },
+  BlockDefinition {
+    name: "torch",
+    solid: true,
+    transparent: true,
+    emission: MAX_LIGHT - 1,
+    textures: Some(net("torch")),
+  },
+  BlockDefinition {
+    name: "lava",
+    solid: false,
+    transparent: false,
+    emission: MAX_LIGHT,
+    textures: Some(uniform("lava", BoxFace::Top)),
+  },
+];
+
+impl Block {
+  pub const ALL: [Block; 12] = [
+    Block::Air,
+    Block::Grass,
+    Block::Dirt,
+    Block::Stone,
+    Block::Sand,
+    Block::Wood,
+    Block::Glass,
+    Block::Snow,
+    Block::Water,
+    Block::Leaves,
+    Block::Torch,
+    Block::Lava,
+  ];
+
+  pub fn id(self) -> u8 {
+    self as u8
+  }
+
+  pub fn from_id(id: u8) -> Option<Self> {
+    Self::ALL.get(usize::from(id)).copied()
+  }
+
+  pub fn definition(self) -> &'static BlockDefinition {
+    &REGISTRY[usize::from(self.id())]
+  }
+
+  pub fn is_air(self) -> bool {
+    self == Block::Air
+  }
+
+  pub fn is_solid(self) -> bool {
+    self.definition().solid
+  }
+
+  pub fn is_transparent(self) -> bool {
+    self.definition().transparent
+  }
+
+  pub fn emission(self) -> u8 {
+    self.definition().emission
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Check that every block's ID round-trips and indexes its own definition.
+  #[test]
+  fn test_ids_match_registry() {
+    for (index, block) in Block::ALL.into_iter().enumerate() {
+      assert_eq!(index, usize::from(block.id()));
+      assert_eq!(Some(block), Block::from_id(block.id()));
+    }
+
+    let mut names: Vec<&str> = REGISTRY.iter().map(|definition| definition.name).collect();
+    names.sort();
+    names.dedup();
+    assert_eq!(Block::ALL.len(), names.len());
+    assert!(
+      REGISTRY
+        .iter()
+        .all(|definition| definition.emission <= MAX_LIGHT)
+    );
+  }
+}
@@ -0,0 +1,107 @@
+use crate::core::bytes::{ByteReader, ByteWriter};
+use crate::core::type_conversions::Coerce;
+use crate::world::block::Block;
+use crate::world::position::LocalPos;
+use anyhow::{Context, Result, ensure};
+
+/// Number of blocks along each edge of a chunk.
+pub const CHUNK_SIZE: i32 = 16;
+pub const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+  blocks: Box<[Block; CHUNK_VOLUME]>,
+  block_count: usize,
+}
+
+impl Default for Chunk {
+  fn default() -> Self {
+    Self {
+      blocks: Box::new([Block::Air; CHUNK_VOLUME]),
+      block_count: 0,
+    }
+  }
+}
+
+impl Chunk {
+  pub fn get(&self, pos: LocalPos) -> Block {
+    self.blocks[pos.index()]
+  }
+
+  pub fn set(&mut self, pos: LocalPos, block: Block) {
+    let slot = &mut self.blocks[pos.index()];
+
+    match (slot.is_air(), block.is_air()) {
+      (true, false) => self.block_count += 1,
+      (false, true) => self.block_count -= 1,
+      _ => {}
+    }
+
+    *slot = block;
+  }
+
+  /// Number of non-air blocks in the chunk.
+  pub fn block_count(&self) -> usize {
+    self.block_count
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.block_count == 0
+  }
+
+  /// Returns every block in the chunk, indexed by [`LocalPos::index`].
+  pub fn blocks_by_index(&self) -> &[Block; CHUNK_VOLUME] {
+    &self.blocks
+  }
+
+  /// Iterates over every non-air block in the chunk.
+  pub fn blocks(&self) -> impl Iterator<Item = (LocalPos, Block)> {
+    self
+      .blocks
+      .iter()
+      .enumerate()
+      .filter(|(_, block)| !block.is_air())
+      .map(|(index, &block)| (LocalPos::from_index(index), block))
+  }
+
+  /// Writes the chunk's blocks run-length encoded, as a `u32` run count
+  /// followed by runs of a `u16` length and a `u8` block ID, covering the
+  /// blocks in [`LocalPos::index`] order.
+  pub fn encode_runs(&self, writer: &mut ByteWriter) {
+    let mut runs: Vec<(u16, Block)> = Vec::new();
+    for &block in self.blocks.iter() {
+      match runs.last_mut() {
+        Some((length, run_block)) if *run_block == block => *length += 1,
+        _ => runs.push((1, block)),
+      }
+    }
+
+    writer.u32(runs.len().coerce());
+    for (length, block) in runs {
+      writer.u16(length);
+      writer.u8(block.id());
+    }
+  }
+
+  /// Reads a chunk written by [`Chunk::encode_runs`].
+  pub fn decode_runs(reader: &mut ByteReader) -> Result<Self> {
+    let mut chunk = Chunk::default();
+    let mut index = 0;
+    for _ in 0..reader.u32()? {
+      let length: usize = reader.u16()?.into();
+      let id = reader.u8()?;
+      let block = Block::from_id(id).with_context(|| format!("unknown block ID {id}"))?;
+      ensure!(index + length <= CHUNK_VOLUME, "chunk has too many blocks");
+
+      if !block.is_air() {
+        for index in index..(index + length) {
+          chunk.set(LocalPos::from_index(index), block);
+        }
+      }
+      index += length;
+    }
+    ensure!(index == CHUNK_VOLUME, "chunk has too few blocks");
+
+    Ok(chunk)
+  }
+}
@@ -0,0 +1,430 @@
+use crate::core::math::vec3::Vec3;
+use crate::core::type_conversions::{Coerce, CoerceLossy};
+use crate::world::World;
+use crate::world::biome::Biome;
+use crate::world::block::Block;
+use crate::world::chunk::{CHUNK_SIZE, Chunk};
+use crate::world::noise::{self, Fractal};
+use crate::world::position::{BlockPos, ChunkPos};
+
+/// Seed of newly created worlds.
+pub const DEFAULT_SEED: u64 = 0x0C0F_FEE0;
+
+/// Horizontal distance, in chunks, around a point which is generated.
+pub const GENERATION_RADIUS: i32 = 4;
+/// Vertical distance, in chunks, around a point which is generated.
+pub const GENERATION_HEIGHT: i32 = 2;
+/// Number of chunks further from a point than they're generated that chunks
+/// are unloaded at, so that moving back and forth over a chunk border doesn't
+/// keep unloading and loading the same chunks.
+pub const UNLOAD_MARGIN: i32 = 1;
+
+/// Height which the land surface varies around.
+const BASE_HEIGHT: f32 = 0.0;
+/// Furthest the surface strays above or below its base height.
+const HEIGHT_AMPLITUDE: f32 = 16.0;
+const HEIGHT_NOISE: Fractal = Fractal {
+  octaves: 4,
+  frequency: 1.0 / 96.0,
+  persistence: 0.5,
+};
+
+/// Height of the top layer of water in oceans and lakes.
+pub const SEA_LEVEL: i32 = -4;
+/// Surfaces this close above sea level are beaches, covered in sand.
+const BEACH_HEIGHT: i32 = 1;
+/// How far the base height of oceans sinks below that of the land.
+const OCEAN_DEPTH: f32 = 20.0;
+/// Humidity at which the land starts sinking towards the ocean floor, so that
+/// coasts slope rather than dropping off at the edge of the ocean biome.
+const COAST_HUMIDITY: f32 = 0.25;
+
+/// Number of subsurface blocks between the surface and the stone below it.
+const DIRT_DEPTH: i32 = 3;
+
+const CLIMATE_NOISE: Fractal = Fractal {
+  octaves: 2,
+  frequency: 1.0 / 384.0,
+  persistence: 0.5,
+};
+
+const CAVE_NOISE: Fractal = Fractal {
+  octaves: 2,
+  frequency: 1.0 / 24.0,
+  persistence: 0.5,
+};
+/// Cave noise above this is carved out, so higher values give smaller caves.
+const CAVE_THRESHOLD: f32 = 0.3;
+/// Caves never reach closer than this to the surface, so the ground doesn't
+/// become riddled with holes.
+const CAVE_ROOF_DEPTH: i32 = 6;
+
+/// Height of the shortest tree trunks.
+const TRUNK_HEIGHT: i32 = 4;
+/// Furthest that leaves reach horizontally from their trunk.
+const CANOPY_RADIUS: i32 = 2;
+
+// Salts which give each noise field its own seed, so that they are
+// uncorrelated for the same world seed.
+const HEIGHT_SALT: i32 = 1;
+const CAVE_SALT: i32 = 2;
+const TEMPERATURE_SALT: i32 = 3;
+const HUMIDITY_SALT: i32 = 4;
+const TREE_SALT: i32 = 5;
+
+/// The biome and surface height of a column of terrain.
+#[derive(Debug, Clone, Copy)]
+struct Column {
+  biome: Biome,
+  surface_height: i32,
+}
+
+impl Column {
+  fn is_above_beach(&self) -> bool {
+    self.surface_height > (SEA_LEVEL + BEACH_HEIGHT)
+  }
+
+  /// Returns the surface and subsurface blocks of the column.
+  fn palette(&self) -> (Block, Block) {
+    if self.is_above_beach() {
+      let definition = self.biome.definition();
+      (definition.surface, definition.subsurface)
+    } else {
+      (Block::Sand, Block::Sand)
+    }
+  }
+}
+
+/// Deterministically generates terrain from a world seed.
+///
+/// Generation is a pure function of the seed and chunk position, so chunks can
+/// be generated in any order, on any platform, with identical results.
+#[derive(Debug, Clone, Copy)]
+pub struct TerrainGenerator {
+  seed: u64,
+}
+
+impl TerrainGenerator {
+  pub fn new(seed: u64) -> Self {
+    Self { seed }
+  }
+
+  pub fn seed(&self) -> u64 {
+    self.seed
+  }
+
+  fn salted_seed(&self, salt: i32) -> u64 {
+    noise::hash(self.seed, salt, 0, 0)
+  }
+
+  /// Returns the temperature and humidity at `(x, z)`, both in [-1, 1].
+  fn climate(&self, x: i32, z: i32) -> (f32, f32) {
+    let sample =
+      |salt| CLIMATE_NOISE.sample_2d(self.salted_seed(salt), x.coerce_lossy(), z.coerce_lossy());
+
+    (sample(TEMPERATURE_SALT), sample(HUMIDITY_SALT))
+  }
+
+  fn column(&self, x: i32, z: i32) -> Column {
+    let (temperature, humidity) = self.climate(x, z);
+
+    let coast = (humidity - COAST_HUMIDITY) / (Biome::OCEAN_HUMIDITY - COAST_HUMIDITY);
+    let base_height = BASE_HEIGHT - (noise::smooth(coast.clamp(0.0, 1.0)) * OCEAN_DEPTH);
+
+    let noise = HEIGHT_NOISE.sample_2d(
+      self.salted_seed(HEIGHT_SALT),
+      x.coerce_lossy(),
+      z.coerce_lossy(),
+    );
+
+    Column {
+      biome: Biome::from_climate(temperature, humidity),
+      surface_height: (base_height + (noise * HEIGHT_AMPLITUDE))
+        .round()
+        .coerce_lossy(),
+    }
+  }
+
+  pub fn biome(&self, x: i32, z: i32) -> Biome {
+    self.column(x, z).biome
+  }
+
+  /// Returns the height of the topmost block of the terrain column at `(x, z)`,
+  /// ignoring any water or trees above it.
+  pub fn surface_height(&self, x: i32, z: i32) -> i32 {
+    self.column(x, z).surface_height
+  }
+
+  fn is_cave(&self, pos: BlockPos) -> bool {
+    let noise = CAVE_NOISE.sample_3d(
+      self.salted_seed(CAVE_SALT),
+      pos.x().coerce_lossy(),
+      pos.y().coerce_lossy(),
+      pos.z().coerce_lossy(),
+    );
+
+    noise > CAVE_THRESHOLD
+  }
+
+  fn block(&self, pos: BlockPos, column: Column) -> Block {
+    let depth = column.surface_height - pos.y();
+    let (surface, subsurface) = column.palette();
+
+    if depth < 0 {
+      if pos.y() <= SEA_LEVEL {
+        Block::Water
+      } else {
+        Block::Air
+      }
+    } else if (depth >= CAVE_ROOF_DEPTH) && self.is_cave(pos) {
+      Block::Air
+    } else if depth == 0 {
+      surface
+    } else if depth <= DIRT_DEPTH {
+      subsurface
+    } else {
+      Block::Stone
+    }
+  }
+
+  /// Returns the height of the trunk of the tree growing from the column at
+  /// `(x, z)`, if there is one.
+  fn tree(&self, x: i32, z: i32, column: Column) -> Option<i32> {
+    let seed = self.salted_seed(TREE_SALT);
+
+    if !column.is_above_beach()
+      || (noise::unit(seed, x, 0, z) >= column.biome.definition().tree_density)
+    {
+      return None;
+    }
+
+    Some(TRUNK_HEIGHT + i32::from(noise::unit(seed, x, 1, z) < 0.5))
+  }
+
+  pub fn generate(&self, pos: ChunkPos) -> Chunk {
+    // Trees rooted just outside the chunk can still reach into it
+    const MARGIN: i32 = CANOPY_RADIUS;
+    const COLUMNS: i32 = CHUNK_SIZE + (2 * MARGIN);
+
+    let mut chunk = Chunk::default();
+    let origin = pos.origin();
+
+    let columns: Vec<Column> = (0..(COLUMNS * COLUMNS))
+      .map(|index| {
+        let x = origin.x() - MARGIN + (index % COLUMNS);
+        let z = origin.z() - MARGIN + (index / COLUMNS);
+        self.column(x, z)
+      })
+      .collect();
+    let column_at = |local_x: i32, local_z: i32| {
+      let index: usize = ((local_z + MARGIN) * COLUMNS + (local_x + MARGIN)).coerce();
+      columns[index]
+    };
+
+    for local_x in 0..CHUNK_SIZE {
+      for local_z in 0..CHUNK_SIZE {
+        let column = column_at(local_x, local_z);
+
+        // Skip the part of the column which is air within this chunk
+        let top = (column.surface_height.max(SEA_LEVEL) - origin.y()).min(CHUNK_SIZE - 1);
+        for local_y in 0..=top {
+          let pos = origin.offset(local_x, local_y, local_z);
+          let block = self.block(pos, column);
+
+          if !block.is_air() {
+            chunk.set(pos.local(), block);
+          }
+        }
+      }
+    }
+
+    for local_x in -MARGIN..(CHUNK_SIZE + MARGIN) {
+      for local_z in -MARGIN..(CHUNK_SIZE + MARGIN) {
+        let column = column_at(local_x, local_z);
+        let root = origin.offset(local_x, 0, local_z);
+
+        if let Some(trunk_height) = self.tree(root.x(), root.z(), column) {
+          let root = BlockPos::new(root.x(), column.surface_height + 1, root.z());
+          place_tree(&mut chunk, pos, root, trunk_height);
+        }
+      }
+    }
+
+    chunk
+  }
+
+  /// Returns where players first appear in the world: on top of the surface
+  /// block at the origin, or of the water above it.
+  pub fn spawn_position(&self) -> Vec3 {
+    let surface_height: f32 = self.surface_height(0, 0).max(SEA_LEVEL).coerce_lossy();
+    Vec3::new(0.0, surface_height + 0.5, 0.0)
+  }
+}
+
+/// Returns the chunks missing from `world` within `radius` chunks horizontally
+/// and [`GENERATION_HEIGHT`] chunks vertically of `center`, nearest first.
+pub fn missing_chunks(world: &World, center: ChunkPos, radius: i32) -> Vec<ChunkPos> {
+  let mut missing = Vec::new();
+  for x in -radius..=radius {
+    for y in -GENERATION_HEIGHT..=GENERATION_HEIGHT {
+      for z in -radius..=radius {
+        let pos = center.offset(x, y, z);
+
+        if world.chunk(pos).is_none() {
+          missing.push((x * x + y * y + z * z, pos));
+        }
+      }
+    }
+  }
+  missing.sort_unstable_by_key(|&(dist_sq, _)| dist_sq);
+
+  missing.into_iter().map(|(_, pos)| pos).collect()
+}
+
+/// Returns whether the chunk at `pos` lies more than `margin` chunks beyond
+/// those loaded within `radius` chunks horizontally of `center`.
+pub fn is_distant(center: ChunkPos, pos: ChunkPos, radius: i32, margin: i32) -> bool {
+  let (radius, height) = (radius + margin, GENERATION_HEIGHT + margin);
+
+  ((pos.x() - center.x()).abs() > radius)
+    || ((pos.y() - center.y()).abs() > height)
+    || ((pos.z() - center.z()).abs() > radius)
+}
+
+/// Places the parts of a tree rooted at `root` which lie within the chunk at
+/// `pos`.
+fn place_tree(chunk: &mut Chunk, pos: ChunkPos, root: BlockPos, trunk_height: i32) {
+  let mut place = |block_pos: BlockPos, block: Block| {
+    // Leaves fill the space around trunks, but never replace them
+    if (block_pos.chunk() == pos)
+      && ((block == Block::Wood) || chunk.get(block_pos.local()).is_air())
+    {
+      chunk.set(block_pos.local(), block);
+    }
+  };
+
+  for y in 0..trunk_height {
+    place(root.offset(0, y, 0), Block::Wood);
+  }
+
+  // Two wide layers around the top of the trunk, then two narrow ones above
+  for y in (trunk_height - 2)..(trunk_height + 2) {
+    let radius = if y < trunk_height { CANOPY_RADIUS } else { 1 };
+
+    for x in -radius..=radius {
+      for z in -radius..=radius {
+        let corner = (x.abs() == radius) && (z.abs() == radius);
+
+        if !corner || (radius == CANOPY_RADIUS && y == trunk_height - 2) {
+          place(root.offset(x, y, z), Block::Leaves);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::world::chunk::CHUNK_VOLUME;
+  use crate::world::position::LocalPos;
+
+  /// FNV-1a over every block ID in the chunk, which unlike the standard
+  /// library's hashers is guaranteed to be stable.
+  fn chunk_hash(chunk: &Chunk) -> u64 {
+    (0..CHUNK_VOLUME).fold(0xCBF2_9CE4_8422_2325, |hash, index| {
+      let id = chunk.get(LocalPos::from_index(index)).id();
+      (hash ^ u64::from(id)).wrapping_mul(0x0100_0000_01B3)
+    })
+  }
+
+  const CHUNKS: [ChunkPos; 4] = [
+    ChunkPos::new(0, 0, 0),
+    ChunkPos::new(0, -1, 0),
+    ChunkPos::new(-3, -2, 5),
+    ChunkPos::new(7, 0, -4),
+  ];
+
+  // Check that the same seed always generates the same chunks, and that
+  // different seeds generate different ones.
+  #[test]
+  fn test_generation_is_deterministic() {
+    let first = TerrainGenerator::new(1234);
+    let second = TerrainGenerator::new(1234);
+    let other = TerrainGenerator::new(4321);
+
+    for pos in CHUNKS {
+      assert_eq!(
+        chunk_hash(&first.generate(pos)),
+        chunk_hash(&second.generate(pos))
+      );
+    }
+
+    assert!(
+      CHUNKS
+        .iter()
+        .any(|&pos| chunk_hash(&first.generate(pos)) != chunk_hash(&other.generate(pos)))
+    );
+  }
+
+  // Check that generation hasn't changed, since that would alter every
+  // existing world created from a seed.
+  #[test]
+  fn test_generated_chunk_hashes() {
+    let generator = TerrainGenerator::new(1234);
+    let hashes: Vec<u64> = CHUNKS
+      .iter()
+      .map(|&pos| chunk_hash(&generator.generate(pos)))
+      .collect();
+
+    assert_eq!(
+      vec![
+        5_992_120_398_593_034_679,
+        4_879_659_061_042_906_176,
+        7_141_953_996_005_509_556,
+        10_801_692_848_289_149_189,
+      ],
+      hashes
+    );
+  }
+
+  // Check that each column is its biome's surface block, then subsurface
+  // blocks, then stone.
+  #[test]
+  fn test_surface_layering() {
+    let generator = TerrainGenerator::new(99);
+
+    for (x, z) in [(0, 0), (15, -30), (-100, 7), (640, 480), (-2000, 1500)] {
+      let column = generator.column(x, z);
+      let (surface, subsurface) = column.palette();
+      let surface_pos = BlockPos::new(x, column.surface_height, z);
+      let block_at = |pos: BlockPos| generator.generate(pos.chunk()).get(pos.local());
+
+      assert_eq!(surface, block_at(surface_pos));
+      assert_eq!(subsurface, block_at(surface_pos.offset(0, -DIRT_DEPTH, 0)));
+      assert_eq!(
+        Block::Stone,
+        block_at(surface_pos.offset(0, -(DIRT_DEPTH + 1), 0))
+      );
+    }
+  }
+
+  // Check that low-lying columns are flooded up to sea level.
+  #[test]
+  fn test_water_fills_to_sea_level() {
+    let generator = TerrainGenerator::new(99);
+
+    let (x, z) = (-512..512)
+      .flat_map(|x| (-512..512).map(move |z| (x * 8, z * 8)))
+      .find(|&(x, z)| generator.surface_height(x, z) < SEA_LEVEL - 1)
+      .unwrap();
+    let block_at = |y: i32| {
+      let pos = BlockPos::new(x, y, z);
+      generator.generate(pos.chunk()).get(pos.local())
+    };
+
+    assert_eq!(Block::Water, block_at(SEA_LEVEL));
+    assert_eq!(Block::Water, block_at(generator.surface_height(x, z) + 1));
+    assert_eq!(Block::Air, block_at(SEA_LEVEL + 1));
+  }
+}
@@ -0,0 +1,225 @@
+use crate::core::type_conversions::Coerce;
+use crate::world::block::Block;
+
+/// Number of slots in the hotbar, which are the first slots of the inventory.
+pub const HOTBAR_SLOTS: usize = 9;
+pub const INVENTORY_SLOTS: usize = 36;
+/// Most blocks which fit in one slot.
+pub const MAX_STACK_SIZE: u8 = 64;
+
+/// A number of blocks of the same type, held in one slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemStack {
+  pub block: Block,
+  pub count: u8,
+}
+
+/// The blocks a player carries, with one slot of the hotbar selected for
+/// placing blocks from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inventory {
+  slots: [Option<ItemStack>; INVENTORY_SLOTS],
+  selected: usize,
+}
+
+impl Default for Inventory {
+  fn default() -> Self {
+    Self {
+      slots: [None; INVENTORY_SLOTS],
+      selected: 0,
+    }
+  }
+}
+
+impl Inventory {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn slots(&self) -> &[Option<ItemStack>; INVENTORY_SLOTS] {
+    &self.slots
+  }
+
+  pub fn hotbar(&self) -> &[Option<ItemStack>] {
+    &self.slots[..HOTBAR_SLOTS]
+  }
+
+  pub fn selected_slot(&self) -> usize {
+    self.selected
+  }
+
+  /// Returns the stack in the selected hotbar slot.
+  pub fn selected(&self) -> Option<ItemStack> {
+    self.slots[self.selected]
+  }
+
+  pub fn select(&mut self, slot: usize) {
+    assert!(slot < HOTBAR_SLOTS, "hotbar slot {slot} out of range");
+
+    self.selected = slot;
+  }
+
+  /// Moves the selection `steps` slots to the right, or to the left if
+  /// negative, wrapping around the ends of the hotbar.
+  pub fn scroll(&mut self, steps: i32) {
+    let slots: i32 = HOTBAR_SLOTS.coerce();
+    let selected: i32 = self.selected.coerce();
+
+    self.selected = (selected + steps).rem_euclid(slots).coerce();
+  }
+
+  /// Adds one `block`, to a stack of the same blocks if there's room in one or
+  /// else to the first empty slot. Returns false if the inventory is full, or
+  /// for air, which can't be carried.
+  pub fn add(&mut self, block: Block) -> bool {
+    if block.is_air() {
+      return false;
+    }
+
+    let stack = self
+      .slots
+      .iter()
+      .position(|slot| {
+        slot.is_some_and(|stack| (stack.block == block) && (stack.count < MAX_STACK_SIZE))
+      })
+      .or_else(|| self.slots.iter().position(Option::is_none));
+
+    match stack {
+      Some(index) => {
+        let slot = &mut self.slots[index];
+        *slot = Some(ItemStack {
+          block,
+          count: slot.map_or(0, |stack| stack.count) + 1,
+        });
+
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Returns whether any slot holds `block`.
+  pub fn contains(&self, block: Block) -> bool {
+    self
+      .slots
+      .iter()
+      .any(|slot| slot.is_some_and(|stack| stack.block == block))
+  }
+
+  /// Removes one `block`, from the selected stack if it holds them or else from
+  /// the last stack which does. Returns false if there are none.
+  pub fn take(&mut self, block: Block) -> bool {
+    let holds_block = |slot: &Option<ItemStack>| slot.is_some_and(|stack| stack.block == block);
+    let index = if holds_block(&self.slots[self.selected]) {
+      Some(self.selected)
+    } else {
+      self.slots.iter().rposition(holds_block)
+    };
+    let Some(index) = index else {
+      return false;
+    };
+
+    let slot = &mut self.slots[index];
+    if let Some(stack) = slot.as_mut() {
+      stack.count -= 1;
+      if stack.count == 0 {
+        *slot = None;
+      }
+    }
+
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Check that blocks are stacked together until a stack is full.
+  #[test]
+  fn test_add_stacks_blocks() {
+    let mut inventory = Inventory::new();
+
+    for _ in 0..(MAX_STACK_SIZE + 1) {
+      assert!(inventory.add(Block::Stone));
+    }
+    assert!(inventory.add(Block::Sand));
+
+    assert_eq!(
+      [
+        Some(ItemStack {
+          block: Block::Stone,
+          count: MAX_STACK_SIZE,
+        }),
+        Some(ItemStack {
+          block: Block::Stone,
+          count: 1,
+        }),
+        Some(ItemStack {
+          block: Block::Sand,
+          count: 1,
+        }),
+        None,
+      ],
+      inventory.hotbar()[..4]
+    );
+  }
+
+  // Check that blocks are refused once every slot is full.
+  #[test]
+  fn test_add_to_full_inventory() {
+    let mut inventory = Inventory::new();
+    for _ in 0..(INVENTORY_SLOTS * usize::from(MAX_STACK_SIZE)) {
+      assert!(inventory.add(Block::Dirt));
+    }
+
+    assert!(!inventory.add(Block::Dirt));
+    assert!(!inventory.add(Block::Glass));
+  }
+
+  // Check that air is never added to the inventory.
+  #[test]
+  fn test_add_air_refused() {
+    let mut inventory = Inventory::new();
+
+    assert!(!inventory.add(Block::Air));
+    assert_eq!(Inventory::new(), inventory);
+  }
+
+  // Check that blocks are taken from the selected slot first, one at a time,
+  // and then from the other slots holding them, until none are left.
+  #[test]
+  fn test_take() {
+    let mut inventory = Inventory::new();
+    inventory.add(Block::Wood);
+    inventory.add(Block::Snow);
+    inventory.add(Block::Snow);
+    inventory.select(1);
+
+    assert!(inventory.contains(Block::Wood));
+    assert!(inventory.take(Block::Wood));
+    assert!(!inventory.contains(Block::Wood));
+    assert!(!inventory.take(Block::Wood));
+    assert!(inventory.take(Block::Snow));
+    assert_eq!(
+      Some(Block::Snow),
+      inventory.selected().map(|stack| stack.block)
+    );
+    assert!(inventory.take(Block::Snow));
+    assert_eq!(None, inventory.selected());
+    assert!(!inventory.take(Block::Snow));
+  }
+
+  // Check that scrolling wraps around both ends of the hotbar.
+  #[test]
+  fn test_scroll_wraps() {
+    let mut inventory = Inventory::new();
+
+    inventory.scroll(-1);
+    assert_eq!(HOTBAR_SLOTS - 1, inventory.selected_slot());
+    inventory.scroll(2);
+    assert_eq!(1, inventory.selected_slot());
+    inventory.scroll(-10);
+    assert_eq!(0, inventory.selected_slot());
+  }
+}
@@ -0,0 +1,576 @@
+//! Sky light and block light, which spread from their sources through
+//! transparent blocks, losing a level with each block travelled.
+//!
+//! Sky light enters the top of chunks which have no chunk loaded above them,
+//! in the columns where the terrain generated above would leave the sky open,
+//! and travels straight down without weakening until it meets an opaque block.
+//! Block light is given off by emissive blocks such as torches. Both are flood
+//! filled when a chunk loads, and updated incrementally as blocks change.
+
+use crate::core::math::aligned_box3::BoxFace;
+use crate::world::World;
+use crate::world::chunk::{CHUNK_SIZE, CHUNK_VOLUME, Chunk};
+use crate::world::position::{BlockPos, ChunkPos, LocalPos};
+use std::collections::VecDeque;
+
+/// Brightest light level, of open sky or the brightest emissive blocks.
+pub const MAX_LIGHT: u8 = 15;
+
+/// The sky light and block light levels at a block.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Light {
+  sky: u8,
+  block: u8,
+}
+
+impl Light {
+  /// Light under the open sky, away from any emissive blocks.
+  pub const SKY: Light = Light::new(MAX_LIGHT, 0);
+
+  pub const fn new(sky: u8, block: u8) -> Self {
+    Self { sky, block }
+  }
+
+  pub const fn sky(self) -> u8 {
+    self.sky
+  }
+
+  pub const fn block(self) -> u8 {
+    self.block
+  }
+
+  fn level(self, channel: Channel) -> u8 {
+    match channel {
+      Channel::Sky => self.sky,
+      Channel::Block => self.block,
+    }
+  }
+
+  fn set_level(&mut self, channel: Channel, level: u8) {
+    match channel {
+      Channel::Sky => self.sky = level,
+      Channel::Block => self.block = level,
+    }
+  }
+}
+
+/// The light levels of every block in a chunk, indexed by [`LocalPos::index`].
+pub(super) type ChunkLight = Box<[Light; CHUNK_VOLUME]>;
+
+/// Light levels spread independently, but in the same way, through each
+/// channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Channel {
+  Sky,
+  Block,
+}
+
+impl Channel {
+  const ALL: [Channel; 2] = [Channel::Sky, Channel::Block];
+
+  /// Returns the level which light at `level` reaches the neighbour through
+  /// `face` with.
+  fn spread(self, level: u8, face: BoxFace) -> u8 {
+    if (self == Channel::Sky) && (face == BoxFace::Bottom) && (level == MAX_LIGHT) {
+      MAX_LIGHT
+    } else {
+      level.saturating_sub(1)
+    }
+  }
+}
+
+/// Light queued to spread from each block, for each channel.
+type LightQueues = [VecDeque<BlockPos>; 2];
+
+/// Iterates over the blocks of the chunk at `pos` which lie against its
+/// `face`.
+fn face_blocks(pos: ChunkPos, face: BoxFace) -> impl Iterator<Item = BlockPos> {
+  let origin = pos.origin();
+  let last = CHUNK_SIZE - 1;
+
+  (0..CHUNK_SIZE).flat_map(move |u| {
+    (0..CHUNK_SIZE).map(move |v| {
+      let (x, y, z) = match face {
+        BoxFace::Left => (last, u, v),
+        BoxFace::Right => (0, u, v),
+        BoxFace::Top => (u, last, v),
+        BoxFace::Bottom => (u, 0, v),
+        BoxFace::Back => (u, v, last),
+        BoxFace::Front => (u, v, 0),
+      };
+
+      origin.offset(x, y, z)
+    })
+  })
+}
+
+/// Spreads light outwards from each block in `queue`, like
+/// [`World::spread_light`], but only within `chunk`.
+fn spread_within_chunk(
+  chunk: &Chunk,
+  light: &mut ChunkLight,
+  channel: Channel,
+  mut queue: VecDeque<LocalPos>,
+) {
+  while let Some(local) = queue.pop_front() {
+    let level = light[local.index()].level(channel);
+    if level <= 1 {
+      continue;
+    }
+
+    for face in BoxFace::ALL {
+      let Some(neighbour) = local.adjacent(face) else {
+        continue;
+      };
+      let reached = channel.spread(level, face);
+
+      let neighbour_light = &mut light[neighbour.index()];
+      if (neighbour_light.level(channel) < reached) && chunk.get(neighbour).is_transparent() {
+        neighbour_light.set_level(channel, reached);
+        queue.push_back(neighbour);
+      }
+    }
+  }
+}
+
+impl World {
+  /// Returns the light at `pos`, which is open sky outside the loaded chunks.
+  pub fn light(&self, pos: BlockPos) -> Light {
+    self
+      .light
+      .get(&pos.chunk())
+      .map_or(Light::SKY, |light| light[pos.local().index()])
+  }
+
+  /// Returns the light level at `pos`, or [`None`] if its chunk isn't loaded.
+  fn light_level(&self, pos: BlockPos, channel: Channel) -> Option<u8> {
+    self
+      .light
+      .get(&pos.chunk())
+      .map(|light| light[pos.local().index()].level(channel))
+  }
+
+  fn set_light_level(&mut self, pos: BlockPos, channel: Channel, level: u8) {
+    if let Some(light) = self.light.get_mut(&pos.chunk()) {
+      light[pos.local().index()].set_level(channel, level);
+      self.mark_dirty(pos);
+    }
+  }
+
+  /// Returns true if sky light falls into `pos` from the unloaded chunk above
+  /// it, which it only does if the terrain generated there leaves the column
+  /// open to the sky.
+  fn is_open_to_sky(&self, pos: BlockPos) -> bool {
+    let above = pos.adjacent(BoxFace::Top);
+
+    !self.chunks.contains_key(&above.chunk())
+      && self
+        .terrain
+        .is_none_or(|terrain| terrain.surface_height(pos.x(), pos.z()) < above.y())
+  }
+
+  /// Returns the light level given off at `pos` regardless of its
+  /// surroundings.
+  fn source_level(&self, pos: BlockPos, channel: Channel) -> u8 {
+    let block = self.get(pos);
+
+    match channel {
+      Channel::Sky => {
+        if block.is_transparent() && self.is_open_to_sky(pos) {
+          MAX_LIGHT
+        } else {
+          0
+        }
+      }
+      Channel::Block => block.emission(),
+    }
+  }
+
+  /// Lights the chunk just loaded at `pos`, letting light in from its
+  /// neighbours and out into them, and taking away the sky light it now covers
+  /// from the chunk below it.
+  pub(super) fn light_new_chunk(&mut self, pos: ChunkPos) {
+    let chunk = &self.chunks[&pos];
+    let mut light: ChunkLight = Box::new([Light::default(); CHUNK_VOLUME]);
+    let mut queues: [VecDeque<LocalPos>; 2] = Default::default();
+
+    for (index, &block) in chunk.blocks_by_index().iter().enumerate() {
+      let local = LocalPos::from_index(index);
+
+      if local.adjacent(BoxFace::Top).is_none()
+        && block.is_transparent()
+        && self.is_open_to_sky(BlockPos::from_chunk_local(pos, local))
+      {
+        light[index].sky = MAX_LIGHT;
+        queues[0].push_back(local);
+      }
+      if block.emission() > 0 {
+        light[index].block = block.emission();
+        queues[1].push_back(local);
+      }
+    }
+
+    // Light shining in from the neighbouring chunks
+    for face in BoxFace::ALL {
+      let Some(neighbour_light) = self.light.get(&pos.adjacent(face)) else {
+        continue;
+      };
+
+      for block_pos in face_blocks(pos, face) {
+        let local = block_pos.local();
+        if !chunk.get(local).is_transparent() {
+          continue;
+        }
+
+        let outside = neighbour_light[block_pos.adjacent(face).local().index()];
+        for (channel, queue) in Channel::ALL.into_iter().zip(&mut queues) {
+          let reached = channel.spread(outside.level(channel), face.opposite());
+
+          if light[local.index()].level(channel) < reached {
+            light[local.index()].set_level(channel, reached);
+            queue.push_back(local);
+          }
+        }
+      }
+    }
+
+    for (channel, queue) in Channel::ALL.into_iter().zip(queues) {
+      spread_within_chunk(chunk, &mut light, channel, queue);
+    }
+    self.light.insert(pos, light);
+
+    // Light shining out into the neighbouring chunks
+    for channel in Channel::ALL {
+      let queue = BoxFace::ALL
+        .into_iter()
+        .filter(|&face| self.light.contains_key(&pos.adjacent(face)))
+        .flat_map(|face| face_blocks(pos, face))
+        .collect();
+      self.spread_light(channel, queue);
+    }
+
+    // The chunk below was lit as if it was under the open sky
+    let below = pos.adjacent(BoxFace::Bottom);
+    if self.light.contains_key(&below) {
+      let covered: Vec<(BlockPos, u8)> = face_blocks(below, BoxFace::Top)
+        .filter(|&block_pos| {
+          (self.light_level(block_pos, Channel::Sky) == Some(MAX_LIGHT))
+            && (self.light_level(block_pos.adjacent(BoxFace::Top), Channel::Sky) != Some(MAX_LIGHT))
+        })
+        .map(|block_pos| (block_pos, MAX_LIGHT))
+        .collect();
+
+      for &(block_pos, _) in &covered {
+        self.set_light_level(block_pos, Channel::Sky, 0);
+      }
+      let relight = self.remove_light(Channel::Sky, covered);
+      self.spread_light(Channel::Sky, relight);
+    }
+  }
+
+  /// Takes away all light in the chunk at `pos` and the light which spread
+  /// from it, before the chunk is replaced.
+  ///
+  /// Returns the lit blocks around the darkened ones, whose light must be
+  /// spread again once the chunk has been replaced.
+  pub(super) fn darken_chunk(&mut self, pos: ChunkPos) -> LightQueues {
+    let mut relight = LightQueues::default();
+
+    for (channel, queue) in Channel::ALL.into_iter().zip(&mut relight) {
+      let mut removed = Vec::new();
+      for index in 0..CHUNK_VOLUME {
+        let block_pos = BlockPos::from_chunk_local(pos, LocalPos::from_index(index));
+
+        if let Some(level) = self.light_level(block_pos, channel)
+          && level > 0
+        {
+          self.set_light_level(block_pos, channel, 0);
+          removed.push((block_pos, level));
+        }
+      }
+
+      *queue = self.remove_light(channel, removed);
+    }
+
+    relight
+  }
+
+  /// Lets the sky light back into the chunk at `pos` once the chunk above it
+  /// has been unloaded, where the terrain above leaves the sky open.
+  pub(super) fn uncover_chunk(&mut self, pos: ChunkPos) {
+    if !self.light.contains_key(&pos) {
+      return;
+    }
+
+    let uncovered: VecDeque<BlockPos> = face_blocks(pos, BoxFace::Top)
+      .filter(|&block_pos| {
+        (self.light_level(block_pos, Channel::Sky) != Some(MAX_LIGHT))
+          && (self.source_level(block_pos, Channel::Sky) == MAX_LIGHT)
+      })
+      .collect();
+    for &block_pos in &uncovered {
+      self.set_light_level(block_pos, Channel::Sky, MAX_LIGHT);
+    }
+    self.spread_light(Channel::Sky, uncovered);
+  }
+
+  /// Spreads the light queued by [`World::darken_chunk`].
+  pub(super) fn relight(&mut self, queues: LightQueues) {
+    for (channel, queue) in Channel::ALL.into_iter().zip(queues) {
+      self.spread_light(channel, queue);
+    }
+  }
+
+  /// Updates the light around `pos` after its block was replaced by one which
+  /// lets through or gives off a different amount of light.
+  pub(super) fn relight_block(&mut self, pos: BlockPos) {
+    for channel in Channel::ALL {
+      let Some(level) = self.light_level(pos, channel) else {
+        return;
+      };
+
+      self.set_light_level(pos, channel, 0);
+      let mut queue = self.remove_light(channel, vec![(pos, level)]);
+
+      // Light flows back in from the neighbours if the block lets it through
+      queue.extend(BoxFace::ALL.map(|face| pos.adjacent(face)));
+
+      let source = self.source_level(pos, channel);
+      if source > 0 {
+        self.set_light_level(pos, channel, source);
+        queue.push_back(pos);
+      }
+
+      self.spread_light(channel, queue);
+    }
+  }
+
+  /// Spreads light outwards from each block in `queue`, brightening the
+  /// transparent blocks which it reaches at a higher level than they have.
+  fn spread_light(&mut self, channel: Channel, mut queue: VecDeque<BlockPos>) {
+    while let Some(pos) = queue.pop_front() {
+      // Light at level 1 doesn't reach any further
+      let Some(level) = self.light_level(pos, channel).filter(|&level| level > 1) else {
+        continue;
+      };
+
+      for face in BoxFace::ALL {
+        let neighbour = pos.adjacent(face);
+        let reached = channel.spread(level, face);
+
+        let chunk_pos = neighbour.chunk();
+        let (Some(chunk), Some(light)) =
+          (self.chunks.get(&chunk_pos), self.light.get_mut(&chunk_pos))
+        else {
+          continue;
+        };
+
+        let local = neighbour.local();
+        let light = &mut light[local.index()];
+        if (light.level(channel) < reached) && chunk.get(local).is_transparent() {
+          light.set_level(channel, reached);
+          self.mark_dirty(neighbour);
+          queue.push_back(neighbour);
+        }
+      }
+    }
+  }
+
+  /// Darkens every block which may have been lit by the blocks in `removed`,
+  /// which have already been darkened from the given levels.
+  ///
+  /// Returns the lit blocks bordering the darkened ones, and any light sources
+  /// among them, from which light must be spread again.
+  fn remove_light(&mut self, channel: Channel, removed: Vec<(BlockPos, u8)>) -> VecDeque<BlockPos> {
+    let mut queue = VecDeque::from(removed);
+    let mut relight = VecDeque::new();
+
+    while let Some((pos, level)) = queue.pop_front() {
+      for face in BoxFace::ALL {
+        let neighbour = pos.adjacent(face);
+        let Some(current) = self.light_level(neighbour, channel) else {
+          continue;
+        };
+        if current == 0 {
+          continue;
+        }
+
+        // Sources at least as bright keep their light, which can't have come
+        // from the removed light
+        let source = self.source_level(neighbour, channel);
+        if (current <= channel.spread(level, face)) && (source < current) {
+          self.set_light_level(neighbour, channel, 0);
+          queue.push_back((neighbour, current));
+
+          if source > 0 {
+            self.set_light_level(neighbour, channel, source);
+            relight.push_back(neighbour);
+          }
+        } else {
+          relight.push_back(neighbour);
+        }
+      }
+    }
+
+    relight
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::world::block::Block;
+  use crate::world::generation::TerrainGenerator;
+
+  // Check that sky light falls undimmed to the ground and fades under an
+  // overhang, while a torch's light fades by one per block.
+  #[test]
+  fn test_sky_and_block_light_spread() {
+    let mut world = World::new();
+    world.insert_chunk(ChunkPos::new(0, 0, 0), Chunk::default());
+    for x in 0..CHUNK_SIZE {
+      for z in 0..CHUNK_SIZE {
+        world.set(BlockPos::new(x, 0, z), Block::Stone);
+      }
+    }
+    for x in 0..4 {
+      for z in 0..4 {
+        world.set(BlockPos::new(x, 5, z), Block::Stone);
+      }
+    }
+    world.set(BlockPos::new(12, 1, 12), Block::Torch);
+
+    assert_eq!(MAX_LIGHT, world.light(BlockPos::new(8, 1, 8)).sky());
+    assert_eq!(MAX_LIGHT - 1, world.light(BlockPos::new(3, 4, 3)).sky());
+    assert_eq!(MAX_LIGHT - 2, world.light(BlockPos::new(2, 4, 2)).sky());
+    assert_eq!(0, world.light(BlockPos::new(8, 0, 8)).sky());
+
+    assert_eq!(MAX_LIGHT - 1, world.light(BlockPos::new(12, 1, 12)).block());
+    assert_eq!(MAX_LIGHT - 5, world.light(BlockPos::new(12, 2, 9)).block());
+  }
+
+  /// Returns a chunk below the terrain generated by `terrain` in the column of
+  /// chunks at the origin, and a chunk above it.
+  fn buried_and_exposed_chunks(terrain: &TerrainGenerator) -> (ChunkPos, ChunkPos) {
+    let heights: Vec<i32> = (0..CHUNK_SIZE)
+      .flat_map(|x| (0..CHUNK_SIZE).map(move |z| terrain.surface_height(x, z)))
+      .collect();
+    let lowest = heights.iter().min().unwrap().div_euclid(CHUNK_SIZE);
+    let highest = heights.iter().max().unwrap().div_euclid(CHUNK_SIZE);
+
+    (
+      ChunkPos::new(0, lowest - 3, 0),
+      ChunkPos::new(0, highest + 1, 0),
+    )
+  }
+
+  // Check that sky light only falls into chunks which the terrain generated
+  // above them leaves open to the sky, however few chunks are loaded.
+  #[test]
+  fn test_sky_light_follows_terrain() {
+    let terrain = TerrainGenerator::new(0);
+    let (buried, exposed) = buried_and_exposed_chunks(&terrain);
+
+    let mut world = World::with_terrain(terrain);
+    for pos in [buried, exposed] {
+      world.insert_chunk(pos, Chunk::default());
+    }
+
+    for index in 0..CHUNK_VOLUME {
+      let local = LocalPos::from_index(index);
+      let buried_pos = BlockPos::from_chunk_local(buried, local);
+      let exposed_pos = BlockPos::from_chunk_local(exposed, local);
+
+      assert_eq!(0, world.light(buried_pos).sky(), "{buried_pos:?}");
+      assert_eq!(MAX_LIGHT, world.light(exposed_pos).sky(), "{exposed_pos:?}");
+    }
+  }
+
+  // Check that breaking and placing blocks leaves the same light as lighting
+  // the resulting chunks from scratch, including when a chunk loads above
+  // one lit by the open sky.
+  #[test]
+  fn test_incremental_matches_full_lighting() {
+    let lower = ChunkPos::new(0, 0, 0);
+    let upper = ChunkPos::new(0, 1, 0);
+
+    let mut roof = Chunk::default();
+    for x in 0..CHUNK_SIZE {
+      for z in 0..CHUNK_SIZE {
+        roof.set(BlockPos::new(x, 0, z).local(), Block::Stone);
+      }
+    }
+
+    let mut world = World::new();
+    world.insert_chunk(lower, Chunk::default());
+    world.set(BlockPos::new(5, 3, 5), Block::Lava);
+    world.insert_chunk(upper, roof);
+    world.set(BlockPos::new(7, CHUNK_SIZE, 7), Block::Air);
+    world.set(BlockPos::new(7, 4, 7), Block::Glass);
+    world.set(BlockPos::new(7, 3, 7), Block::Stone);
+    world.set(BlockPos::new(5, 3, 5), Block::Air);
+    world.set(BlockPos::new(12, 2, 12), Block::Torch);
+
+    let mut fresh = World::new();
+    for pos in [upper, lower] {
+      fresh.insert_chunk(pos, world.chunk(pos).unwrap().clone());
+    }
+
+    for (pos, _) in world.chunks() {
+      for index in 0..CHUNK_VOLUME {
+        let block_pos = BlockPos::from_chunk_local(pos, LocalPos::from_index(index));
+        assert_eq!(
+          fresh.light(block_pos),
+          world.light(block_pos),
+          "{block_pos:?}"
+        );
+      }
+    }
+    assert_eq!(MAX_LIGHT, world.light(BlockPos::new(7, 4, 7)).sky());
+    assert_eq!(0, world.light(BlockPos::new(5, 3, 5)).block());
+  }
+
+  // Check that unloading a chunk leaves the chunk below lit as if the
+  // unloaded chunk had never been loaded: under the open sky again above the
+  // terrain, but still dark beneath it.
+  #[test]
+  fn test_unloading_matches_never_loaded() {
+    let terrain = TerrainGenerator::new(0);
+    let (buried, exposed) = buried_and_exposed_chunks(&terrain);
+
+    let mut roof = Chunk::default();
+    for x in 0..CHUNK_SIZE {
+      for z in 0..CHUNK_SIZE {
+        roof.set(BlockPos::new(x, 1, z).local(), Block::Stone);
+      }
+    }
+    roof.set(BlockPos::new(4, 0, 4).local(), Block::Lava);
+
+    let mut world = World::with_terrain(terrain);
+    let mut fresh = World::with_terrain(terrain);
+    for lower in [buried, exposed] {
+      let upper = lower.adjacent(BoxFace::Top);
+
+      world.insert_chunk(lower, Chunk::default());
+      world.set(lower.origin().offset(12, 2, 12), Block::Torch);
+      world.insert_chunk(upper, roof.clone());
+      world.remove_chunk(upper);
+      fresh.insert_chunk(lower, world.chunk(lower).unwrap().clone());
+
+      for index in 0..CHUNK_VOLUME {
+        let block_pos = BlockPos::from_chunk_local(lower, LocalPos::from_index(index));
+        assert_eq!(
+          fresh.light(block_pos),
+          world.light(block_pos),
+          "{block_pos:?}"
+        );
+      }
+    }
+
+    assert_eq!(0, world.light(buried.origin().offset(4, 3, 4)).sky());
+    assert_eq!(
+      MAX_LIGHT,
+      world.light(exposed.origin().offset(4, 3, 4)).sky()
+    );
+  }
+}
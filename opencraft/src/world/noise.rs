@@ -0,0 +1,166 @@
+use crate::core::type_conversions::CoerceLossy;
+
+/// Mixes a seed and lattice coordinates into a well-distributed hash.
+///
+/// This is the SplitMix64 finaliser applied to each coordinate in turn, so it
+/// is cheap, has no state, and gives the same result on every platform.
+pub fn hash(seed: u64, x: i32, y: i32, z: i32) -> u64 {
+  fn mix(mut value: u64) -> u64 {
+    value = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+  }
+
+  [x, y, z].into_iter().fold(mix(seed), |hash, coordinate| {
+    mix(hash ^ u64::from(coordinate.cast_unsigned()))
+  })
+}
+
+/// Returns a pseudo-random value in [0, 1) for a point.
+pub fn unit(seed: u64, x: i32, y: i32, z: i32) -> f32 {
+  const UNIT: f32 = (1 << 24) as f32;
+
+  // The top 24 bits fit exactly in an `f32` mantissa
+  let value: f32 = (hash(seed, x, y, z) >> 40).coerce_lossy();
+  value / UNIT
+}
+
+/// Returns the pseudo-random value in [-1, 1] at a lattice point.
+fn lattice(seed: u64, x: i32, y: i32, z: i32) -> f32 {
+  (unit(seed, x, y, z) * 2.0) - 1.0
+}
+
+/// Eases `t` ∈ [0, 1] so that noise has no creases at lattice boundaries.
+pub fn smooth(t: f32) -> f32 {
+  t * t * (3.0 - (2.0 * t))
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+  a + ((b - a) * t)
+}
+
+/// Splits a coordinate into its lattice cell and eased position in the cell.
+fn cell(value: f32) -> (i32, f32) {
+  let floor = value.floor();
+  (floor.coerce_lossy(), smooth(value - floor))
+}
+
+/// Smoothly interpolated value noise in [-1, 1], with a lattice spacing of 1.
+pub fn value_2d(seed: u64, x: f32, z: f32) -> f32 {
+  let (x0, tx) = cell(x);
+  let (z0, tz) = cell(z);
+
+  let near = lerp(lattice(seed, x0, 0, z0), lattice(seed, x0 + 1, 0, z0), tx);
+  let far = lerp(
+    lattice(seed, x0, 0, z0 + 1),
+    lattice(seed, x0 + 1, 0, z0 + 1),
+    tx,
+  );
+
+  lerp(near, far, tz)
+}
+
+/// Smoothly interpolated value noise in [-1, 1], with a lattice spacing of 1.
+pub fn value_3d(seed: u64, x: f32, y: f32, z: f32) -> f32 {
+  let (x0, tx) = cell(x);
+  let (y0, ty) = cell(y);
+  let (z0, tz) = cell(z);
+
+  let layer = |y: i32| {
+    let near = lerp(lattice(seed, x0, y, z0), lattice(seed, x0 + 1, y, z0), tx);
+    let far = lerp(
+      lattice(seed, x0, y, z0 + 1),
+      lattice(seed, x0 + 1, y, z0 + 1),
+      tx,
+    );
+
+    lerp(near, far, tz)
+  };
+
+  lerp(layer(y0), layer(y0 + 1), ty)
+}
+
+/// Several octaves of value noise summed together, each at a higher frequency
+/// and lower amplitude than the last, normalised back to [-1, 1].
+#[derive(Debug, Clone, Copy)]
+pub struct Fractal {
+  pub octaves: u32,
+  /// Frequency of the first octave, in lattice cells per block.
+  pub frequency: f32,
+  /// Amplitude of each octave relative to the previous one.
+  pub persistence: f32,
+}
+
+impl Fractal {
+  const LACUNARITY: f32 = 2.0;
+
+  fn sum(&self, mut sample: impl FnMut(u32, f32) -> f32) -> f32 {
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut max_amplitude = 0.0;
+    let mut frequency = self.frequency;
+
+    for octave in 0..self.octaves {
+      total += amplitude * sample(octave, frequency);
+      max_amplitude += amplitude;
+      amplitude *= self.persistence;
+      frequency *= Self::LACUNARITY;
+    }
+
+    total / max_amplitude
+  }
+
+  /// Each octave is decorrelated from the others by its own seed.
+  fn octave_seed(seed: u64, octave: u32) -> u64 {
+    hash(seed, octave.cast_signed(), 0, 0)
+  }
+
+  pub fn sample_2d(&self, seed: u64, x: f32, z: f32) -> f32 {
+    self.sum(|octave, frequency| {
+      value_2d(
+        Self::octave_seed(seed, octave),
+        x * frequency,
+        z * frequency,
+      )
+    })
+  }
+
+  pub fn sample_3d(&self, seed: u64, x: f32, y: f32, z: f32) -> f32 {
+    self.sum(|octave, frequency| {
+      value_3d(
+        Self::octave_seed(seed, octave),
+        x * frequency,
+        y * frequency,
+        z * frequency,
+      )
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Check that noise stays in range and matches the lattice values at lattice
+  // points, so neighbouring cells meet without seams.
+  #[test]
+  fn test_value_noise_range_and_lattice() {
+    const SEED: u64 = 42;
+
+    for x in -20..20 {
+      for z in -20..20 {
+        let (x, z): (f32, f32) = (x.coerce_lossy(), z.coerce_lossy());
+        let value = value_2d(SEED, x * 0.37, z * 0.37);
+        assert!((-1.0..=1.0).contains(&value));
+
+        let value = value_3d(SEED, x * 0.37, 1.5, z * 0.37);
+        assert!((-1.0..=1.0).contains(&value));
+      }
+    }
+
+    assert_eq!(lattice(SEED, 3, 0, -7), value_2d(SEED, 3.0, -7.0));
+    assert_eq!(lattice(SEED, 3, 2, -7), value_3d(SEED, 3.0, 2.0, -7.0));
+    assert_ne!(hash(SEED, 3, 0, -7), hash(SEED + 1, 3, 0, -7));
+  }
+}
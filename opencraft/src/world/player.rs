@@ -0,0 +1,327 @@
+use crate::core::math::aligned_box3::AlignedBox3;
+use crate::core::math::vec3::Vec3;
+use crate::core::type_conversions::CoerceLossy;
+use crate::world::World;
+use crate::world::position::BlockPos;
+
+/// Acceleration due to gravity, in blocks per second squared.
+const GRAVITY: f32 = 28.0;
+/// Upwards speed given by a jump, enough to clear one block.
+const JUMP_SPEED: f32 = 9.0;
+/// Fastest speed that falling can reach.
+const TERMINAL_VELOCITY: f32 = 60.0;
+const WALK_SPEED: f32 = 4.5;
+const FLY_SPEED: f32 = 10.0;
+/// Gap kept between the player and the blocks they collide with, so that
+/// rounding never leaves them overlapping.
+const SKIN: f32 = 1e-4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovementMode {
+  /// Affected by gravity and colliding with solid blocks.
+  Walking,
+  /// Moving freely in every direction, passing through blocks.
+  Flying,
+}
+
+/// The player's body, moved by input and physics.
+#[derive(Debug, Clone)]
+pub struct Player {
+  /// Centre of the bottom of the player's bounding box.
+  position: Vec3,
+  velocity: Vec3,
+  on_ground: bool,
+  mode: MovementMode,
+}
+
+impl Player {
+  pub const WIDTH: f32 = 0.6;
+  pub const HEIGHT: f32 = 1.8;
+  /// Height of the player's eyes above their feet.
+  pub const EYE_HEIGHT: f32 = 1.6;
+  /// Fastest that a player moves horizontally or upwards, which is while
+  /// flying.
+  pub const MAX_SPEED: f32 = FLY_SPEED;
+  /// Fastest that a player falls.
+  pub const MAX_FALL_SPEED: f32 = TERMINAL_VELOCITY;
+
+  pub fn new(position: Vec3) -> Self {
+    Self {
+      position,
+      velocity: Vec3::default(),
+      on_ground: false,
+      mode: MovementMode::Walking,
+    }
+  }
+
+  pub fn position(&self) -> Vec3 {
+    self.position
+  }
+
+  pub fn eye_position(&self) -> Vec3 {
+    self.position + Vec3::new(0.0, Self::EYE_HEIGHT, 0.0)
+  }
+
+  pub fn velocity(&self) -> Vec3 {
+    self.velocity
+  }
+
+  pub fn is_on_ground(&self) -> bool {
+    self.on_ground
+  }
+
+  pub fn mode(&self) -> MovementMode {
+    self.mode
+  }
+
+  /// Moves the player straight to `position`, stopping them.
+  pub fn teleport(&mut self, position: Vec3) {
+    self.position = position;
+    self.velocity = Vec3::default();
+    self.on_ground = false;
+  }
+
+  pub fn toggle_mode(&mut self) {
+    self.mode = match self.mode {
+      MovementMode::Walking => MovementMode::Flying,
+      MovementMode::Flying => MovementMode::Walking,
+    };
+
+    self.velocity = Vec3::default();
+    self.on_ground = false;
+  }
+
+  pub fn bounding_box(&self) -> AlignedBox3 {
+    let extents = Vec3::new(Self::WIDTH / 2.0, Self::HEIGHT / 2.0, Self::WIDTH / 2.0);
+    AlignedBox3::new(self.position + Vec3::new(0.0, extents.y(), 0.0), extents)
+  }
+
+  /// Advances the player by `delta` seconds.
+  ///
+  /// `movement` is the direction to move in world space, with a length of at
+  /// most 1. While walking, only its horizontal part is used, and `jump`
+  /// launches the player upwards if they are standing on the ground.
+  pub fn update(&mut self, world: &World, movement: Vec3, jump: bool, delta: f32) {
+    match self.mode {
+      MovementMode::Flying => {
+        self.velocity = FLY_SPEED * movement;
+        self.position += delta * self.velocity;
+      }
+      MovementMode::Walking => {
+        let mut velocity_y = self.velocity.y() - (GRAVITY * delta);
+        if jump && self.on_ground {
+          velocity_y = JUMP_SPEED;
+        }
+
+        self.velocity = Vec3::new(
+          WALK_SPEED * movement.x(),
+          velocity_y.max(-TERMINAL_VELOCITY),
+          WALK_SPEED * movement.z(),
+        );
+        self.move_colliding(world, delta * self.velocity);
+      }
+    }
+  }
+
+  /// Moves by `displacement` one axis at a time, stopping at solid blocks.
+  fn move_colliding(&mut self, world: &World, displacement: Vec3) {
+    let [x, y, z]: [f32; 3] = displacement.into();
+
+    // Vertical first, so that landing takes priority over sliding along walls
+    let moved_y = sweep(world, &self.bounding_box(), 1, y);
+    self.position += Vec3::new(0.0, moved_y, 0.0);
+    let moved_x = sweep(world, &self.bounding_box(), 0, x);
+    self.position += Vec3::new(moved_x, 0.0, 0.0);
+    let moved_z = sweep(world, &self.bounding_box(), 2, z);
+    self.position += Vec3::new(0.0, 0.0, moved_z);
+
+    self.on_ground = (y < 0.0) && (moved_y > y);
+    self.velocity = Vec3::new(
+      if moved_x == x { self.velocity.x() } else { 0.0 },
+      if moved_y == y { self.velocity.y() } else { 0.0 },
+      if moved_z == z { self.velocity.z() } else { 0.0 },
+    );
+  }
+}
+
+/// Returns whether a block stops movement.
+///
+/// Unloaded blocks do too, so that the player can't fall out of the world
+/// before the ground beneath them has been generated.
+fn is_obstacle(world: &World, pos: BlockPos) -> bool {
+  world
+    .chunk(pos.chunk())
+    .is_none_or(|chunk| chunk.get(pos.local()).is_solid())
+}
+
+/// Returns how far `bounding_box` can move along `axis` (0, 1 or 2 for x, y or
+/// z) towards `displacement` before hitting a block.
+fn sweep(world: &World, bounding_box: &AlignedBox3, axis: usize, displacement: f32) -> f32 {
+  if displacement == 0.0 {
+    return 0.0;
+  }
+
+  let min: [f32; 3] = bounding_box.min().into();
+  let max: [f32; 3] = bounding_box.max().into();
+
+  // Blocks span [n - 0.5, n + 0.5], so these are the blocks which the box
+  // overlaps (rather than touches) on each axis
+  let overlapping = |axis: usize| {
+    let start: i32 = (min[axis] - 0.5).floor().coerce_lossy();
+    let end: i32 = (max[axis] + 0.5).ceil().coerce_lossy();
+    (start + 1, end - 1)
+  };
+  let (other_axis_a, other_axis_b) = ((axis + 1) % 3, (axis + 2) % 3);
+  let (a_start, a_end) = overlapping(other_axis_a);
+  let (b_start, b_end) = overlapping(other_axis_b);
+
+  let is_layer_blocked = |layer: i32| {
+    (a_start..=a_end).any(|a| {
+      (b_start..=b_end).any(|b| {
+        let mut pos = [0; 3];
+        pos[axis] = layer;
+        pos[other_axis_a] = a;
+        pos[other_axis_b] = b;
+
+        is_obstacle(world, BlockPos::new(pos[0], pos[1], pos[2]))
+      })
+    })
+  };
+
+  if displacement > 0.0 {
+    // Layers of blocks whose near side lies between the box and its target
+    let first: i32 = (max[axis] + 0.5 - SKIN).ceil().coerce_lossy();
+    let beyond_last: i32 = (max[axis] + displacement + 0.5).ceil().coerce_lossy();
+
+    for layer in first..beyond_last {
+      if is_layer_blocked(layer) {
+        let layer: f32 = layer.coerce_lossy();
+        return displacement.min((layer - 0.5) - max[axis] - SKIN).max(0.0);
+      }
+    }
+  } else {
+    let first: i32 = (min[axis] - 0.5 + SKIN).floor().coerce_lossy();
+    let beyond_last: i32 = (min[axis] + displacement - 0.5).floor().coerce_lossy();
+
+    for layer in ((beyond_last + 1)..=first).rev() {
+      if is_layer_blocked(layer) {
+        let layer: f32 = layer.coerce_lossy();
+        return displacement.max((layer + 0.5) - min[axis] + SKIN).min(0.0);
+      }
+    }
+  }
+
+  displacement
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::core::math::X_AXIS;
+  use crate::world::block::Block;
+  use crate::world::chunk::Chunk;
+  use crate::world::position::ChunkPos;
+
+  const TIMESTEP: f32 = 1.0 / 60.0;
+
+  /// A loaded world with a floor whose top surface is at y = 0.5.
+  fn world() -> World {
+    let mut world = World::new();
+    for x in -1..=0 {
+      for y in -1..=0 {
+        for z in -1..=0 {
+          world.insert_chunk(ChunkPos::new(x, y, z), Chunk::default());
+        }
+      }
+    }
+
+    for x in -8..8 {
+      for z in -8..8 {
+        world.set(BlockPos::new(x, 0, z), Block::Stone);
+      }
+    }
+    world
+  }
+
+  fn run(player: &mut Player, world: &World, movement: Vec3, jump: bool, seconds: f32) {
+    let steps: u32 = (seconds / TIMESTEP).round().coerce_lossy();
+    for _ in 0..steps {
+      player.update(world, movement, jump, TIMESTEP);
+    }
+  }
+
+  // Check that a falling player lands on top of the ground and stays there.
+  #[test]
+  fn test_fall_and_land() {
+    let world = world();
+    let mut player = Player::new(Vec3::new(0.0, 5.0, 0.0));
+
+    run(&mut player, &world, Vec3::default(), false, 2.0);
+
+    assert!(player.is_on_ground());
+    assert!((player.position().y() - 0.5).abs() < 1e-3);
+    assert_eq!(0.0, player.velocity().y());
+  }
+
+  // Check that jumping clears a block's height, then returns to the ground.
+  #[test]
+  fn test_jump() {
+    let world = world();
+    let mut player = Player::new(Vec3::new(0.0, 0.5, 0.0));
+    run(&mut player, &world, Vec3::default(), false, 0.1);
+
+    let mut peak: f32 = 0.0;
+    player.update(&world, Vec3::default(), true, TIMESTEP);
+    assert!(!player.is_on_ground());
+    for _ in 0..120 {
+      player.update(&world, Vec3::default(), false, TIMESTEP);
+      peak = peak.max(player.position().y());
+    }
+
+    assert!(peak > 1.5);
+    assert!(player.is_on_ground());
+  }
+
+  // Check that walking into a wall stops the player at its surface.
+  #[test]
+  fn test_wall_collision() {
+    let mut world = world();
+    for y in 1..=3 {
+      world.set(BlockPos::new(3, y, 0), Block::Stone);
+    }
+    let mut player = Player::new(Vec3::new(0.0, 0.5, 0.0));
+
+    run(&mut player, &world, X_AXIS, false, 3.0);
+
+    let expected = 2.5 - (Player::WIDTH / 2.0);
+    assert!((player.position().x() - expected).abs() < 1e-3);
+    assert!(player.is_on_ground());
+  }
+
+  // Check that unloaded chunks block movement, so the player can't fall out
+  // of the world.
+  #[test]
+  fn test_unloaded_chunks_block() {
+    let world = World::new();
+    let mut player = Player::new(Vec3::new(0.0, 0.7, 0.0));
+
+    run(&mut player, &world, Vec3::default(), false, 1.0);
+
+    assert!(player.is_on_ground());
+    assert!((player.position().y() - 0.5).abs() < 1e-3);
+  }
+
+  // Check that flying ignores gravity and passes through blocks.
+  #[test]
+  fn test_fly_mode() {
+    let world = world();
+    let mut player = Player::new(Vec3::new(0.0, 3.0, 0.0));
+    player.toggle_mode();
+
+    run(&mut player, &world, Vec3::default(), false, 1.0);
+    assert_eq!(3.0, player.position().y());
+
+    run(&mut player, &world, Vec3::new(0.0, -1.0, 0.0), false, 0.5);
+    assert!(player.position().y() < 0.0);
+  }
+}
@@ -0,0 +1,222 @@
+use crate::core::math::aligned_box3::{AlignedBox3, BoxFace};
+use crate::core::math::vec3::Vec3;
+use crate::core::type_conversions::{Coerce, CoerceLossy};
+use crate::world::chunk::CHUNK_SIZE;
+
+/// Integer coordinates of a block in the world.
+///
+/// A block at `(x, y, z)` is a unit cube centred on that point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockPos {
+  x: i32,
+  y: i32,
+  z: i32,
+}
+
+impl BlockPos {
+  pub const fn new(x: i32, y: i32, z: i32) -> Self {
+    Self { x, y, z }
+  }
+
+  /// Returns the position of the block which contains `point`.
+  pub fn containing(point: Vec3) -> Self {
+    Self::new(
+      point.x().round().coerce_lossy(),
+      point.y().round().coerce_lossy(),
+      point.z().round().coerce_lossy(),
+    )
+  }
+
+  pub fn from_chunk_local(chunk: ChunkPos, local: LocalPos) -> Self {
+    Self::new(
+      (chunk.x() * CHUNK_SIZE) + i32::from(local.x()),
+      (chunk.y() * CHUNK_SIZE) + i32::from(local.y()),
+      (chunk.z() * CHUNK_SIZE) + i32::from(local.z()),
+    )
+  }
+
+  pub const fn x(self) -> i32 {
+    self.x
+  }
+
+  pub const fn y(self) -> i32 {
+    self.y
+  }
+
+  pub const fn z(self) -> i32 {
+    self.z
+  }
+
+  pub fn center(self) -> Vec3 {
+    Vec3::new(
+      self.x.coerce_lossy(),
+      self.y.coerce_lossy(),
+      self.z.coerce_lossy(),
+    )
+  }
+
+  pub const fn offset(self, x: i32, y: i32, z: i32) -> Self {
+    Self::new(self.x + x, self.y + y, self.z + z)
+  }
+
+  /// Returns the neighbouring block which shares `face` with this block.
+  pub fn adjacent(self, face: BoxFace) -> Self {
+    match face {
+      BoxFace::Left => self.offset(1, 0, 0),
+      BoxFace::Right => self.offset(-1, 0, 0),
+      BoxFace::Top => self.offset(0, 1, 0),
+      BoxFace::Bottom => self.offset(0, -1, 0),
+      BoxFace::Back => self.offset(0, 0, 1),
+      BoxFace::Front => self.offset(0, 0, -1),
+    }
+  }
+
+  pub fn chunk(self) -> ChunkPos {
+    ChunkPos::new(
+      self.x.div_euclid(CHUNK_SIZE),
+      self.y.div_euclid(CHUNK_SIZE),
+      self.z.div_euclid(CHUNK_SIZE),
+    )
+  }
+
+  pub fn local(self) -> LocalPos {
+    fn local_coordinate(value: i32) -> u8 {
+      value.rem_euclid(CHUNK_SIZE).try_into().unwrap()
+    }
+
+    LocalPos::new(
+      local_coordinate(self.x),
+      local_coordinate(self.y),
+      local_coordinate(self.z),
+    )
+  }
+}
+
+/// Integer coordinates of a chunk, in units of [`CHUNK_SIZE`] blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkPos {
+  x: i32,
+  y: i32,
+  z: i32,
+}
+
+impl ChunkPos {
+  pub const fn new(x: i32, y: i32, z: i32) -> Self {
+    Self { x, y, z }
+  }
+
+  pub const fn x(self) -> i32 {
+    self.x
+  }
+
+  pub const fn y(self) -> i32 {
+    self.y
+  }
+
+  pub const fn z(self) -> i32 {
+    self.z
+  }
+
+  pub const fn offset(self, x: i32, y: i32, z: i32) -> Self {
+    Self::new(self.x + x, self.y + y, self.z + z)
+  }
+
+  /// Returns the neighbouring chunk which shares `face` with this chunk.
+  pub fn adjacent(self, face: BoxFace) -> Self {
+    match face {
+      BoxFace::Left => self.offset(1, 0, 0),
+      BoxFace::Right => self.offset(-1, 0, 0),
+      BoxFace::Top => self.offset(0, 1, 0),
+      BoxFace::Bottom => self.offset(0, -1, 0),
+      BoxFace::Back => self.offset(0, 0, 1),
+      BoxFace::Front => self.offset(0, 0, -1),
+    }
+  }
+
+  /// Returns the position of the block at the chunk's minimum corner.
+  pub fn origin(self) -> BlockPos {
+    BlockPos::new(
+      self.x * CHUNK_SIZE,
+      self.y * CHUNK_SIZE,
+      self.z * CHUNK_SIZE,
+    )
+  }
+
+  /// Returns the box which the chunk's blocks fill.
+  pub fn bounding_box(self) -> AlignedBox3 {
+    let half_size: f32 = CHUNK_SIZE.coerce_lossy() / 2.0;
+    let origin_corner = self.origin().center() - Vec3::new(0.5, 0.5, 0.5);
+
+    AlignedBox3::cube(
+      origin_corner + Vec3::new(half_size, half_size, half_size),
+      half_size,
+    )
+  }
+}
+
+/// Coordinates of a block relative to the origin of its chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalPos {
+  x: u8,
+  y: u8,
+  z: u8,
+}
+
+impl LocalPos {
+  pub fn new(x: u8, y: u8, z: u8) -> Self {
+    let size = u8::try_from(CHUNK_SIZE).unwrap();
+    assert!(
+      (x < size) && (y < size) && (z < size),
+      "local position ({}, {}, {}) out of chunk bounds",
+      x,
+      y,
+      z
+    );
+
+    Self { x, y, z }
+  }
+
+  pub fn from_index(index: usize) -> Self {
+    let size: usize = CHUNK_SIZE.coerce();
+
+    Self::new(
+      (index % size).coerce(),
+      ((index / size) % size).coerce(),
+      (index / (size * size)).coerce(),
+    )
+  }
+
+  pub const fn x(self) -> u8 {
+    self.x
+  }
+
+  pub const fn y(self) -> u8 {
+    self.y
+  }
+
+  pub const fn z(self) -> u8 {
+    self.z
+  }
+
+  /// Returns the neighbouring block which shares `face` with this block, or
+  /// [`None`] if it lies in another chunk.
+  pub fn adjacent(self, face: BoxFace) -> Option<Self> {
+    let last = u8::try_from(CHUNK_SIZE - 1).unwrap();
+    let Self { x, y, z } = self;
+
+    match face {
+      BoxFace::Left => (x < last).then(|| Self::new(x + 1, y, z)),
+      BoxFace::Right => x.checked_sub(1).map(|x| Self::new(x, y, z)),
+      BoxFace::Top => (y < last).then(|| Self::new(x, y + 1, z)),
+      BoxFace::Bottom => y.checked_sub(1).map(|y| Self::new(x, y, z)),
+      BoxFace::Back => (z < last).then(|| Self::new(x, y, z + 1)),
+      BoxFace::Front => z.checked_sub(1).map(|z| Self::new(x, y, z)),
+    }
+  }
+
+  pub fn index(self) -> usize {
+    let size: usize = CHUNK_SIZE.coerce();
+
+    usize::from(self.x) + (usize::from(self.y) * size) + (usize::from(self.z) * size * size)
+  }
+}
@@ -0,0 +1,157 @@
+use crate::core::math::aligned_box3::BoxFace;
+use crate::core::math::segment3::Segment3;
+use crate::core::math::vec3::Vec3;
+use crate::core::type_conversions::CoerceLossy;
+use crate::world::World;
+use crate::world::position::BlockPos;
+
+/// The first solid block along a ray.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+  pub block: BlockPos,
+  /// The face of the block which the ray entered through.
+  pub face: BoxFace,
+  /// Where the ray entered the block.
+  pub point: Vec3,
+  /// Distance along the ray to [`RaycastHit::point`].
+  pub distance: f32,
+}
+
+/// Faces entered when stepping along each axis in the positive direction, then
+/// in the negative direction.
+const ENTERED_FACES: [(BoxFace, BoxFace); 3] = [
+  (BoxFace::Right, BoxFace::Left),
+  (BoxFace::Bottom, BoxFace::Top),
+  (BoxFace::Front, BoxFace::Back),
+];
+
+/// Finds the first solid block which `ray` enters, by walking through the
+/// blocks it passes through in order (Amanatides & Woo's voxel traversal).
+///
+/// The block containing the start of the ray is ignored, since the ray doesn't
+/// enter it through any face.
+pub fn raycast(world: &World, ray: &Segment3) -> Option<RaycastHit> {
+  let unit_direction = ray.direction().norm();
+  let direction = [unit_direction.x(), unit_direction.y(), unit_direction.z()];
+  // Blocks are centred on integer coordinates, so shift the start to put the
+  // boundaries between blocks on integers instead
+  let start = ray.start() + Vec3::new(0.5, 0.5, 0.5);
+  let start = [start.x(), start.y(), start.z()];
+
+  let mut block = start.map(|coordinate| -> i32 { coordinate.floor().coerce_lossy() });
+  let mut step = [0; 3];
+  // Distance along the ray at which it next crosses a boundary on each axis
+  let mut boundary_distance = [f32::INFINITY; 3];
+  // Distance along the ray between boundaries on each axis
+  let mut boundary_spacing = [f32::INFINITY; 3];
+
+  for axis in 0..3 {
+    if direction[axis] == 0.0 {
+      continue;
+    }
+
+    let block_start: f32 = block[axis].coerce_lossy();
+    let (axis_step, next_boundary) = if direction[axis] > 0.0 {
+      (1, block_start + 1.0)
+    } else {
+      (-1, block_start)
+    };
+
+    step[axis] = axis_step;
+    boundary_distance[axis] = (next_boundary - start[axis]) / direction[axis];
+    boundary_spacing[axis] = 1.0 / direction[axis].abs();
+  }
+
+  loop {
+    let axis = (0..3)
+      .min_by(|&a, &b| boundary_distance[a].total_cmp(&boundary_distance[b]))
+      .unwrap();
+    let distance = boundary_distance[axis];
+
+    if distance > ray.len() {
+      return None;
+    }
+
+    block[axis] += step[axis];
+    boundary_distance[axis] += boundary_spacing[axis];
+
+    let pos = BlockPos::new(block[0], block[1], block[2]);
+    if world.get(pos).is_solid() {
+      let (positive, negative) = ENTERED_FACES[axis];
+
+      return Some(RaycastHit {
+        block: pos,
+        face: if step[axis] > 0 { positive } else { negative },
+        point: ray.start() + (distance * unit_direction),
+        distance,
+      });
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::core::math::{X_AXIS, Y_AXIS};
+  use crate::world::block::Block;
+
+  fn world(blocks: &[BlockPos]) -> World {
+    let mut world = World::new();
+    for &pos in blocks {
+      world.set(pos, Block::Stone);
+    }
+    world
+  }
+
+  // Check that a ray looking down onto a block hits its top face, at the top
+  // surface of the block.
+  #[test]
+  fn test_hit_top_face() {
+    let world = world(&[BlockPos::new(2, -3, 4)]);
+    let ray = Segment3::start_direction_len(Vec3::new(2.2, 1.0, 3.9), -Y_AXIS, 10.0);
+
+    let hit = raycast(&world, &ray).unwrap();
+
+    assert_eq!(BlockPos::new(2, -3, 4), hit.block);
+    assert_eq!(BoxFace::Top, hit.face);
+    assert!((hit.distance - 3.5).abs() < 1e-5);
+    assert!((hit.point.y() - -2.5).abs() < 1e-5);
+  }
+
+  // Check that a diagonal ray hits the first block it enters, through the face
+  // it crosses, even when that isn't the axis it mostly travels along.
+  #[test]
+  fn test_diagonal_hit_side_face() {
+    let world = world(&[BlockPos::new(2, 1, 0), BlockPos::new(3, 1, 0)]);
+    let direction = Vec3::new(1.0, 0.3, 0.2).norm();
+    let ray = Segment3::start_direction_len(Vec3::default(), direction, 10.0);
+
+    let hit = raycast(&world, &ray).unwrap();
+
+    assert_eq!(BlockPos::new(2, 1, 0), hit.block);
+    assert_eq!(BoxFace::Bottom, hit.face);
+    assert!((hit.point.y() - 0.5).abs() < 1e-5);
+  }
+
+  // Check that blocks beyond the end of the ray, or behind it, are missed.
+  #[test]
+  fn test_miss_out_of_reach() {
+    let world = world(&[BlockPos::new(-6, 0, 0), BlockPos::new(3, 0, 0)]);
+    let ray = Segment3::start_direction_len(Vec3::default(), -X_AXIS, 5.0);
+
+    assert_eq!(None, raycast(&world, &ray));
+  }
+
+  // Check that the block containing the start of the ray is ignored.
+  #[test]
+  fn test_start_block_ignored() {
+    let world = world(&[BlockPos::new(0, 0, 0), BlockPos::new(-2, 0, 0)]);
+    let ray = Segment3::start_direction_len(Vec3::default(), -X_AXIS, 5.0);
+
+    let hit = raycast(&world, &ray).unwrap();
+
+    assert_eq!(BlockPos::new(-2, 0, 0), hit.block);
+    assert_eq!(BoxFace::Left, hit.face);
+    assert!((hit.distance - 1.5).abs() < 1e-5);
+  }
+}
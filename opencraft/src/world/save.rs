@@ -0,0 +1,735 @@
+//! Saving and loading worlds.
+//!
+//! A saved world is a `world` directory in [`Storage`] containing:
+//!
+//! - `world.txt`, the world metadata, as `key = value` lines. Blank lines and
+//!   lines starting with `#` are ignored. The keys are:
+//!   - `version`: the [`FORMAT_VERSION`] the world was saved with.
+//!   - `seed`: the seed of the
+//!     [`TerrainGenerator`](super::generation::TerrainGenerator).
+//!   - `camera_position`: the camera's `x y z` position.
+//!   - `camera_yaw` and `camera_pitch`: the camera's orientation, in radians.
+//!   - `time`: the [`WorldTime`] which has passed in the world, in
+//!     milliseconds. Worlds saved without it start at the default time.
+//! - `regions/r.<x>.<y>.<z>.bin`, the region files. Each region groups the
+//!   saved chunks within a cube of [`REGION_SIZE`] chunks per edge, named by
+//!   the region's coordinates in units of regions.
+//!
+//! Region files are binary, with all integers little-endian:
+//!
+//! | Field         | Type      | Description                                  |
+//! |---------------|-----------|----------------------------------------------|
+//! | magic         | `[u8; 4]` | [`REGION_MAGIC`]                             |
+//! | version       | `u32`     | [`FORMAT_VERSION`]                           |
+//! | chunk count   | `u32`     | Number of chunk records which follow         |
+//!
+//! Followed by each chunk record:
+//!
+//! | Field         | Type      | Description                                  |
+//! |---------------|-----------|----------------------------------------------|
+//! | position      | `[u8; 3]` | Chunk `x y z` relative to the region origin  |
+//! | run count     | `u32`     | Number of runs which follow                  |
+//! | runs          | `[run]`   | Run-length encoded blocks                    |
+//!
+//! Where each run is a `u16` length followed by a `u8` block ID, and the runs
+//! cover the chunk's blocks in [`LocalPos::index`] order.
+
+use crate::core::bytes::{ByteReader, ByteWriter};
+use crate::core::math::angle::Angle;
+use crate::core::math::vec3::Vec3;
+use crate::platform::Storage;
+use crate::world::World;
+use crate::world::chunk::Chunk;
+use crate::world::generation::{self, TerrainGenerator};
+use crate::world::position::ChunkPos;
+use crate::world::time::WorldTime;
+use anyhow::{Context, Result, bail, ensure};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Version of the save format, which is bumped whenever it changes.
+pub const FORMAT_VERSION: u32 = 1;
+/// Number of chunks along each edge of a region.
+pub const REGION_SIZE: i32 = 8;
+pub const REGION_MAGIC: [u8; 4] = *b"OCRG";
+
+const METADATA_KEY: &str = "world/world.txt";
+const REGIONS_DIRECTORY: &str = "world/regions";
+
+/// How often a running game saves the world.
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Everything saved about a world besides its blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldMetadata {
+  pub seed: u64,
+  pub camera_position: Vec3,
+  pub camera_yaw: Angle,
+  pub camera_pitch: Angle,
+  pub time: WorldTime,
+}
+
+impl WorldMetadata {
+  pub fn encode(&self) -> String {
+    format!(
+      "# Opencraft world metadata\n\
+       version = {}\n\
+       seed = {}\n\
+       camera_position = {} {} {}\n\
+       camera_yaw = {}\n\
+       camera_pitch = {}\n\
+       time = {}\n",
+      FORMAT_VERSION,
+      self.seed,
+      self.camera_position.x(),
+      self.camera_position.y(),
+      self.camera_position.z(),
+      self.camera_yaw.as_radians(),
+      self.camera_pitch.as_radians(),
+      self.time.as_millis(),
+    )
+  }
+
+  pub fn decode(text: &str) -> Result<Self> {
+    let mut values = HashMap::new();
+    for line in text.lines().map(str::trim) {
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+
+      let (key, value) = line
+        .split_once('=')
+        .with_context(|| format!("metadata line has no '=': {line:?}"))?;
+      values.insert(key.trim(), value.trim());
+    }
+
+    let value = |key: &str| {
+      values
+        .get(key)
+        .copied()
+        .with_context(|| format!("metadata is missing {key:?}"))
+    };
+
+    let version: u32 = value("version")?.parse()?;
+    ensure!(
+      version == FORMAT_VERSION,
+      "unsupported world format version {version}"
+    );
+
+    let position: Vec<f32> = value("camera_position")?
+      .split_whitespace()
+      .map(str::parse)
+      .collect::<Result<_, _>>()?;
+    let &[x, y, z] = position.as_slice() else {
+      bail!("camera position does not have 3 components");
+    };
+
+    Ok(Self {
+      seed: value("seed")?.parse()?,
+      camera_position: Vec3::new(x, y, z),
+      camera_yaw: Angle::radians(value("camera_yaw")?.parse()?),
+      camera_pitch: Angle::radians(value("camera_pitch")?.parse()?),
+      time: match values.get("time") {
+        Some(millis) => WorldTime::from_millis(millis.parse()?),
+        None => WorldTime::default(),
+      },
+    })
+  }
+}
+
+/// Integer coordinates of a region, in units of [`REGION_SIZE`] chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegionPos {
+  x: i32,
+  y: i32,
+  z: i32,
+}
+
+impl RegionPos {
+  pub const fn new(x: i32, y: i32, z: i32) -> Self {
+    Self { x, y, z }
+  }
+
+  pub fn containing(chunk: ChunkPos) -> Self {
+    Self::new(
+      chunk.x().div_euclid(REGION_SIZE),
+      chunk.y().div_euclid(REGION_SIZE),
+      chunk.z().div_euclid(REGION_SIZE),
+    )
+  }
+
+  fn origin(self) -> ChunkPos {
+    ChunkPos::new(
+      self.x * REGION_SIZE,
+      self.y * REGION_SIZE,
+      self.z * REGION_SIZE,
+    )
+  }
+
+  pub fn file_name(self) -> String {
+    format!("r.{}.{}.{}.bin", self.x, self.y, self.z)
+  }
+
+  pub fn from_file_name(name: &str) -> Option<Self> {
+    let mut coordinates = name
+      .strip_prefix("r.")?
+      .strip_suffix(".bin")?
+      .split('.')
+      .map(str::parse);
+
+    let pos = Self::new(
+      coordinates.next()?.ok()?,
+      coordinates.next()?.ok()?,
+      coordinates.next()?.ok()?,
+    );
+
+    coordinates.next().is_none().then_some(pos)
+  }
+}
+
+/// Encodes the chunks of a single region, which must all lie within `region`.
+pub fn encode_region<'a>(
+  region: RegionPos,
+  chunks: impl IntoIterator<Item = (ChunkPos, &'a Chunk)>,
+) -> Vec<u8> {
+  let mut records = ByteWriter::new();
+  let mut chunk_count: u32 = 0;
+
+  for (pos, chunk) in chunks {
+    assert_eq!(region, RegionPos::containing(pos));
+    let origin = region.origin();
+
+    for coordinate in [
+      pos.x() - origin.x(),
+      pos.y() - origin.y(),
+      pos.z() - origin.z(),
+    ] {
+      records.u8(u8::try_from(coordinate).unwrap());
+    }
+
+    chunk.encode_runs(&mut records);
+
+    chunk_count += 1;
+  }
+
+  let mut bytes = ByteWriter::new();
+  bytes.bytes(&REGION_MAGIC);
+  bytes.u32(FORMAT_VERSION);
+  bytes.u32(chunk_count);
+  bytes.bytes(&records.into_bytes());
+  bytes.into_bytes()
+}
+
+pub fn decode_region(region: RegionPos, bytes: &[u8]) -> Result<Vec<(ChunkPos, Chunk)>> {
+  let mut reader = ByteReader::new(bytes);
+
+  ensure!(reader.take()? == REGION_MAGIC, "not a region file");
+  let version = reader.u32()?;
+  ensure!(
+    version == FORMAT_VERSION,
+    "unsupported region format version {version}"
+  );
+
+  let chunk_count = reader.u32()?;
+  let mut chunks = Vec::new();
+  for _ in 0..chunk_count {
+    let [x, y, z] = [reader.u8()?, reader.u8()?, reader.u8()?].map(i32::from);
+    ensure!(
+      [x, y, z].iter().all(|&coordinate| coordinate < REGION_SIZE),
+      "chunk lies outside its region"
+    );
+    let pos = region.origin();
+    let pos = ChunkPos::new(pos.x() + x, pos.y() + y, pos.z() + z);
+
+    chunks.push((pos, Chunk::decode_runs(&mut reader)?));
+  }
+
+  Ok(chunks)
+}
+
+/// Encodes the `chunks` lying in each of `regions`, including regions left
+/// with none.
+///
+/// Only modified chunks need saving, since the rest can be generated again
+/// from the world's seed.
+pub fn encode_regions<'a>(
+  chunks: impl IntoIterator<Item = (ChunkPos, &'a Chunk)>,
+  regions: impl IntoIterator<Item = RegionPos>,
+) -> HashMap<RegionPos, Vec<u8>> {
+  let mut regions: HashMap<RegionPos, Vec<(ChunkPos, &Chunk)>> = regions
+    .into_iter()
+    .map(|region| (region, Vec::new()))
+    .collect();
+  for (pos, chunk) in chunks {
+    if let Some(chunks) = regions.get_mut(&RegionPos::containing(pos)) {
+      chunks.push((pos, chunk));
+    }
+  }
+
+  regions
+    .into_iter()
+    .map(|(region, mut chunks)| {
+      // Keep the output stable regardless of hash map iteration order
+      chunks.sort_unstable_by_key(|(pos, _)| (pos.x(), pos.y(), pos.z()));
+      (region, encode_region(region, chunks))
+    })
+    .collect()
+}
+
+/// A world saved in [`Storage`], whose regions are only read once chunks in
+/// them are needed.
+pub struct WorldSave {
+  storage: Storage,
+  /// Regions read so far, with their saved chunks which aren't loaded into the
+  /// world.
+  regions: HashMap<RegionPos, HashMap<ChunkPos, Chunk>>,
+}
+
+impl WorldSave {
+  pub fn new(storage: Storage) -> Self {
+    Self {
+      storage,
+      regions: HashMap::new(),
+    }
+  }
+
+  /// Reads the metadata of the world saved, or returns [`None`] if no world
+  /// has been saved.
+  pub fn read_metadata(&self) -> Result<Option<WorldMetadata>> {
+    let Some(metadata) = self.storage.read(METADATA_KEY)? else {
+      return Ok(None);
+    };
+
+    Ok(Some(WorldMetadata::decode(str::from_utf8(&metadata)?)?))
+  }
+
+  /// Loads the chunk at `pos` into `world` if it was saved, reading its region
+  /// first if necessary, and returns whether it was.
+  ///
+  /// The chunk is modified but not unsaved, since it is unchanged since it was
+  /// saved.
+  pub fn load_chunk(&mut self, world: &mut World, pos: ChunkPos) -> Result<bool> {
+    let Some(chunk) = self
+      .read_region(world, RegionPos::containing(pos))?
+      .remove(&pos)
+    else {
+      return Ok(false);
+    };
+
+    world.insert_chunk(pos, chunk);
+    world.mark_modified(pos);
+    Ok(true)
+  }
+
+  /// Loads up to `limit` of the chunks missing from `world` within `radius`
+  /// chunks horizontally of `center`, nearest first, and returns how many were
+  /// loaded. Chunks which were never saved are generated by `terrain`.
+  pub fn load_around(
+    &mut self,
+    world: &mut World,
+    terrain: &TerrainGenerator,
+    center: ChunkPos,
+    radius: i32,
+    limit: usize,
+  ) -> Result<usize> {
+    let missing = generation::missing_chunks(world, center, radius);
+
+    for &pos in missing.iter().take(limit) {
+      if !self.load_chunk(world, pos)? {
+        world.insert_chunk(pos, terrain.generate(pos));
+      }
+    }
+
+    Ok(missing.len().min(limit))
+  }
+
+  /// Unloads the chunk at `pos` from `world`, first saving its region if the
+  /// chunk has unsaved changes, so that it can be loaded again later.
+  pub fn unload_chunk(&mut self, world: &mut World, pos: ChunkPos) -> Result<()> {
+    let region = RegionPos::containing(pos);
+    if world.is_unsaved(pos) {
+      self.write_regions(world, HashSet::from([region]))?;
+    }
+
+    let modified = world.is_modified(pos);
+    if let Some(chunk) = world.remove_chunk(pos)
+      && modified
+    {
+      self.regions.entry(region).or_default().insert(pos, chunk);
+    }
+
+    // Regions are read again once they're needed again
+    if !world
+      .chunks()
+      .any(|(loaded, _)| RegionPos::containing(loaded) == region)
+    {
+      self.regions.remove(&region);
+    }
+
+    Ok(())
+  }
+
+  /// Saves the world, rewriting only the regions holding chunks which have
+  /// changed since it was last saved.
+  pub fn write(&mut self, metadata: &WorldMetadata, world: &mut World) -> Result<()> {
+    let changed: HashSet<RegionPos> = world.unsaved_chunks().map(RegionPos::containing).collect();
+    self.write_regions(world, changed)?;
+
+    // Written last, so that a world is only ever loaded once it is complete
+    self
+      .storage
+      .write(METADATA_KEY, metadata.encode().as_bytes())
+  }
+
+  /// Rewrites each of `regions`, recording that their chunks are saved.
+  fn write_regions(&mut self, world: &mut World, regions: HashSet<RegionPos>) -> Result<()> {
+    // Rewritten regions keep the saved chunks which aren't loaded
+    for &region in &regions {
+      self.read_region(world, region)?;
+    }
+    let saved = self
+      .regions
+      .values()
+      .flat_map(|chunks| chunks.iter().map(|(&pos, chunk)| (pos, chunk)))
+      .filter(|&(pos, _)| world.chunk(pos).is_none());
+
+    for (region, bytes) in encode_regions(
+      world.modified_chunks().chain(saved),
+      regions.iter().copied(),
+    ) {
+      self.storage.write(
+        &format!("{}/{}", REGIONS_DIRECTORY, region.file_name()),
+        &bytes,
+      )?;
+    }
+
+    let written: Vec<ChunkPos> = world
+      .unsaved_chunks()
+      .filter(|&pos| regions.contains(&RegionPos::containing(pos)))
+      .collect();
+    world.mark_saved(written);
+
+    Ok(())
+  }
+
+  /// Returns the saved chunks of `region` which aren't loaded into `world`,
+  /// reading them the first time.
+  fn read_region(
+    &mut self,
+    world: &World,
+    region: RegionPos,
+  ) -> Result<&mut HashMap<ChunkPos, Chunk>> {
+    if !self.regions.contains_key(&region) {
+      let name = region.file_name();
+      let chunks = match self.storage.read(&format!("{REGIONS_DIRECTORY}/{name}"))? {
+        Some(bytes) => {
+          decode_region(region, &bytes).with_context(|| format!("could not load region {name}"))?
+        }
+        None => Vec::new(),
+      };
+
+      // Chunks already loaded, such as ones changed before their region was
+      // read, are newer than the saved ones
+      let chunks = chunks
+        .into_iter()
+        .filter(|&(pos, _)| world.chunk(pos).is_none())
+        .collect();
+      self.regions.insert(region, chunks);
+    }
+
+    Ok(self.regions.get_mut(&region).unwrap())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::world::block::Block;
+  use crate::world::position::BlockPos;
+  use std::{env, fs};
+
+  fn metadata() -> WorldMetadata {
+    WorldMetadata {
+      seed: 0xDEAD_BEEF_0000_0001,
+      camera_position: Vec3::new(-12.25, 30.5, 1e-3),
+      camera_yaw: Angle::radians(4.0),
+      camera_pitch: Angle::radians(-0.3),
+      time: WorldTime::from_millis(123_456_789),
+    }
+  }
+
+  /// A world of generated terrain spanning several regions, with edits to
+  /// every chunk.
+  fn world() -> World {
+    let generator = TerrainGenerator::new(7);
+    let mut world = World::new();
+
+    for x in -1..=1 {
+      for y in -2..=0 {
+        for z in [-REGION_SIZE, 0, REGION_SIZE - 1] {
+          let pos = ChunkPos::new(x, y, z);
+          world.insert_chunk(pos, generator.generate(pos));
+          world.set(pos.origin(), Block::Wood);
+        }
+      }
+    }
+
+    world.set(BlockPos::new(0, 40, 0), Block::Glass);
+    world.set(BlockPos::new(-1, -20, -1), Block::Air);
+    world
+  }
+
+  /// Returns every region holding a modified chunk.
+  fn modified_regions(world: &World) -> HashSet<RegionPos> {
+    world
+      .modified_chunks()
+      .map(|(pos, _)| RegionPos::containing(pos))
+      .collect()
+  }
+
+  fn assert_worlds_eq(expected: &World, actual: &World) {
+    assert_eq!(expected.chunk_count(), actual.chunk_count());
+
+    for (pos, chunk) in expected.chunks() {
+      assert!(actual.is_modified(pos));
+      assert!(actual.chunk(pos) == Some(chunk), "chunk {pos:?} differs");
+    }
+  }
+
+  // Check that metadata survives a round trip exactly.
+  #[test]
+  fn test_metadata_round_trip() {
+    let metadata = metadata();
+
+    assert_eq!(metadata, WorldMetadata::decode(&metadata.encode()).unwrap());
+  }
+
+  // Check that worlds saved in an unknown format are rejected rather than
+  // misread.
+  #[test]
+  fn test_unsupported_version_rejected() {
+    let metadata = metadata().encode().replace(
+      &format!("version = {FORMAT_VERSION}"),
+      &format!("version = {}", FORMAT_VERSION + 1),
+    );
+    assert!(WorldMetadata::decode(&metadata).is_err());
+
+    let mut region = encode_region(RegionPos::new(0, 0, 0), []);
+    region[4] += 1;
+    assert!(decode_region(RegionPos::new(0, 0, 0), &region).is_err());
+  }
+
+  // Check that every chunk survives encoding into regions and decoding again.
+  #[test]
+  fn test_regions_round_trip() {
+    let world = world();
+    let regions = encode_regions(world.modified_chunks(), modified_regions(&world));
+    assert!(regions.len() > 1);
+
+    let mut loaded = World::new();
+    for (region, bytes) in regions {
+      for (pos, chunk) in decode_region(region, &bytes).unwrap() {
+        assert_eq!(region, RegionPos::containing(pos));
+        loaded.insert_chunk(pos, chunk);
+        loaded.mark_modified(pos);
+      }
+    }
+
+    assert_worlds_eq(&world, &loaded);
+    assert_eq!(Block::Glass, loaded.get(BlockPos::new(0, 40, 0)));
+  }
+
+  // Check that only modified chunks are saved, since the rest can be
+  // generated again.
+  #[test]
+  fn test_unmodified_chunks_not_saved() {
+    let generator = TerrainGenerator::new(7);
+    let mut world = World::new();
+    for pos in [ChunkPos::new(0, -1, 0), ChunkPos::new(1, -1, 0)] {
+      world.insert_chunk(pos, generator.generate(pos));
+    }
+    world.set(BlockPos::new(0, -1, 0), Block::Glass);
+
+    let regions = encode_regions(world.modified_chunks(), modified_regions(&world));
+    let chunks = decode_region(
+      RegionPos::new(0, -1, 0),
+      &regions[&RegionPos::new(0, -1, 0)],
+    );
+
+    assert_eq!(1, regions.len());
+    assert_eq!(
+      vec![ChunkPos::new(0, -1, 0)],
+      chunks
+        .unwrap()
+        .into_iter()
+        .map(|(pos, _)| pos)
+        .collect::<Vec<_>>()
+    );
+  }
+
+  // Check that a truncated region file is an error rather than a panic.
+  #[test]
+  fn test_truncated_region_rejected() {
+    let world = world();
+    let (region, bytes) = encode_regions(world.modified_chunks(), modified_regions(&world))
+      .into_iter()
+      .next()
+      .unwrap();
+
+    assert!(decode_region(region, &bytes[..bytes.len() - 1]).is_err());
+  }
+
+  /// Loads the saved chunks at `positions` into a new world.
+  fn load(save: &mut WorldSave, positions: impl IntoIterator<Item = ChunkPos>) -> World {
+    let mut world = World::new();
+    for pos in positions {
+      assert!(save.load_chunk(&mut world, pos).unwrap());
+    }
+    world
+  }
+
+  // Check that a world written to storage is read back identically, without
+  // its chunks needing saving again.
+  #[test]
+  fn test_storage_round_trip() {
+    let path = env::temp_dir().join(format!("opencraft-save-test-{}", std::process::id()));
+    let (metadata, mut world) = (metadata(), world());
+    let positions: Vec<ChunkPos> = world.chunks().map(|(pos, _)| pos).collect();
+
+    let mut save = WorldSave::new(Storage::at(&path));
+    assert!(save.read_metadata().unwrap().is_none());
+    save.write(&metadata, &mut world).unwrap();
+    let mut save = WorldSave::new(Storage::at(&path));
+    let loaded_metadata = save.read_metadata().unwrap();
+    let loaded_world = load(&mut save, positions);
+    fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!(Some(metadata), loaded_metadata);
+    assert_worlds_eq(&world, &loaded_world);
+    assert_eq!(0, loaded_world.unsaved_chunks().count());
+  }
+
+  // Check that saving again only rewrites the regions whose chunks changed,
+  // and keeps the chunks saved before.
+  #[test]
+  fn test_only_changed_regions_rewritten() {
+    let path = env::temp_dir().join(format!("opencraft-resave-test-{}", std::process::id()));
+    let mut save = WorldSave::new(Storage::at(&path));
+    let (metadata, mut world) = (metadata(), world());
+    let region_path = |region: RegionPos| path.join(REGIONS_DIRECTORY).join(region.file_name());
+
+    save.write(&metadata, &mut world).unwrap();
+    assert_eq!(0, world.unsaved_chunks().count());
+
+    let (changed, unchanged) = (RegionPos::new(0, 0, 0), RegionPos::new(0, 0, -1));
+    fs::remove_file(region_path(unchanged)).unwrap();
+    world.set(BlockPos::new(1, 1, 1), Block::Sand);
+    save.write(&metadata, &mut world).unwrap();
+    let exists = (
+      fs::exists(region_path(changed)).unwrap(),
+      fs::exists(region_path(unchanged)).unwrap(),
+    );
+    let loaded_world = load(
+      &mut WorldSave::new(Storage::at(&path)),
+      [ChunkPos::new(0, 0, 0), ChunkPos::new(0, 2, 0)],
+    );
+    fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!((true, false), exists);
+    assert_eq!(Block::Sand, loaded_world.get(BlockPos::new(1, 1, 1)));
+    assert_eq!(Block::Glass, loaded_world.get(BlockPos::new(0, 40, 0)));
+  }
+
+  // Check that rewriting a region keeps its saved chunks which were never
+  // loaded.
+  #[test]
+  fn test_unloaded_chunks_kept() {
+    let path = env::temp_dir().join(format!("opencraft-partial-test-{}", std::process::id()));
+    let (metadata, mut world) = (metadata(), world());
+    WorldSave::new(Storage::at(&path))
+      .write(&metadata, &mut world)
+      .unwrap();
+
+    let mut save = WorldSave::new(Storage::at(&path));
+    let mut partial = load(&mut save, [ChunkPos::new(0, 0, 0)]);
+    partial.set(BlockPos::new(1, 1, 1), Block::Sand);
+    save.write(&metadata, &mut partial).unwrap();
+    let loaded_world = load(
+      &mut WorldSave::new(Storage::at(&path)),
+      [ChunkPos::new(0, 0, 0), ChunkPos::new(1, 0, 0)],
+    );
+    fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!(Block::Sand, loaded_world.get(BlockPos::new(1, 1, 1)));
+    assert!(loaded_world.chunk(ChunkPos::new(1, 0, 0)) == world.chunk(ChunkPos::new(1, 0, 0)));
+  }
+
+  // Check that unloading a chunk saves its changes first, and that it is
+  // loaded again as it was.
+  #[test]
+  fn test_unloaded_chunk_saved() {
+    let path = env::temp_dir().join(format!("opencraft-unload-test-{}", std::process::id()));
+    let (changed, untouched) = (ChunkPos::new(0, 0, 0), ChunkPos::new(-REGION_SIZE, 0, 0));
+    let mut save = WorldSave::new(Storage::at(&path));
+    let mut world = World::new();
+    for pos in [changed, untouched] {
+      world.insert_chunk(pos, TerrainGenerator::new(7).generate(pos));
+    }
+    world.set(BlockPos::new(1, 1, 1), Block::Sand);
+
+    save.unload_chunk(&mut world, changed).unwrap();
+    save.unload_chunk(&mut world, untouched).unwrap();
+    let unsaved = world.unsaved_chunks().count();
+    let reloaded = load(&mut save, [changed]);
+    let untouched_saved = save.load_chunk(&mut World::new(), untouched).unwrap();
+    let fresh = load(&mut WorldSave::new(Storage::at(&path)), [changed]);
+    fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!(0, world.chunk_count());
+    assert_eq!(0, unsaved);
+    assert_eq!(Block::Sand, reloaded.get(BlockPos::new(1, 1, 1)));
+    assert!(!untouched_saved);
+    assert_eq!(Block::Sand, fresh.get(BlockPos::new(1, 1, 1)));
+  }
+
+  // Check that regions are only read once chunks in them are loaded, so that a
+  // damaged region doesn't stop the rest of the world loading.
+  #[test]
+  fn test_regions_read_when_needed() {
+    let path = env::temp_dir().join(format!("opencraft-lazy-test-{}", std::process::id()));
+    let (metadata, mut world) = (metadata(), world());
+    WorldSave::new(Storage::at(&path))
+      .write(&metadata, &mut world)
+      .unwrap();
+    let damaged = RegionPos::new(0, 0, -1);
+    fs::write(
+      path.join(REGIONS_DIRECTORY).join(damaged.file_name()),
+      b"junk",
+    )
+    .unwrap();
+
+    let mut save = WorldSave::new(Storage::at(&path));
+    let mut loaded = World::new();
+    let results = (
+      save.load_chunk(&mut loaded, ChunkPos::new(0, 0, 0)),
+      save.load_chunk(&mut loaded, ChunkPos::new(0, 0, -REGION_SIZE)),
+      save.load_chunk(&mut loaded, ChunkPos::new(5, 5, 5)),
+    );
+    fs::remove_dir_all(&path).unwrap();
+
+    assert!(results.0.unwrap());
+    assert!(results.1.is_err());
+    assert!(!results.2.unwrap());
+  }
+
+  // Check that region file names are parsed back into the same position.
+  #[test]
+  fn test_region_file_names() {
+    let region = RegionPos::new(-3, 0, 12);
+
+    assert_eq!(Some(region), RegionPos::from_file_name(&region.file_name()));
+    assert_eq!(None, RegionPos::from_file_name("r.1.2.bin"));
+    assert_eq!(None, RegionPos::from_file_name("r.1.2.3.4.bin"));
+    assert_eq!(None, RegionPos::from_file_name("r.1.2.3.bin.tmp"));
+  }
+}
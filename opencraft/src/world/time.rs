@@ -0,0 +1,185 @@
+//! The time of day in a world, which advances with its simulation.
+
+use crate::core::math::angle::FULL_ROTATION;
+use crate::core::math::vec3::Vec3;
+use crate::core::type_conversions::CoerceLossy;
+use anyhow::{Context, Result, ensure};
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+/// Length of a whole day and night.
+pub const DAY_LENGTH: Duration = Duration::from_secs(20 * 60);
+/// Time of day which new worlds start at, as a fraction of a day since
+/// midnight: early in the morning.
+const START_TIME_OF_DAY: f32 = 0.3;
+
+/// Times of day which can be named instead of given as a clock time.
+const NAMED_TIMES: [(&str, f32); 4] = [
+  ("midnight", 0.0),
+  ("sunrise", 0.25),
+  ("noon", 0.5),
+  ("sunset", 0.75),
+];
+
+/// Height of the sun, as the sine of its angle above the horizon, around which
+/// the sky fades between night and day.
+const TWILIGHT_HEIGHTS: (f32, f32) = (-0.2, 0.2);
+/// Ambient light at night, relative to full daylight, so that the moon still
+/// lights the world a little.
+const NIGHT_AMBIENT_LIGHT: f32 = 0.2;
+
+/// The time which has passed in a world since it was created.
+///
+/// The sun rises in the +X direction at 06:00, is overhead at 12:00 and sets
+/// in the -X direction at 18:00.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WorldTime {
+  elapsed: Duration,
+}
+
+impl Default for WorldTime {
+  fn default() -> Self {
+    let mut time = Self::from_elapsed(Duration::ZERO);
+    time.set_time_of_day(START_TIME_OF_DAY);
+    time
+  }
+}
+
+impl WorldTime {
+  pub const fn from_elapsed(elapsed: Duration) -> Self {
+    Self { elapsed }
+  }
+
+  /// Returns the time passed in whole milliseconds, as it is saved and sent
+  /// over the network.
+  pub fn as_millis(self) -> u64 {
+    u64::try_from(self.elapsed.as_millis()).unwrap_or(u64::MAX)
+  }
+
+  pub const fn from_millis(millis: u64) -> Self {
+    Self::from_elapsed(Duration::from_millis(millis))
+  }
+
+  pub fn advance(&mut self, by: Duration) {
+    self.elapsed += by;
+  }
+
+  /// Returns the number of whole days which have passed.
+  pub fn day(self) -> u64 {
+    self.elapsed.as_secs() / DAY_LENGTH.as_secs()
+  }
+
+  /// Returns the fraction of the current day which has passed since midnight,
+  /// from 0 to 1.
+  pub fn time_of_day(self) -> f32 {
+    let days = self.elapsed.as_secs_f64() / DAY_LENGTH.as_secs_f64();
+    let fraction: f32 = days.fract().coerce_lossy();
+    fraction
+  }
+
+  /// Changes the time of day, staying on the current day.
+  pub fn set_time_of_day(&mut self, time_of_day: f32) {
+    let day_start = Duration::from_secs(self.day() * DAY_LENGTH.as_secs());
+    self.elapsed = day_start + DAY_LENGTH.mul_f32(time_of_day.clamp(0.0, 1.0));
+  }
+
+  /// Returns the direction from the world towards the sun. The moon is always
+  /// in the opposite direction.
+  pub fn sun_direction(self) -> Vec3 {
+    // The sun is on the horizon at sunrise, a quarter of the way through the day
+    let angle = FULL_ROTATION * (self.time_of_day() - 0.25);
+
+    Vec3::new(angle.cos(), angle.sin(), 0.0)
+  }
+
+  /// Returns how light the sky is, from 0 at night to 1 during the day.
+  pub fn daylight(self) -> f32 {
+    let (night, day) = TWILIGHT_HEIGHTS;
+    let progress = ((self.sun_direction().y() - night) / (day - night)).clamp(0.0, 1.0);
+
+    // Smoothly ease in and out of twilight
+    progress * progress * (3.0 - (2.0 * progress))
+  }
+
+  /// Returns the factor which sky light is scaled by, from
+  /// [`NIGHT_AMBIENT_LIGHT`] at night to 1 during the day.
+  pub fn ambient_light(self) -> f32 {
+    NIGHT_AMBIENT_LIGHT + ((1.0 - NIGHT_AMBIENT_LIGHT) * self.daylight())
+  }
+}
+
+/// Parses a time of day given as `HH:MM` on a 24-hour clock, or as one of
+/// `midnight`, `sunrise`, `noon` or `sunset`, into a fraction of a day since
+/// midnight.
+pub fn parse_time_of_day(text: &str) -> Result<f32> {
+  if let Some(&(_, time_of_day)) = NAMED_TIMES.iter().find(|(name, _)| *name == text) {
+    return Ok(time_of_day);
+  }
+
+  let (hours, minutes) = text
+    .split_once(':')
+    .with_context(|| format!("time {text:?} is not HH:MM or a named time"))?;
+  let (hours, minutes): (u8, u8) = (hours.parse()?, minutes.parse()?);
+  ensure!(
+    (hours < 24) && (minutes < 60),
+    "time {text:?} is not on a 24-hour clock"
+  );
+
+  Ok(((f32::from(hours) * 60.0) + f32::from(minutes)) / (24.0 * 60.0))
+}
+
+/// Formats the time as the day and a 24-hour clock time, such as
+/// `Day 2, 18:30`.
+impl Display for WorldTime {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    let minutes: u32 = (self.time_of_day() * 24.0 * 60.0).coerce_lossy();
+
+    write!(
+      f,
+      "Day {}, {:02}:{:02}",
+      self.day() + 1,
+      minutes / 60,
+      minutes % 60
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Check that the sun rises, peaks and sets at the expected times, and that it
+  // is dark only at night.
+  #[test]
+  fn test_sun_follows_time_of_day() {
+    let mut time = WorldTime::default();
+
+    for (clock, height) in [
+      ("06:00", 0.0),
+      ("noon", 1.0),
+      ("18:00", 0.0),
+      ("00:00", -1.0),
+    ] {
+      time.set_time_of_day(parse_time_of_day(clock).unwrap());
+      assert!((time.sun_direction().y() - height).abs() < 1e-3, "{clock}");
+    }
+
+    time.set_time_of_day(0.5);
+    assert_eq!(1.0, time.daylight());
+    time.set_time_of_day(0.0);
+    assert_eq!(NIGHT_AMBIENT_LIGHT, time.ambient_light());
+  }
+
+  // Check that setting the time of day keeps the day, and that the time is
+  // shown on a 24-hour clock.
+  #[test]
+  fn test_set_time_of_day_keeps_day() {
+    let mut time = WorldTime::from_elapsed((DAY_LENGTH * 3) + (DAY_LENGTH / 2));
+    time.set_time_of_day(parse_time_of_day("18:30").unwrap());
+
+    assert_eq!(3, time.day());
+    assert_eq!("Day 4, 18:30", time.to_string());
+    assert!(parse_time_of_day("24:00").is_err());
+    assert!(parse_time_of_day("dusk").is_err());
+  }
+}